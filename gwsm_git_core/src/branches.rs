@@ -0,0 +1,284 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use crate::git_command::GitCommand;
+
+// The "git branch" (i.e. "git for-each-ref") format that parse_branches() expects
+pub const BRANCH_FORMAT: &str = concat!(
+    "--format=%(HEAD)%00%(refname:short)%00%(objectname:short)",
+    "%00%(upstream:short)%00%(upstream:track,nobracket)",
+    "%00%(committerdate:format:%Y-%m-%d %H:%M)%00%(authorname)%00%(contents:subject)",
+);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Branch {
+    pub name: String,
+    pub is_current: bool,
+    pub is_merged: bool,
+    pub rev: String,
+    pub upstream: Option<String>,
+    pub upstream_gone: bool,
+    pub ahead: u32,
+    pub behind: u32,
+    pub date: String,
+    pub author: String,
+    pub synopsis: String,
+}
+
+// The commits (one per line) that would become unreachable if the branch were
+// deleted i.e. those not on any other branch, tag or remote branch
+pub fn unmerged_commits_cmd(branch: &str) -> GitCommand {
+    // NB: exclusions for "--branches" are given without the "refs/heads/"
+    GitCommand::new("log")
+        .arg("--oneline")
+        .arg(format!("refs/heads/{}", branch))
+        .arg("--not")
+        .arg(format!("--exclude={}", branch))
+        .args(["--branches", "--tags", "--remotes"])
+}
+
+// Parse the "upstream:track,nobracket" field e.g. "ahead 1, behind 2" or "gone"
+fn parse_tracking(text: &str) -> (bool, u32, u32) {
+    let mut ahead = 0;
+    let mut behind = 0;
+    for item in text.split(", ") {
+        match item.split_once(' ') {
+            Some(("ahead", count)) => ahead = count.parse().unwrap_or(0),
+            Some(("behind", count)) => behind = count.parse().unwrap_or(0),
+            _ => (),
+        }
+    }
+    (text == "gone", ahead, behind)
+}
+
+// Parse the output of "git branch" run with BRANCH_FORMAT using that of
+// "git branch --merged" to determine which branches have been merged into HEAD.
+pub fn parse_branches(all_branches_text: &str, merged_branches_text: &str) -> Vec<Branch> {
+    let merged_set: HashSet<&str> = merged_branches_text
+        .lines()
+        .filter_map(|line| line.get(2..))
+        .map(|name| name.trim_end())
+        .collect();
+    let mut branches = vec![];
+    for line in all_branches_text.lines() {
+        let fields: Vec<&str> = line.splitn(8, '\0').collect();
+        if fields.len() < 8 {
+            continue;
+        }
+        let (upstream_gone, ahead, behind) = parse_tracking(fields[4]);
+        branches.push(Branch {
+            name: fields[1].to_string(),
+            is_current: fields[0] == "*",
+            is_merged: merged_set.contains(fields[1]),
+            rev: fields[2].to_string(),
+            upstream: if fields[3].is_empty() {
+                None
+            } else {
+                Some(fields[3].to_string())
+            },
+            upstream_gone,
+            ahead,
+            behind,
+            date: fields[5].to_string(),
+            author: fields[6].to_string(),
+            synopsis: fields[7].to_string(),
+        });
+    }
+    branches
+}
+
+// The "git for-each-ref refs/remotes" format that parse_remote_branches() expects
+pub const REMOTE_BRANCH_FORMAT: &str =
+    "--format=%(refname)%00%(symref)%00%(objectname:short)%00%(contents:subject)";
+
+// The "git for-each-ref refs/heads" format that parse_remote_branches() expects
+pub const LOCAL_UPSTREAM_FORMAT: &str = "--format=%(refname:lstrip=2)%00%(upstream)";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RemoteBranch {
+    pub remote: String,
+    pub branch: String,
+    pub rev: String,
+    pub synopsis: String,
+    pub local: Option<String>,
+}
+
+impl RemoteBranch {
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.remote, self.branch)
+    }
+}
+
+// Parse the output of "git for-each-ref refs/remotes" run with REMOTE_BRANCH_FORMAT
+// grouped (and sorted) by remote.  The output of "git remote" is needed to
+// split names as remote names may contain '/' and that of "git for-each-ref
+// refs/heads" run with LOCAL_UPSTREAM_FORMAT to find each remote branch's
+// local counterpart i.e. the branch tracking it (preferring one with the same
+// name) or, failing that, the local branch with the same name.
+pub fn parse_remote_branches(
+    remote_refs_text: &str,
+    remotes_text: &str,
+    local_branches_text: &str,
+) -> Vec<RemoteBranch> {
+    let remotes: Vec<&str> = remotes_text.lines().map(|line| line.trim()).collect();
+    let locals: Vec<(&str, &str)> = local_branches_text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(2, '\0');
+            Some((fields.next()?, fields.next().unwrap_or("")))
+        })
+        .collect();
+    let mut remote_branches = vec![];
+    for line in remote_refs_text.lines() {
+        let fields: Vec<&str> = line.splitn(4, '\0').collect();
+        if fields.len() < 4 || !fields[1].is_empty() {
+            // skip symbolic refs such as "origin/HEAD"
+            continue;
+        }
+        let refname = fields[0];
+        let name = match refname.strip_prefix("refs/remotes/") {
+            Some(name) => name,
+            None => continue,
+        };
+        let (remote, branch) = match remotes
+            .iter()
+            .filter(|remote| {
+                name.len() > remote.len()
+                    && name.starts_with(*remote)
+                    && name[remote.len()..].starts_with('/')
+            })
+            .max_by_key(|remote| remote.len())
+        {
+            Some(remote) => (*remote, &name[remote.len() + 1..]),
+            None => match name.find('/') {
+                Some(index) => (&name[..index], &name[index + 1..]),
+                None => continue,
+            },
+        };
+        let trackers: Vec<&str> = locals
+            .iter()
+            .filter(|(_, upstream)| *upstream == refname)
+            .map(|(local, _)| *local)
+            .collect();
+        let local = trackers
+            .iter()
+            .find(|local| **local == branch)
+            .or_else(|| trackers.first())
+            .or_else(|| {
+                locals
+                    .iter()
+                    .map(|(local, _)| local)
+                    .find(|local| **local == branch)
+            })
+            .map(|local| local.to_string());
+        remote_branches.push(RemoteBranch {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+            rev: fields[2].to_string(),
+            synopsis: fields[3].to_string(),
+            local,
+        });
+    }
+    remote_branches.sort_by(|a, b| (&a.remote, &a.branch).cmp(&(&b.remote, &b.branch)));
+    remote_branches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_branches_works() {
+        let all_branches_text = concat!(
+            " \0feature\01a2b3c4\0origin/feature\0ahead 1, behind 2\02019-12-01 10:30\0Jo Bloggs\0Add the feature\n",
+            "*\0master\05d6e7f8\0origin/master\0\02019-12-02 09:00\0Peter\0Merge branch 'fix'\n",
+            " \0old-fix\09a8b7c6\0origin/old-fix\0gone\02019-11-30 23:59\0Peter\0Fix: the thing\n",
+            " \0(HEAD detached at 9a8b7c6)\09a8b7c6\0\0\02019-11-30 23:59\0Peter\0Fix: the thing\n",
+        );
+        let merged_branches_text = "* master\n  old-fix\n";
+        let branches = parse_branches(all_branches_text, merged_branches_text);
+        assert_eq!(branches.len(), 4);
+        assert_eq!(
+            branches[0],
+            Branch {
+                name: "feature".to_string(),
+                is_current: false,
+                is_merged: false,
+                rev: "1a2b3c4".to_string(),
+                upstream: Some("origin/feature".to_string()),
+                upstream_gone: false,
+                ahead: 1,
+                behind: 2,
+                date: "2019-12-01 10:30".to_string(),
+                author: "Jo Bloggs".to_string(),
+                synopsis: "Add the feature".to_string(),
+            }
+        );
+        assert!(branches[1].is_current && branches[1].is_merged);
+        assert_eq!(branches[1].name, "master");
+        assert_eq!((branches[1].ahead, branches[1].behind), (0, 0));
+        assert!(!branches[2].is_current && branches[2].is_merged);
+        assert!(branches[2].upstream_gone);
+        assert_eq!(branches[2].synopsis, "Fix: the thing");
+        assert_eq!(branches[3].name, "(HEAD detached at 9a8b7c6)");
+        assert_eq!(branches[3].upstream, None);
+        assert!(parse_branches("", "").is_empty());
+    }
+
+    #[test]
+    fn parse_tracking_works() {
+        assert_eq!(parse_tracking(""), (false, 0, 0));
+        assert_eq!(parse_tracking("ahead 3"), (false, 3, 0));
+        assert_eq!(parse_tracking("behind 12"), (false, 0, 12));
+        assert_eq!(parse_tracking("ahead 1, behind 2"), (false, 1, 2));
+        assert_eq!(parse_tracking("gone"), (true, 0, 0));
+    }
+
+    #[test]
+    fn parse_remote_branches_works() {
+        let remote_refs_text = concat!(
+            "refs/remotes/my/fork/HEAD\0refs/remotes/my/fork/master\01a2b3c4\0Fork work\n",
+            "refs/remotes/my/fork/master\0\01a2b3c4\0Fork work\n",
+            "refs/remotes/origin/feature/x\0\05d6e7f8\0Add x\n",
+            "refs/remotes/origin/master\0\09a8b7c6\0Initial: commit\n",
+        );
+        let remotes_text = "my/fork\norigin\n";
+        let local_branches_text = concat!(
+            "master\0refs/remotes/origin/master\n",
+            "mine\0refs/remotes/my/fork/master\n",
+            "feature/x\0\n",
+        );
+        let remote_branches =
+            parse_remote_branches(remote_refs_text, remotes_text, local_branches_text);
+        assert_eq!(remote_branches.len(), 3);
+        assert_eq!(
+            remote_branches[0],
+            RemoteBranch {
+                remote: "my/fork".to_string(),
+                branch: "master".to_string(),
+                rev: "1a2b3c4".to_string(),
+                synopsis: "Fork work".to_string(),
+                local: Some("mine".to_string()),
+            }
+        );
+        assert_eq!(remote_branches[0].name(), "my/fork/master");
+        assert_eq!(remote_branches[1].name(), "origin/feature/x");
+        assert_eq!(remote_branches[1].local.as_deref(), Some("feature/x"));
+        assert_eq!(remote_branches[2].synopsis, "Initial: commit");
+        assert_eq!(remote_branches[2].local.as_deref(), Some("master"));
+        assert!(parse_remote_branches("", "", "").is_empty());
+    }
+}
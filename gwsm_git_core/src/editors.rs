@@ -0,0 +1,156 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::convert::From;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use glob::{Pattern, PatternError};
+
+// The editor assignment table is an ordered list of (globs, editor command)
+// pairs stored as JSON.  The first entry with a glob matching a file wins.
+
+#[derive(Debug)]
+pub enum EditorTableError {
+    InputOutput(io::Error),
+    GlobPattern(PatternError),
+    SerdeJson(serde_json::Error),
+}
+
+impl fmt::Display for EditorTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditorTableError::InputOutput(err) => {
+                write!(f, "I/O Error accessing editor assignment table: {}", err)
+            }
+            EditorTableError::GlobPattern(err) => write!(
+                f,
+                "Glob Pattern Error accessing editor assignment table: {}",
+                err
+            ),
+            EditorTableError::SerdeJson(err) => write!(
+                f,
+                "Serde Json Error accessing editor assignment table: {}",
+                err
+            ),
+        }
+    }
+}
+
+impl Error for EditorTableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EditorTableError::InputOutput(err) => Some(err),
+            EditorTableError::GlobPattern(err) => Some(err),
+            EditorTableError::SerdeJson(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for EditorTableError {
+    fn from(error: io::Error) -> Self {
+        EditorTableError::InputOutput(error)
+    }
+}
+
+impl From<PatternError> for EditorTableError {
+    fn from(error: PatternError) -> Self {
+        EditorTableError::GlobPattern(error)
+    }
+}
+
+impl From<serde_json::Error> for EditorTableError {
+    fn from(error: serde_json::Error) -> Self {
+        EditorTableError::SerdeJson(error)
+    }
+}
+
+#[cfg(target_family = "unix")]
+const PATH_SEP: char = ':';
+#[cfg(target_os = "windows")]
+const PATH_SEP: char = ';';
+
+pub fn read_editor_assignment_table(
+    file_path: &Path,
+) -> Result<Vec<(String, String)>, EditorTableError> {
+    let mut file = File::open(file_path)?;
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+    let v: Vec<(String, String)> = serde_json::from_str(&buffer)?;
+    Ok(v)
+}
+
+pub fn write_editor_assignment_table(
+    file_path: &Path,
+    table: &[(String, String)],
+) -> Result<usize, EditorTableError> {
+    let data = serde_json::to_string(table)?;
+    let mut file = File::create(file_path)?;
+    let nbytes = file.write(data.as_bytes())?;
+    Ok(nbytes)
+}
+
+// The editor assigned to the file (if any)
+pub fn find_assigned_editor<'a>(
+    table: &'a [(String, String)],
+    file_path: &str,
+) -> Result<Option<&'a str>, EditorTableError> {
+    for (globs, editor) in table.iter() {
+        for glob in globs.split(PATH_SEP) {
+            let pattern = Pattern::new(glob)?;
+            if pattern.matches(file_path) {
+                return Ok(Some(editor));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editor_assignment_table_works() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("editor_assignment_table");
+        let table = vec![
+            (format!("*.rs{}*.toml", PATH_SEP), "gvim".to_string()),
+            ("*".to_string(), "gedit".to_string()),
+        ];
+        write_editor_assignment_table(&file_path, &table).unwrap();
+        let table = read_editor_assignment_table(&file_path).unwrap();
+        assert_eq!(
+            find_assigned_editor(&table, "src/main.rs").unwrap(),
+            Some("gvim")
+        );
+        assert_eq!(
+            find_assigned_editor(&table, "Cargo.toml").unwrap(),
+            Some("gvim")
+        );
+        assert_eq!(
+            find_assigned_editor(&table, "README.md").unwrap(),
+            Some("gedit")
+        );
+        assert_eq!(
+            find_assigned_editor(&table[..1], "README.md").unwrap(),
+            None
+        );
+        let bad_table = vec![("[".to_string(), "gvim".to_string())];
+        assert!(find_assigned_editor(&bad_table, "x").is_err());
+    }
+}
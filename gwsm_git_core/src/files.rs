@@ -0,0 +1,79 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Commands that act on files in the work tree and/or the index.  Paths are
+// always preceded by "--" so that they can't be mistaken for options.
+
+use std::path::Path;
+
+use crate::git_command::GitCommand;
+
+pub fn add_cmd<S: AsRef<str>>(paths: &[S]) -> GitCommand {
+    GitCommand::new("add").arg("--").args(paths)
+}
+
+pub fn unstage_cmd<S: AsRef<str>>(paths: &[S]) -> GitCommand {
+    GitCommand::new("reset").args(["HEAD", "--"]).args(paths)
+}
+
+// Discard the work tree's (unstaged) changes
+pub fn discard_changes_cmd<S: AsRef<str>>(paths: &[S]) -> GitCommand {
+    GitCommand::new("checkout").arg("--").args(paths)
+}
+
+pub fn move_cmd(path: &str, target: &str) -> GitCommand {
+    GitCommand::new("mv").arg("--").arg(path).arg(target)
+}
+
+// Remove from the index but leave the work tree alone
+pub fn remove_from_index_cmd<S: AsRef<str>>(paths: &[S]) -> GitCommand {
+    GitCommand::new("rm")
+        .args(["-r", "--cached", "--"])
+        .args(paths)
+}
+
+// Apply a patch (e.g. chosen hunks) to the index without touching the work
+// tree.  "reverse" is for taking changes back out of the index.
+pub fn apply_to_index_cmd(patch_path: &Path, reverse: bool) -> GitCommand {
+    GitCommand::new("apply")
+        .args(["--cached", "--recount"])
+        .arg_if(reverse, "-R")
+        .arg(patch_path.to_string_lossy())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+// Resolve a conflict by taking one side's version of the file.  If that
+// side deleted the file then taking it means removing the file.
+pub fn take_side_cmds(path: &str, side: Side, side_deleted: bool) -> Vec<GitCommand> {
+    if side_deleted {
+        vec![GitCommand::new("rm").arg("--").arg(path)]
+    } else {
+        let side_option = match side {
+            Side::Ours => "--ours",
+            Side::Theirs => "--theirs",
+        };
+        vec![
+            GitCommand::new("checkout")
+                .arg(side_option)
+                .arg("--")
+                .arg(path),
+            add_cmd(&[path]),
+        ]
+    }
+}
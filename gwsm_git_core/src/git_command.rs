@@ -0,0 +1,235 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::process::Command;
+
+use crate::workspace::WsPath;
+
+// A git command line held as an argument vector so that arguments (branch
+// names, paths, messages, etc.) reach git exactly as given and are never
+// re-parsed by a shell lexer.  The Display form is for the transaction log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitCommand {
+    envs: Vec<(String, String)>,
+    args: Vec<String>,
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+            chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+        _ => false,
+    }
+}
+
+impl GitCommand {
+    pub fn new(sub_cmd: &str) -> Self {
+        Self {
+            envs: vec![],
+            args: vec![sub_cmd.to_string()],
+        }
+    }
+
+    // Recover a command from an argument vector (including the leading "git"
+    // and any "NAME=value" environment settings preceding it)
+    pub fn from_argv(argv: &[String]) -> Option<Self> {
+        let mut envs = vec![];
+        let mut rest = argv;
+        while let Some((first, tail)) = rest.split_first() {
+            match first.find('=') {
+                Some(index) if is_env_name(&first[..index]) => {
+                    envs.push((first[..index].to_string(), first[index + 1..].to_string()));
+                    rest = tail;
+                }
+                _ => break,
+            }
+        }
+        match rest.split_first() {
+            Some((program, args)) if program == "git" && !args.is_empty() => Some(Self {
+                envs,
+                args: args.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+
+    // Set an environment variable for git.  Unlike a "-c" setting this takes
+    // precedence over any value the user has exported (e.g. GIT_EDITOR).
+    pub fn env(mut self, name: &str, value: &str) -> Self {
+        debug_assert!(is_env_name(name));
+        self.envs.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Add a "-c name=value" option (which must precede the sub command)
+    pub fn config(mut self, setting: &str) -> Self {
+        self.args
+            .splice(0..0, vec!["-c".to_string(), setting.to_string()]);
+        self
+    }
+
+    pub fn arg<S: AsRef<str>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    pub fn arg_if<S: AsRef<str>>(self, condition: bool, arg: S) -> Self {
+        if condition {
+            self.arg(arg)
+        } else {
+            self
+        }
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
+    // Any environment settings come first (as they would in a shell)
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv: Vec<String> = self
+            .envs
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        argv.push("git".to_string());
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+
+    pub fn command(&self, ws_path: &WsPath) -> Command {
+        let mut cmd = ws_path.git();
+        cmd.envs(self.envs.iter().map(|(name, value)| (name, value)));
+        cmd.args(&self.args);
+        cmd
+    }
+}
+
+// Only the values of environment settings are quoted as a quoted name would
+// stop the shell treating the word as an assignment.
+impl fmt::Display for GitCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in self.envs.iter() {
+            match shlex::try_quote(value) {
+                Ok(quoted) => write!(f, "{}={} ", name, quoted)?,
+                Err(_) => write!(f, "{}={:?} ", name, value)?,
+            }
+        }
+        write!(f, "git")?;
+        for arg in self.args.iter() {
+            match shlex::try_quote(arg) {
+                Ok(quoted) => write!(f, " {}", quoted)?,
+                Err(_) => write!(f, " {:?}", arg)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// A command to be replayed (in its original directory) by a shell script.
+// Failed commands are included for the record but only as comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptStep {
+    pub cwd: String,
+    pub cmd: GitCommand,
+    pub failure: Option<String>,
+}
+
+// Every line of the heading and of failed commands (whose arguments may
+// contain newlines) is commented out so that none of it can be executed.
+pub fn replay_script(heading: &str, steps: &[ScriptStep]) -> String {
+    let mut script = "#!/bin/sh\n".to_string();
+    for line in heading.lines() {
+        script += &format!("# {}\n", line);
+    }
+    let mut cwd: Option<&str> = None;
+    for step in steps.iter() {
+        if cwd != Some(&step.cwd) {
+            cwd = Some(&step.cwd);
+            let quoted = shlex::try_quote(&step.cwd).unwrap_or_else(|_| step.cwd.as_str().into());
+            script += &format!("cd {} || exit 1\n", quoted);
+        }
+        match step.failure {
+            None => script += &format!("{}\n", step.cmd),
+            Some(ref failure) => {
+                let text = format!("[{}] {}", failure, step.cmd);
+                for line in text.lines() {
+                    script += &format!("# {}\n", line);
+                }
+            }
+        }
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn git_command_works() {
+        let cmd = GitCommand::new("commit")
+            .arg_if(true, "--signoff")
+            .arg_if(false, "--amend")
+            .arg("-m")
+            .arg("it's \"done\"");
+        assert_eq!(
+            cmd.argv(),
+            vec!["git", "commit", "--signoff", "-m", "it's \"done\""]
+        );
+        assert_eq!(
+            cmd.to_string(),
+            r#"git commit --signoff -m "it's \"done\"""#
+        );
+        assert_eq!(GitCommand::from_argv(&cmd.argv()), Some(cmd));
+        let cmd = GitCommand::new("add").arg("--").args(["a b", "'c"]);
+        assert_eq!(cmd.argv(), vec!["git", "add", "--", "a b", "'c"]);
+        let cmd = GitCommand::new("merge")
+            .config("core.editor=true")
+            .arg("--continue");
+        assert_eq!(
+            cmd.argv(),
+            vec!["git", "-c", "core.editor=true", "merge", "--continue"]
+        );
+        let cmd = GitCommand::new("rebase")
+            .env("GIT_EDITOR", "cp 'a b'")
+            .arg("--continue");
+        assert_eq!(
+            cmd.argv(),
+            vec!["GIT_EDITOR=cp 'a b'", "git", "rebase", "--continue"]
+        );
+        assert_eq!(
+            cmd.to_string(),
+            r#"GIT_EDITOR="cp 'a b'" git rebase --continue"#
+        );
+        assert_eq!(GitCommand::from_argv(&cmd.argv()), Some(cmd.clone()));
+        let split = shlex::split(&cmd.to_string()).unwrap();
+        assert_eq!(GitCommand::from_argv(&split), Some(cmd));
+        assert_eq!(GitCommand::from_argv(&["meld".to_string()]), None);
+        assert_eq!(
+            GitCommand::from_argv(&["GIT_EDITOR=true".to_string(), "meld".to_string()]),
+            None
+        );
+        assert_eq!(GitCommand::from_argv(&["git".to_string()]), None);
+    }
+}
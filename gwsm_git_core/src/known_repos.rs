@@ -0,0 +1,111 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+// The known repos table is a sorted list of (name, path) pairs stored as JSON
+
+#[derive(Debug)]
+pub enum KRTError {
+    IOError(io::Error),
+    JsonError(serde_json::Error),
+}
+
+impl fmt::Display for KRTError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KRTError::IOError(err) => write!(f, "I/O Error accessing known repos table: {}", err),
+            KRTError::JsonError(err) => {
+                write!(f, "Serde Json Error accessing known repos table: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for KRTError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KRTError::IOError(err) => Some(err),
+            KRTError::JsonError(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for KRTError {
+    fn from(error: io::Error) -> Self {
+        KRTError::IOError(error)
+    }
+}
+
+impl From<serde_json::Error> for KRTError {
+    fn from(error: serde_json::Error) -> Self {
+        KRTError::JsonError(error)
+    }
+}
+
+pub fn read_known_repos_table(file_path: &Path) -> Result<Vec<(String, String)>, KRTError> {
+    let mut file = File::open(file_path)?;
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
+    let v: Vec<(String, String)> = serde_json::from_str(&buffer)?;
+    Ok(v)
+}
+
+pub fn write_known_repos_table(
+    file_path: &Path,
+    table: &[(String, String)],
+) -> Result<usize, KRTError> {
+    let data = serde_json::to_string(table)?;
+    let mut file = File::create(file_path)?;
+    let nbytes = file.write(data.as_bytes())?;
+    Ok(nbytes)
+}
+
+// Insert the entry in order (if it isn't already there)
+pub fn insert_known_repo(table: &mut Vec<(String, String)>, name: &str, repo_path: &str) {
+    let new_entry = (name.to_string(), repo_path.to_string());
+    if let Err(insert_index) = table.binary_search(&new_entry) {
+        table.insert(insert_index, new_entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_repos_table_works() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("known_repos_table");
+        assert!(read_known_repos_table(&file_path).is_err());
+        write_known_repos_table(&file_path, &[]).unwrap();
+        let mut table = read_known_repos_table(&file_path).unwrap();
+        assert!(table.is_empty());
+        insert_known_repo(&mut table, "zed", "/home/me/SRC/zed");
+        insert_known_repo(&mut table, "alpha", "/home/me/SRC/alpha");
+        insert_known_repo(&mut table, "zed", "/home/me/SRC/zed");
+        write_known_repos_table(&file_path, &table).unwrap();
+        assert_eq!(
+            read_known_repos_table(&file_path).unwrap(),
+            vec![
+                ("alpha".to_string(), "/home/me/SRC/alpha".to_string()),
+                ("zed".to_string(), "/home/me/SRC/zed".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// GUI-free models of git's state and the parsers that extract them from
+// the output of git commands.
+
+#[macro_use]
+extern crate lazy_static;
+
+pub mod branches;
+pub mod editors;
+pub mod files;
+pub mod git_command;
+pub mod known_repos;
+pub mod merge;
+pub mod rebase;
+pub mod remotes;
+pub mod stashes;
+pub mod status;
+pub mod tags;
+pub mod workspace;
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
@@ -0,0 +1,114 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::git_command::GitCommand;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FastForward {
+    #[default]
+    Default,
+    Never,
+    Only,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MergeOptions {
+    pub fast_forward: FastForward,
+    pub squash: bool,
+    pub no_commit: bool,
+    pub strategy: Option<String>,
+    pub strategy_option: Option<String>,
+    pub message: Option<String>,
+}
+
+impl MergeOptions {
+    // The message is only used when the merge is to create a merge commit
+    pub fn uses_message(&self) -> bool {
+        !(self.squash || self.no_commit || self.fast_forward == FastForward::Only)
+    }
+
+    pub fn command(&self, branch: &str) -> GitCommand {
+        let mut cmd = GitCommand::new("merge")
+            .arg_if(self.fast_forward == FastForward::Never, "--no-ff")
+            .arg_if(self.fast_forward == FastForward::Only, "--ff-only")
+            .arg_if(self.squash, "--squash")
+            .arg_if(self.no_commit, "--no-commit");
+        if let Some(ref strategy) = self.strategy {
+            cmd = cmd.arg("--strategy").arg(strategy);
+        }
+        if let Some(ref strategy_option) = self.strategy_option {
+            cmd = cmd.arg("--strategy-option").arg(strategy_option);
+        }
+        if self.uses_message() {
+            if let Some(ref message) = self.message {
+                cmd = cmd.arg("-m").arg(message);
+            }
+        }
+        cmd.arg(branch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_command_works() {
+        assert_eq!(
+            MergeOptions::default().command("topic").argv(),
+            vec!["git", "merge", "topic"]
+        );
+        let options = MergeOptions {
+            fast_forward: FastForward::Never,
+            strategy: Some("recursive".to_string()),
+            strategy_option: Some("theirs".to_string()),
+            message: Some("Merge it\n\nBecause".to_string()),
+            ..MergeOptions::default()
+        };
+        assert_eq!(
+            options.command("topic").argv(),
+            vec![
+                "git",
+                "merge",
+                "--no-ff",
+                "--strategy",
+                "recursive",
+                "--strategy-option",
+                "theirs",
+                "-m",
+                "Merge it\n\nBecause",
+                "topic"
+            ]
+        );
+        let options = MergeOptions {
+            fast_forward: FastForward::Only,
+            message: Some("Ignored".to_string()),
+            ..MergeOptions::default()
+        };
+        assert_eq!(
+            options.command("topic").argv(),
+            vec!["git", "merge", "--ff-only", "topic"]
+        );
+        let options = MergeOptions {
+            squash: true,
+            no_commit: true,
+            message: Some("Ignored".to_string()),
+            ..MergeOptions::default()
+        };
+        assert_eq!(
+            options.command("topic").argv(),
+            vec!["git", "merge", "--squash", "--no-commit", "topic"]
+        );
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::git_command::GitCommand;
+use crate::workspace::WsPath;
+
+// The "git log" format that parse_rebase_commits() expects
+pub const REBASE_COMMITS_FORMAT: &str = "--format=%h%x00%s";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RebaseAction {
+    Pick,
+    Reword,
+    Edit,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    pub const ALL: [RebaseAction; 6] = [
+        RebaseAction::Pick,
+        RebaseAction::Reword,
+        RebaseAction::Edit,
+        RebaseAction::Squash,
+        RebaseAction::Fixup,
+        RebaseAction::Drop,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RebaseAction::Pick => "pick",
+            RebaseAction::Reword => "reword",
+            RebaseAction::Edit => "edit",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RebaseAction> {
+        RebaseAction::ALL
+            .iter()
+            .find(|action| action.name() == name)
+            .copied()
+    }
+}
+
+// One line of an interactive rebase's plan.  A reworded commit's new message
+// is applied by an "exec git commit --amend" line as there is nobody to type
+// it into git's editor.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RebaseStep {
+    pub action: RebaseAction,
+    pub commit: String,
+    pub subject: String,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RebasePlanError {
+    NothingToDo,
+    NoPreviousCommit(String),
+}
+
+impl fmt::Display for RebasePlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebasePlanError::NothingToDo => write!(f, "The rebase plan has no commits to keep"),
+            RebasePlanError::NoPreviousCommit(commit) => write!(
+                f,
+                "Commit {} can't be combined with a previous commit as there isn't one",
+                commit
+            ),
+        }
+    }
+}
+
+impl Error for RebasePlanError {}
+
+// Parse the output of "git log --reverse" run with REBASE_COMMITS_FORMAT
+// into a plan that (by default) picks every commit
+pub fn parse_rebase_commits(text: &str) -> Vec<RebaseStep> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(2, '\0');
+            let commit = fields.next().filter(|commit| !commit.is_empty())?;
+            Some(RebaseStep {
+                action: RebaseAction::Pick,
+                commit: commit.to_string(),
+                subject: fields.next().unwrap_or("").to_string(),
+                message: None,
+            })
+        })
+        .collect()
+}
+
+pub fn check_rebase_plan(steps: &[RebaseStep]) -> Result<(), RebasePlanError> {
+    match steps.iter().find(|step| step.action != RebaseAction::Drop) {
+        None => Err(RebasePlanError::NothingToDo),
+        Some(step) if step.action == RebaseAction::Squash || step.action == RebaseAction::Fixup => {
+            Err(RebasePlanError::NoPreviousCommit(step.commit.clone()))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+fn shell_quote(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    match shlex::try_quote(&text) {
+        Ok(quoted) => quoted.to_string(),
+        Err(_) => format!("{:?}", text),
+    }
+}
+
+// The commits that "git rebase -i <upstream>" would put in its plan (oldest
+// first): merges are dropped and so are commits already applied upstream
+pub fn rebase_commits_cmd(upstream: &str) -> GitCommand {
+    GitCommand::new("log")
+        .arg("--reverse")
+        .arg("--no-merges")
+        .arg("--cherry-pick")
+        .arg("--right-only")
+        .arg(REBASE_COMMITS_FORMAT)
+        .arg(format!("{}...HEAD", upstream))
+}
+
+// Write the plan (and the files holding any new messages) into "dir" in the
+// form of a "git-rebase-todo" file and return the todo file's path.
+pub fn write_rebase_plan(steps: &[RebaseStep], dir: &Path) -> io::Result<PathBuf> {
+    remove_rebase_plan(dir)?;
+    fs::create_dir_all(dir)?;
+    let mut todo = String::new();
+    for (index, step) in steps.iter().enumerate() {
+        todo.push_str(&format!(
+            "{} {} {}\n",
+            step.action.name(),
+            step.commit,
+            step.subject
+        ));
+        if let (RebaseAction::Reword, Some(message)) = (step.action, &step.message) {
+            let message_path = dir.join(format!("message-{}", index));
+            fs::write(&message_path, message)?;
+            todo.push_str(&format!(
+                "exec git commit --amend --only --allow-empty -q -F {}\n",
+                shell_quote(&message_path)
+            ));
+        }
+    }
+    let todo_path = dir.join("git-rebase-todo");
+    fs::write(&todo_path, todo)?;
+    Ok(todo_path)
+}
+
+// A place in the repository's git directory for the plan files which must
+// outlast any stops (e.g. for "edit" or conflicts) during the rebase
+pub fn rebase_plan_dir(ws_path: &WsPath) -> Option<PathBuf> {
+    let output = ws_path
+        .git()
+        .arg("rev-parse")
+        .arg("--git-path")
+        .arg("gwsm-rebase")
+        .output()
+        .ok()?;
+    if output.status.success() {
+        let text = String::from_utf8_lossy(&output.stdout);
+        Some(ws_path.path().join(text.trim_end()))
+    } else {
+        None
+    }
+}
+
+// Once the rebase has finished (or been aborted) the plan files are litter
+pub fn remove_rebase_plan(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+// Run "git rebase -i" with a stand-in GIT_SEQUENCE_EDITOR that replaces git's
+// plan with ours.  GIT_EDITOR is overridden so that squashed commits' combined
+// messages are accepted rather than waiting for an editor the user can't see.
+pub fn interactive_rebase_cmd(upstream: &str, todo_path: &Path) -> GitCommand {
+    GitCommand::new("rebase")
+        .env(
+            "GIT_SEQUENCE_EDITOR",
+            &format!("cp {}", shell_quote(todo_path)),
+        )
+        .env("GIT_EDITOR", "true")
+        .arg("--interactive")
+        .arg(upstream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(action: RebaseAction, commit: &str) -> RebaseStep {
+        RebaseStep {
+            action,
+            commit: commit.to_string(),
+            subject: format!("Subject of {}", commit),
+            message: None,
+        }
+    }
+
+    #[test]
+    fn rebase_action_names_work() {
+        for action in RebaseAction::ALL.iter() {
+            assert_eq!(RebaseAction::from_name(action.name()), Some(*action));
+        }
+        assert_eq!(RebaseAction::from_name("exec"), None);
+    }
+
+    #[test]
+    fn parse_rebase_commits_works() {
+        let steps = parse_rebase_commits("1a2b3c4\0First: one\n5d6e7f8\0\n\n");
+        assert_eq!(
+            steps,
+            vec![
+                RebaseStep {
+                    action: RebaseAction::Pick,
+                    commit: "1a2b3c4".to_string(),
+                    subject: "First: one".to_string(),
+                    message: None,
+                },
+                RebaseStep {
+                    action: RebaseAction::Pick,
+                    commit: "5d6e7f8".to_string(),
+                    subject: "".to_string(),
+                    message: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_rebase_plan_works() {
+        assert_eq!(check_rebase_plan(&[]), Err(RebasePlanError::NothingToDo));
+        assert_eq!(
+            check_rebase_plan(&[step(RebaseAction::Drop, "a1")]),
+            Err(RebasePlanError::NothingToDo)
+        );
+        assert_eq!(
+            check_rebase_plan(&[
+                step(RebaseAction::Drop, "a1"),
+                step(RebaseAction::Fixup, "b2")
+            ]),
+            Err(RebasePlanError::NoPreviousCommit("b2".to_string()))
+        );
+        assert_eq!(
+            check_rebase_plan(&[
+                step(RebaseAction::Edit, "a1"),
+                step(RebaseAction::Squash, "b2")
+            ]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rebase_commits_cmd_works() {
+        assert_eq!(
+            rebase_commits_cmd("master").argv(),
+            vec![
+                "git",
+                "log",
+                "--reverse",
+                "--no-merges",
+                "--cherry-pick",
+                "--right-only",
+                REBASE_COMMITS_FORMAT,
+                "master...HEAD"
+            ]
+        );
+    }
+
+    #[test]
+    fn interactive_rebase_cmd_works() {
+        let cmd = interactive_rebase_cmd("master", Path::new("/tmp/a dir/git-rebase-todo"));
+        assert_eq!(
+            cmd.argv(),
+            vec![
+                "GIT_SEQUENCE_EDITOR=cp '/tmp/a dir/git-rebase-todo'",
+                "GIT_EDITOR=true",
+                "git",
+                "rebase",
+                "--interactive",
+                "master"
+            ]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use regex::Regex;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Remote {
+    pub name: String,
+    pub inbound_url: String,
+    pub outbound_url: String,
+}
+
+lazy_static! {
+    static ref VREMOTE_RE: Regex = Regex::new(r"(\S+)\s+(\S+)\s*(\S*)").unwrap();
+}
+
+// Parse the output of "git remote -v" which has a "(fetch)" line followed by
+// a "(push)" line for each remote
+pub fn parse_remotes(text: &str) -> Vec<Remote> {
+    let mut remotes: Vec<Remote> = vec![];
+    for captures in text.lines().filter_map(|line| VREMOTE_RE.captures(line)) {
+        let name = captures.get(1).unwrap().as_str();
+        let url = captures.get(2).unwrap().as_str();
+        if remotes.last().is_none_or(|remote| remote.name != name) {
+            remotes.push(Remote {
+                name: name.to_string(),
+                ..Remote::default()
+            });
+        }
+        let remote = remotes.last_mut().unwrap();
+        if captures.get(3).unwrap().as_str() == "(push)" {
+            remote.outbound_url = url.to_string();
+        } else {
+            remote.inbound_url = url.to_string();
+        }
+    }
+    remotes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_remotes_works() {
+        let text = concat!(
+            "origin\thttps://github.com/pwil3058/rs_gwsm_git.git (fetch)\n",
+            "origin\tgit@github.com:pwil3058/rs_gwsm_git.git (push)\n",
+            "upstream\t/srv/git/gwsm_git.git (fetch)\n",
+            "upstream\t/srv/git/gwsm_git.git (push)\n",
+        );
+        assert_eq!(
+            parse_remotes(text),
+            vec![
+                Remote {
+                    name: "origin".to_string(),
+                    inbound_url: "https://github.com/pwil3058/rs_gwsm_git.git".to_string(),
+                    outbound_url: "git@github.com:pwil3058/rs_gwsm_git.git".to_string(),
+                },
+                Remote {
+                    name: "upstream".to_string(),
+                    inbound_url: "/srv/git/gwsm_git.git".to_string(),
+                    outbound_url: "/srv/git/gwsm_git.git".to_string(),
+                },
+            ]
+        );
+        assert!(parse_remotes("").is_empty());
+    }
+}
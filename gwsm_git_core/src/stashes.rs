@@ -0,0 +1,68 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use regex::Regex;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stash {
+    pub name: String,
+    pub branch: String,
+    pub commit: String,
+}
+
+lazy_static! {
+    static ref STASH_RE: Regex =
+        Regex::new(r"^(stash@\{\d+\}):\s*([^:]+):(.*)").expect("STASH regex creation failed");
+}
+
+// Parse the output of "git stash list"
+pub fn parse_stashes(text: &str) -> Vec<Stash> {
+    text.lines()
+        .filter_map(|line| STASH_RE.captures(line))
+        .map(|captures| Stash {
+            name: captures.get(1).unwrap().as_str().to_string(),
+            branch: captures.get(2).unwrap().as_str().to_string(),
+            commit: captures.get(3).unwrap().as_str().to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stashes_works() {
+        let text = concat!(
+            "stash@{0}: WIP on master: 1a2b3c4 Add the feature\n",
+            "stash@{1}: On topic: keep: this for later\n",
+        );
+        assert_eq!(
+            parse_stashes(text),
+            vec![
+                Stash {
+                    name: "stash@{0}".to_string(),
+                    branch: "WIP on master".to_string(),
+                    commit: " 1a2b3c4 Add the feature".to_string(),
+                },
+                Stash {
+                    name: "stash@{1}".to_string(),
+                    branch: "On topic".to_string(),
+                    commit: " keep: this for later".to_string(),
+                },
+            ]
+        );
+        assert!(parse_stashes("").is_empty());
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::path::MAIN_SEPARATOR;
+
+pub const NO_STATUS: &str = "";
+pub const UNMODIFIED: &str = "  ";
+pub const WD_ONLY_MODIFIED: &str = " M";
+pub const WD_ONLY_DELETED: &str = " D";
+pub const MODIFIED: &str = "M ";
+pub const MODIFIED_MODIFIED: &str = "MM";
+pub const MODIFIED_DELETED: &str = "MD";
+pub const ADDED: &str = "A ";
+pub const ADDED_MODIFIED: &str = "AM";
+pub const ADDED_DELETED: &str = "AD";
+pub const DELETED: &str = "D ";
+pub const DELETED_MODIFIED: &str = "DM";
+pub const RENAMED: &str = "R ";
+pub const RENAMED_MODIFIED: &str = "RM";
+pub const RENAMED_DELETED: &str = "RD";
+pub const COPIED: &str = "C ";
+pub const COPIED_MODIFIED: &str = "CM";
+pub const COPIED_DELETED: &str = "CD";
+pub const UNMERGED: &str = "UU";
+pub const UNMERGED_ADDED: &str = "AA";
+pub const UNMERGED_ADDED_US: &str = "AU";
+pub const UNMERGED_ADDED_THEM: &str = "UA";
+pub const UNMERGED_DELETED: &str = "DD";
+pub const UNMERGED_DELETED_US: &str = "DU";
+pub const UNMERGED_DELETED_THEM: &str = "UD";
+pub const NOT_TRACKED: &str = "??";
+pub const IGNORED: &str = "!!";
+
+lazy_static! {
+    static ref UNMERGED_SET: HashSet<&'static str> = {
+        let mut s: HashSet<&'static str> = HashSet::new();
+        for status in [
+            UNMERGED,
+            UNMERGED_ADDED,
+            UNMERGED_ADDED_US,
+            UNMERGED_ADDED_THEM,
+            UNMERGED_DELETED,
+            UNMERGED_DELETED_US,
+            UNMERGED_DELETED_THEM,
+        ]
+        .iter()
+        {
+            s.insert(status);
+        }
+        s
+    };
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RelatedFileData {
+    pub file_path: String,
+    pub relation: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct SubmoduleState {
+    pub commit_changed: bool,
+    pub has_tracked_changes: bool,
+    pub has_untracked_changes: bool,
+}
+
+impl SubmoduleState {
+    // porcelain v2 "<sub>" field: "N..." for non submodules or "S<c><m><u>"
+    fn from_porcelain_v2(field: &str) -> Option<Self> {
+        let bytes = field.as_bytes();
+        if bytes.len() == 4 && bytes[0] == b'S' {
+            Some(Self {
+                commit_changed: bytes[1] == b'C',
+                has_tracked_changes: bytes[2] == b'M',
+                has_untracked_changes: bytes[3] == b'U',
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// A file's entry in the output of "git status".  The path is relative to the
+// top of the working directory and prefixed with "./".
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatusEntry {
+    pub path: String,
+    pub status: String,
+    pub related_file_data: Option<RelatedFileData>,
+    pub rename_score: Option<u8>,
+    pub submodule_state: Option<SubmoduleState>,
+}
+
+// git uses "." for "unmodified" in porcelain v2 XY fields but we keep the v1 style
+fn v2_xy_to_status(xy: &str) -> String {
+    xy.replace('.', " ")
+}
+
+// git doesn't include "./" but we have to for read_dir() so add for compatibility
+fn v2_path(path: &str) -> String {
+    format!(".{}{}", MAIN_SEPARATOR, path)
+}
+
+// Parse the output of "git status --porcelain=v2 -z".  Records are NUL terminated
+// and paths are not quoted so no unescaping is required.
+pub fn parse_porcelain_v2(text: &str) -> Vec<StatusEntry> {
+    let mut entries = vec![];
+    let mut records = text.split('\0');
+    while let Some(record) = records.next() {
+        if record.is_empty() || record.starts_with('#') {
+            continue;
+        }
+        let (kind, rest) = record.split_at(1);
+        // only the one separating space: file names may start with spaces
+        let rest = rest.strip_prefix(' ').unwrap_or(rest);
+        match kind {
+            "1" => {
+                // 1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>
+                let fields: Vec<&str> = rest.splitn(8, ' ').collect();
+                if fields.len() == 8 {
+                    entries.push(StatusEntry {
+                        path: v2_path(fields[7]),
+                        status: v2_xy_to_status(fields[0]),
+                        related_file_data: None,
+                        rename_score: None,
+                        submodule_state: SubmoduleState::from_porcelain_v2(fields[1]),
+                    });
+                }
+            }
+            "2" => {
+                // 2 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path>NUL<origPath>
+                let fields: Vec<&str> = rest.splitn(9, ' ').collect();
+                let orig_path = records.next().unwrap_or("");
+                if fields.len() == 9 {
+                    let rename_score = fields[7].get(1..).and_then(|s| s.parse::<u8>().ok());
+                    entries.push(StatusEntry {
+                        path: v2_path(orig_path),
+                        status: v2_xy_to_status(fields[0]),
+                        related_file_data: Some(RelatedFileData {
+                            file_path: fields[8].to_string(),
+                            relation: "->".to_string(),
+                        }),
+                        rename_score,
+                        submodule_state: SubmoduleState::from_porcelain_v2(fields[1]),
+                    });
+                }
+            }
+            "u" => {
+                // u <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>
+                let fields: Vec<&str> = rest.splitn(10, ' ').collect();
+                if fields.len() == 10 {
+                    entries.push(StatusEntry {
+                        path: v2_path(fields[9]),
+                        status: v2_xy_to_status(fields[0]),
+                        related_file_data: None,
+                        rename_score: None,
+                        submodule_state: SubmoduleState::from_porcelain_v2(fields[1]),
+                    });
+                }
+            }
+            "?" | "!" => {
+                // wholly untracked/ignored directories are reported with a trailing "/"
+                let path = match rest.strip_suffix('/') {
+                    Some(dir_path) if !dir_path.is_empty() => dir_path,
+                    _ => rest,
+                };
+                entries.push(StatusEntry {
+                    path: v2_path(path),
+                    status: if kind == "?" { NOT_TRACKED } else { IGNORED }.to_string(),
+                    related_file_data: None,
+                    rename_score: None,
+                    submodule_state: None,
+                });
+            }
+            _ => (),
+        }
+    }
+    entries
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct StatusCounts {
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+// Counts of files in each state for output of "git status --porcelain=v2 -z"
+pub fn status_counts_from_text(text: &str) -> StatusCounts {
+    let mut counts = StatusCounts::default();
+    for entry in parse_porcelain_v2(text) {
+        let status = entry.status.as_str();
+        if status == NOT_TRACKED {
+            counts.untracked += 1;
+        } else if UNMERGED_SET.contains(status) {
+            counts.conflicted += 1;
+        } else if status != IGNORED && status != NO_STATUS {
+            if !status.starts_with(' ') {
+                counts.staged += 1;
+            }
+            if !status.ends_with(' ') {
+                counts.unstaged += 1;
+            }
+        }
+    }
+    counts
+}
+
+// Is the file one of a merge's (or similar operation's) conflicts?
+pub fn status_is_unmerged(status: &str) -> bool {
+    UNMERGED_SET.contains(status)
+}
+
+// Does the status indicate that the working directory copy differs from the index?
+pub fn status_has_wd_changes(status: &str) -> bool {
+    if status == NOT_TRACKED || status == IGNORED || UNMERGED_SET.contains(status) {
+        false
+    } else {
+        status.ends_with('M') || status.ends_with('D')
+    }
+}
+
+// Is the file/directory known to git?
+pub fn status_is_tracked(status: &str) -> bool {
+    status != NOT_TRACKED && status != IGNORED
+}
+
+// Is there an entry in the index that "git rm --cached" could remove?
+pub fn status_is_in_index(status: &str) -> bool {
+    status_is_tracked(status) && !status.starts_with('D')
+}
+
+// Does the file/directory exist in the working directory?
+pub fn status_is_in_wd(status: &str) -> bool {
+    !status.ends_with('D') && status != DELETED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Captured from "git status --porcelain=v2 -z --ignored --untracked=all --ignore-submodules=none"
+    const SAMPLE: &str = concat!(
+        "1 .M N... 100644 100644 100644 3b18e512dba79e4c8300dd08aeb37f8e728b8dad 3b18e512dba79e4c8300dd08aeb37f8e728b8dad src/main.rs\0",
+        "1 A. N... 000000 100644 100644 0000000000000000000000000000000000000000 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 a file with \"quotes\" and -> arrow.txt\0",
+        "2 R. N... 100644 100644 100644 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 R87 new\\name.txt\0old name.txt\0",
+        "1 .M SC.U 160000 160000 160000 8d2b3c3b7d1c0c3c79ac9ba3d6b0d4a4d2b7e0f1 8d2b3c3b7d1c0c3c79ac9ba3d6b0d4a4d2b7e0f1 sub/module\0",
+        "u UU N... 100644 100644 100644 100644 257cc5642cb1a054f08cc83f2d943e56fd3ebe99 5716ca5987cbf97d6bb54920bea6adde242d87e6 76018072e09c5d31c8c6e3113b8aa0fe625195ca conflicted.rs\0",
+        "? line\nbreak.txt\0",
+        "?    three leading spaces.txt\0",
+        "! target/debug/\0",
+    );
+
+    #[test]
+    fn parse_porcelain_v2_works() {
+        let entries: HashMap<String, StatusEntry> = parse_porcelain_v2(SAMPLE)
+            .into_iter()
+            .map(|entry| (entry.path.clone(), entry))
+            .collect();
+        assert_eq!(entries.len(), 8);
+
+        let fs = &entries["./src/main.rs"];
+        assert_eq!(fs.status, WD_ONLY_MODIFIED);
+        assert_eq!(fs.submodule_state, None);
+
+        let fs = &entries["./a file with \"quotes\" and -> arrow.txt"];
+        assert_eq!(fs.status, ADDED);
+        assert_eq!(fs.related_file_data, None);
+
+        let fs = &entries["./old name.txt"];
+        assert_eq!(fs.status, RENAMED);
+        assert_eq!(fs.rename_score, Some(87));
+        assert_eq!(
+            fs.related_file_data,
+            Some(RelatedFileData {
+                file_path: "new\\name.txt".to_string(),
+                relation: "->".to_string(),
+            })
+        );
+
+        let fs = &entries["./sub/module"];
+        assert_eq!(fs.status, WD_ONLY_MODIFIED);
+        assert_eq!(
+            fs.submodule_state,
+            Some(SubmoduleState {
+                commit_changed: true,
+                has_tracked_changes: false,
+                has_untracked_changes: true,
+            })
+        );
+
+        assert_eq!(entries["./conflicted.rs"].status, UNMERGED);
+        assert_eq!(entries["./line\nbreak.txt"].status, NOT_TRACKED);
+        assert_eq!(entries["./   three leading spaces.txt"].status, NOT_TRACKED);
+        assert_eq!(entries["./target/debug"].status, IGNORED);
+    }
+
+    #[test]
+    fn parse_porcelain_v2_skips_headers_and_empty_input() {
+        assert!(parse_porcelain_v2("").is_empty());
+        let text = "# branch.oid 3b18e512dba79e4c8300dd08aeb37f8e728b8dad\0# branch.head master\0";
+        assert!(parse_porcelain_v2(text).is_empty());
+    }
+
+    #[test]
+    fn status_is_unmerged_works() {
+        let text = "u UD N... 100644 100644 000000 100644 aaaa bbbb 0000 gone.txt\0";
+        let entries = parse_porcelain_v2(text);
+        assert_eq!(entries[0].status, UNMERGED_DELETED_THEM);
+        assert!(status_is_unmerged(&entries[0].status));
+        assert!(status_is_unmerged(UNMERGED));
+        assert!(!status_is_unmerged(MODIFIED_MODIFIED));
+        assert!(!status_is_unmerged(NOT_TRACKED));
+    }
+
+    #[test]
+    fn status_counts_from_text_works() {
+        assert_eq!(
+            status_counts_from_text(SAMPLE),
+            StatusCounts {
+                staged: 2,
+                unstaged: 2,
+                untracked: 2,
+                conflicted: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use regex::Regex;
+
+// The "git tag" format that parse_tags() expects
+pub const TAG_FORMAT: &str = "--format=%(refname:short) %(objectname:short) %(subject)";
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub rev: String,
+    pub synopsis: String,
+}
+
+lazy_static! {
+    static ref TAGS_RE: Regex =
+        Regex::new(r"(\S+)\s+([a-fA-F0-9]{7}[a-fA-F0-9]*)?\s*(.*)").unwrap();
+}
+
+// Parse the output of "git tag" run with TAG_FORMAT
+pub fn parse_tags(text: &str) -> Vec<Tag> {
+    text.lines()
+        .filter_map(|line| TAGS_RE.captures(line))
+        .map(|captures| Tag {
+            name: captures.get(1).unwrap().as_str().to_string(),
+            rev: captures.get(2).map_or("", |m| m.as_str()).to_string(),
+            synopsis: captures.get(3).unwrap().as_str().to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tags_works() {
+        let text = "v0.1 1a2b3c4 First release\nv0.2 5d6e7f8a Second: the sequel\nbare 9a8b7c6 \n";
+        assert_eq!(
+            parse_tags(text),
+            vec![
+                Tag {
+                    name: "v0.1".to_string(),
+                    rev: "1a2b3c4".to_string(),
+                    synopsis: "First release".to_string(),
+                },
+                Tag {
+                    name: "v0.2".to_string(),
+                    rev: "5d6e7f8a".to_string(),
+                    synopsis: "Second: the sequel".to_string(),
+                },
+                Tag {
+                    name: "bare".to_string(),
+                    rev: "9a8b7c6".to_string(),
+                    synopsis: "".to_string(),
+                },
+            ]
+        );
+    }
+}
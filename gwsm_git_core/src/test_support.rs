@@ -0,0 +1,245 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use tempfile::TempDir;
+
+use crate::workspace::WsPath;
+
+// Throwaway git repositories in temporary directories for use in tests.
+// All git commands are expected to succeed (and panic if they don't) so
+// that tests can script whatever history they need in a few lines.
+
+pub fn git_output(dir: &Path, args: &[&str]) -> Output {
+    Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_EDITOR", "true")
+        .output()
+        .expect("failed to run git")
+}
+
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = git_output(dir, args);
+    assert!(
+        output.status.success(),
+        "git {:?} failed in {:?}: {}",
+        args,
+        dir,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub struct ScratchRepo {
+    dir: TempDir,
+    // Remotes and submodule sources that must outlive this repository
+    others: RefCell<Vec<TempDir>>,
+}
+
+impl Default for ScratchRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScratchRepo {
+    // A repository on "master" containing a single commit (of "README")
+    pub fn new() -> Self {
+        let repo = Self::empty();
+        repo.commit_file("README", "Read me.\n", "Initial commit");
+        repo
+    }
+
+    // A repository on an unborn "master"
+    pub fn empty() -> Self {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        git(dir.path(), &["init", "-q", "-b", "master"]);
+        for (name, value) in [
+            ("user.name", "Scratch Tester"),
+            ("user.email", "tester@example.com"),
+            ("commit.gpgSign", "false"),
+            ("tag.gpgSign", "false"),
+        ]
+        .iter()
+        {
+            git(dir.path(), &["config", name, value]);
+        }
+        Self {
+            dir,
+            others: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path().to_string_lossy().to_string()
+    }
+
+    pub fn ws_path(&self) -> WsPath {
+        WsPath::new(self.path())
+    }
+
+    pub fn git(&self, args: &[&str]) -> String {
+        git(self.path(), args)
+    }
+
+    // For when failure is expected (or needs to be examined)
+    pub fn git_output(&self, args: &[&str]) -> Output {
+        git_output(self.path(), args)
+    }
+
+    pub fn write_file(&self, file_path: &str, content: &str) {
+        let path = self.path().join(file_path);
+        if let Some(dir_path) = path.parent() {
+            fs::create_dir_all(dir_path).expect("failed to create directory");
+        }
+        fs::write(path, content).expect("failed to write file");
+    }
+
+    pub fn commit_file(&self, file_path: &str, content: &str, message: &str) {
+        self.write_file(file_path, content);
+        self.git(&["add", "--", file_path]);
+        self.git(&["commit", "-q", "-m", message]);
+    }
+
+    pub fn head_rev(&self) -> String {
+        self.git(&["rev-parse", "--short", "HEAD"])
+            .trim_end()
+            .to_string()
+    }
+
+    pub fn create_branch(&self, name: &str) {
+        self.git(&["branch", name]);
+    }
+
+    pub fn checkout(&self, rev: &str) {
+        self.git(&["checkout", "-q", rev]);
+    }
+
+    // An annotated tag if there's an annotation otherwise a lightweight one
+    pub fn tag(&self, name: &str, annotation: Option<&str>) {
+        match annotation {
+            Some(message) => self.git(&["tag", "-a", "-m", message, name]),
+            None => self.git(&["tag", name]),
+        };
+    }
+
+    // Stash a change to the (tracked) file
+    pub fn stash(&self, file_path: &str, content: &str, message: &str) {
+        self.write_file(file_path, content);
+        self.git(&["stash", "push", "-q", "-m", message]);
+    }
+
+    pub fn rename(&self, from_path: &str, to_path: &str) {
+        self.git(&["mv", "--", from_path, to_path]);
+    }
+
+    // Add the pattern to (and commit) .gitignore
+    pub fn ignore(&self, pattern: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path().join(".gitignore"))
+            .expect("failed to open .gitignore");
+        writeln!(file, "{}", pattern).expect("failed to write .gitignore");
+        self.git(&["add", ".gitignore"]);
+        self.git(&["commit", "-q", "-m", &format!("Ignore {}", pattern)]);
+    }
+
+    // Leave the repository in the middle of a merge of "branch" (created for
+    // the purpose) with conflicting changes to the (tracked) file
+    pub fn start_conflicted_merge(&self, branch: &str, file_path: &str) {
+        self.git(&["checkout", "-q", "-b", branch]);
+        self.commit_file(file_path, "their change\n", "Their change");
+        self.git(&["checkout", "-q", "-"]);
+        self.commit_file(file_path, "our change\n", "Our change");
+        let output = self.git_output(&["merge", branch]);
+        assert!(!output.status.success(), "merge unexpectedly succeeded");
+    }
+
+    // Add a (freshly created) repository as a submodule and commit it
+    pub fn add_submodule(&self, sub_path: &str) -> PathBuf {
+        let source = Self::new();
+        source.commit_file("lib.txt", "library\n", "Add library");
+        self.git(&[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "-q",
+            &source.path_string(),
+            sub_path,
+        ]);
+        self.git(&["commit", "-q", "-m", &format!("Add submodule {}", sub_path)]);
+        let source_path = source.path().to_path_buf();
+        self.adopt(source);
+        source_path
+    }
+
+    // Add a bare repository as a remote and push the current branch to it
+    // (setting it as the upstream)
+    pub fn add_bare_remote(&self, name: &str) -> PathBuf {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        git(dir.path(), &["init", "-q", "--bare"]);
+        let remote_path = dir.path().to_path_buf();
+        self.others.borrow_mut().push(dir);
+        self.git(&["remote", "add", name, &remote_path.to_string_lossy()]);
+        self.git(&["push", "-q", "-u", name, "HEAD"]);
+        remote_path
+    }
+
+    // A clone of this repository (sharing its lifetime)
+    pub fn clone_repo(&self) -> ScratchRepo {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        git(dir.path(), &["clone", "-q", &self.path_string(), "."]);
+        git(dir.path(), &["config", "user.name", "Scratch Cloner"]);
+        git(dir.path(), &["config", "user.email", "cloner@example.com"]);
+        Self {
+            dir,
+            others: RefCell::new(vec![]),
+        }
+    }
+
+    fn adopt(&self, other: ScratchRepo) {
+        let mut others = self.others.borrow_mut();
+        others.push(other.dir);
+        others.extend(other.others.into_inner());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratch_repo_works() {
+        let repo = ScratchRepo::new();
+        assert!(repo.path().join(".git").is_dir());
+        assert_eq!(repo.git(&["log", "--format=%s"]), "Initial commit\n");
+        repo.start_conflicted_merge("theirs", "README");
+        assert!(repo.path().join(".git/MERGE_HEAD").is_file());
+        let remote_path = repo.add_bare_remote("origin");
+        assert!(remote_path.join("HEAD").is_file());
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+
+// The directory of a workspace window.  It is shared by all of the window's
+// widgets so that git is run (and relative paths are interpreted) in the
+// right place without relying on the process's current directory.
+#[derive(Debug, Clone)]
+pub struct WsPath(Rc<RefCell<PathBuf>>);
+
+impl Default for WsPath {
+    fn default() -> Self {
+        let path = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        Self::new(path)
+    }
+}
+
+impl WsPath {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        WsPath(Rc::new(RefCell::new(path.as_ref().to_path_buf())))
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.0.borrow().clone()
+    }
+
+    pub fn set<P: AsRef<Path>>(&self, path: P) {
+        *self.0.borrow_mut() = path.as_ref().to_path_buf();
+    }
+
+    pub fn path_string(&self) -> String {
+        self.0.borrow().to_string_lossy().to_string()
+    }
+
+    // Abbreviate the home directory to "~" for display purposes.
+    pub fn path_string_rel_home(&self) -> String {
+        let path = self.0.borrow();
+        if let Some(home) = env::var_os("HOME") {
+            if let Ok(rel_path) = path.strip_prefix(&home) {
+                return Path::new("~").join(rel_path).to_string_lossy().to_string();
+            }
+        }
+        path.to_string_lossy().to_string()
+    }
+
+    // Relative paths are interpreted relative to the workspace directory and
+    // absolute paths are left alone.
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> String {
+        self.0.borrow().join(path).to_string_lossy().to_string()
+    }
+
+    pub fn command<S: AsRef<OsStr>>(&self, program: S) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.current_dir(&*self.0.borrow());
+        cmd
+    }
+
+    pub fn git(&self) -> Command {
+        self.command("git")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ws_path_works() {
+        let ws_path = WsPath::new("/tmp/work");
+        let shared = ws_path.clone();
+        assert_eq!(ws_path.join("./src/main.rs"), "/tmp/work/./src/main.rs");
+        assert_eq!(ws_path.join("/etc/hosts"), "/etc/hosts");
+        shared.set("/tmp/other");
+        assert_eq!(ws_path.path(), PathBuf::from("/tmp/other"));
+        assert_eq!(ws_path.path_string(), "/tmp/other");
+        if let Some(home) = env::var_os("HOME") {
+            shared.set(Path::new(&home).join("work"));
+            assert_eq!(ws_path.path_string_rel_home(), "~/work");
+        }
+    }
+}
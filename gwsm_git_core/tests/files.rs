@@ -0,0 +1,111 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Run the file command builders in scratch repositories
+
+use std::fs;
+
+use gwsm_git_core::files::{self, Side};
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::test_support::ScratchRepo;
+
+fn run(repo: &ScratchRepo, cmd: &GitCommand) {
+    let output = cmd.command(&repo.ws_path()).output().expect("git failed");
+    assert!(
+        output.status.success(),
+        "{}: {}",
+        cmd,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn short_status(repo: &ScratchRepo) -> String {
+    repo.git(&["status", "--porcelain", "--untracked-files=all"])
+}
+
+#[test]
+fn index_and_work_tree_commands_work() {
+    let repo = ScratchRepo::new();
+    repo.commit_file("-odd name.txt", "original\n", "Add odd name");
+
+    repo.write_file("-odd name.txt", "changed\n");
+    run(&repo, &files::discard_changes_cmd(&["-odd name.txt"]));
+    assert!(short_status(&repo).is_empty());
+
+    repo.write_file("new.txt", "new\n");
+    run(&repo, &files::add_cmd(&["new.txt"]));
+    assert_eq!(short_status(&repo), "A  new.txt\n");
+    run(&repo, &files::unstage_cmd(&["new.txt"]));
+    assert_eq!(short_status(&repo), "?? new.txt\n");
+
+    run(&repo, &files::move_cmd("-odd name.txt", "-renamed.txt"));
+    assert_eq!(
+        short_status(&repo),
+        "R  \"-odd name.txt\" -> -renamed.txt\n?? new.txt\n"
+    );
+
+    run(&repo, &files::remove_from_index_cmd(&["-renamed.txt"]));
+    assert!(repo.path().join("-renamed.txt").exists());
+    assert_eq!(
+        short_status(&repo),
+        "D  \"-odd name.txt\"\n?? -renamed.txt\n?? new.txt\n"
+    );
+}
+
+#[test]
+fn apply_to_index_cmd_works() {
+    let repo = ScratchRepo::new();
+    repo.commit_file("f.txt", "one\ntwo\n", "Add f.txt");
+    repo.write_file("f.txt", "one\nTWO\n");
+    let patch = repo.git(&["diff"]);
+    let patch_dir = tempfile::tempdir().unwrap();
+    let patch_path = patch_dir.path().join("partial.patch");
+    fs::write(&patch_path, patch).unwrap();
+
+    run(&repo, &files::apply_to_index_cmd(&patch_path, false));
+    assert_eq!(short_status(&repo), "M  f.txt\n");
+    run(&repo, &files::apply_to_index_cmd(&patch_path, true));
+    assert_eq!(short_status(&repo), " M f.txt\n");
+}
+
+#[test]
+fn take_side_cmds_work() {
+    for (side, content) in [
+        (Side::Ours, "our change\n"),
+        (Side::Theirs, "their change\n"),
+    ]
+    .iter()
+    {
+        let repo = ScratchRepo::new();
+        repo.start_conflicted_merge("theirs", "README");
+        for cmd in files::take_side_cmds("README", *side, false).iter() {
+            run(&repo, cmd);
+        }
+        assert_eq!(
+            fs::read_to_string(repo.path().join("README")).unwrap(),
+            *content
+        );
+        assert!(repo
+            .git(&["diff", "--name-only", "--diff-filter=U"])
+            .is_empty());
+    }
+
+    let repo = ScratchRepo::new();
+    repo.start_conflicted_merge("theirs", "README");
+    let cmds = files::take_side_cmds("README", Side::Theirs, true);
+    assert_eq!(cmds.len(), 1);
+    run(&repo, &cmds[0]);
+    assert!(!repo.path().join("README").exists());
+    assert_eq!(short_status(&repo), "D  README\n");
+}
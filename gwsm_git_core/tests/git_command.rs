@@ -0,0 +1,203 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Run GitCommands in scratch repositories to check that their arguments
+// reach git intact
+
+use gwsm_git_core::git_command::{self, GitCommand, ScriptStep};
+use gwsm_git_core::merge::{FastForward, MergeOptions};
+use gwsm_git_core::test_support::ScratchRepo;
+use gwsm_git_core::{branches, status, tags};
+
+fn run(repo: &ScratchRepo, cmd: &GitCommand) -> String {
+    let output = cmd.command(&repo.ws_path()).output().expect("git failed");
+    assert!(
+        output.status.success(),
+        "{}: {}",
+        cmd,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn git_command_passes_awkward_arguments() {
+    let repo = ScratchRepo::new();
+    repo.write_file("it's a \"file\" -> here.txt", "awkward\n");
+    run(
+        &repo,
+        &GitCommand::new("add")
+            .arg("--")
+            .args(["it's a \"file\" -> here.txt"]),
+    );
+    let message = "Fix: it's \"done\"; $HOME `true`";
+    run(
+        &repo,
+        &GitCommand::new("commit")
+            .arg_if(false, "--amend")
+            .arg("-q")
+            .arg("-m")
+            .arg(message),
+    );
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]).trim_end(), message);
+    assert!(repo.git(&["status", "--porcelain"]).is_empty());
+
+    run(
+        &repo,
+        &GitCommand::new("tag")
+            .arg("-a")
+            .args(["-m", "a; b", "v1.0"]),
+    );
+    let tags = tags::parse_tags(&run(&repo, &GitCommand::new("tag").arg(tags::TAG_FORMAT)));
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].name, "v1.0");
+    assert_eq!(tags[0].synopsis, "a; b");
+
+    run(
+        &repo,
+        &GitCommand::new("checkout").args(["-q", "-b", "feature/x"]),
+    );
+    let text = run(
+        &repo,
+        &GitCommand::new("branch").arg(branches::BRANCH_FORMAT),
+    );
+    let merged = run(&repo, &GitCommand::new("branch").arg("--merged"));
+    let branches = branches::parse_branches(&text, &merged);
+    assert!(branches
+        .iter()
+        .any(|branch| branch.name == "feature/x" && branch.is_current));
+}
+
+#[test]
+fn git_command_config_is_honoured() {
+    let repo = ScratchRepo::new();
+    repo.start_conflicted_merge("theirs", "README");
+    repo.write_file("README", "resolved\n");
+    run(&repo, &GitCommand::new("add").arg("README"));
+    // without the editor override this would wait for an editor
+    let cmd = GitCommand::new("merge")
+        .config("core.editor=true")
+        .arg("--continue");
+    run(&repo, &cmd);
+    let text = run(
+        &repo,
+        &GitCommand::new("status").args(["--porcelain=v2", "-z"]),
+    );
+    assert!(status::parse_porcelain_v2(&text).is_empty());
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%P"])
+            .split_whitespace()
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn git_command_reruns_from_argv() {
+    let repo = ScratchRepo::new();
+    let cmd = GitCommand::new("branch").arg("from argv");
+    let output = cmd.command(&repo.ws_path()).output().unwrap();
+    assert!(!output.status.success()); // not a valid branch name
+    let cmd = GitCommand::from_argv(&GitCommand::new("branch").arg("from-argv").argv()).unwrap();
+    run(&repo, &cmd);
+    assert!(repo.git(&["branch"]).contains("from-argv"));
+}
+
+#[test]
+fn replay_script_replays_only_successful_commands() {
+    let repo = ScratchRepo::new();
+    let steps = vec![
+        ScriptStep {
+            cwd: repo.path_string(),
+            cmd: GitCommand::new("commit").arg("-m").arg("a\ntouch pwned"),
+            failure: Some("failed".to_string()),
+        },
+        ScriptStep {
+            cwd: repo.path_string(),
+            cmd: GitCommand::new("tag").arg("it's-replayed"),
+            failure: None,
+        },
+    ];
+    let script = git_command::replay_script("replay\ntest", &steps);
+    let script_path = repo.path().join("replay.sh");
+    std::fs::write(&script_path, script).unwrap();
+    let output = std::process::Command::new("sh")
+        .arg(&script_path)
+        .output()
+        .expect("sh failed");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(repo.git(&["tag", "--list"]), "it's-replayed\n");
+    assert!(!repo.path().join("pwned").exists());
+}
+
+#[test]
+fn unmerged_commits_cmd_works() {
+    let repo = ScratchRepo::new();
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.commit_file("shared.txt", "shared\n", "Shared work");
+    repo.git(&["branch", "other"]);
+    repo.commit_file("topic.txt", "topic\n", "Topic only work");
+    repo.checkout("master");
+    let text = run(&repo, &branches::unmerged_commits_cmd("topic"));
+    let subjects: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.split_once(' ').map(|(_, subject)| subject))
+        .collect();
+    assert_eq!(subjects, vec!["Topic only work"]);
+    repo.git(&["tag", "keep", "topic"]);
+    assert!(run(&repo, &branches::unmerged_commits_cmd("topic")).is_empty());
+}
+
+#[test]
+fn merge_options_commands_work() {
+    let repo = ScratchRepo::new();
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.commit_file("topic.txt", "topic\n", "Topic work");
+    repo.checkout("master");
+
+    let options = MergeOptions {
+        fast_forward: FastForward::Only,
+        squash: true,
+        ..MergeOptions::default()
+    };
+    run(&repo, &options.command("topic"));
+    let text = run(
+        &repo,
+        &GitCommand::new("status").args(["--porcelain=v2", "-z"]),
+    );
+    let entries = status::parse_porcelain_v2(&text);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].path, "./topic.txt");
+    run(&repo, &GitCommand::new("reset").args(["-q", "--hard"]));
+
+    let message = "Merge \"topic\"; it's ready";
+    let options = MergeOptions {
+        fast_forward: FastForward::Never,
+        strategy_option: Some("theirs".to_string()),
+        message: Some(message.to_string()),
+        ..MergeOptions::default()
+    };
+    run(&repo, &options.command("topic"));
+    assert_eq!(repo.git(&["log", "-1", "--format=%s"]).trim_end(), message);
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%P"])
+            .split_whitespace()
+            .count(),
+        2
+    );
+}
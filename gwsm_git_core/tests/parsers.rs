@@ -0,0 +1,226 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Exercise the parsers against the output of git run in scratch repositories
+
+use gwsm_git_core::test_support::ScratchRepo;
+use gwsm_git_core::{branches, remotes, stashes, status, tags};
+
+fn status_entries(repo: &ScratchRepo) -> Vec<status::StatusEntry> {
+    let text = repo.git(&[
+        "status",
+        "--porcelain=v2",
+        "-z",
+        "--ignored",
+        "--untracked=all",
+        "--ignore-submodules=none",
+    ]);
+    status::parse_porcelain_v2(&text)
+}
+
+fn find_entry<'a>(entries: &'a [status::StatusEntry], path: &str) -> &'a status::StatusEntry {
+    entries
+        .iter()
+        .find(|entry| entry.path == path)
+        .unwrap_or_else(|| panic!("{}: not in status", path))
+}
+
+#[test]
+fn status_parses_repo_output() {
+    let repo = ScratchRepo::new();
+    repo.ignore("*.o");
+    repo.commit_file("old name.txt", "renamed\n", "Add file to rename");
+    repo.write_file("README", "read me again\n");
+    repo.write_file("new file.txt", "new\n");
+    repo.git(&["add", "new file.txt"]);
+    repo.write_file("junk.o", "");
+    repo.write_file("sub dir/untracked.txt", "");
+    repo.rename("old name.txt", "new name.txt");
+    let entries = status_entries(&repo);
+    assert_eq!(entries.len(), 5);
+    assert_eq!(
+        find_entry(&entries, "./README").status,
+        status::WD_ONLY_MODIFIED
+    );
+    assert_eq!(find_entry(&entries, "./new file.txt").status, status::ADDED);
+    assert_eq!(find_entry(&entries, "./junk.o").status, status::IGNORED);
+    assert_eq!(
+        find_entry(&entries, "./sub dir/untracked.txt").status,
+        status::NOT_TRACKED
+    );
+    let renamed = find_entry(&entries, "./old name.txt");
+    assert_eq!(renamed.status, status::RENAMED);
+    assert_eq!(renamed.rename_score, Some(100));
+    assert_eq!(
+        renamed.related_file_data,
+        Some(status::RelatedFileData {
+            file_path: "new name.txt".to_string(),
+            relation: "->".to_string(),
+        })
+    );
+    let text = repo.git(&["status", "--porcelain=v2", "-z"]);
+    assert_eq!(
+        status::status_counts_from_text(&text),
+        status::StatusCounts {
+            staged: 2,
+            unstaged: 1,
+            untracked: 1,
+            conflicted: 0,
+        }
+    );
+}
+
+#[test]
+fn status_parses_conflicts() {
+    let repo = ScratchRepo::new();
+    repo.start_conflicted_merge("theirs", "README");
+    let entries = status_entries(&repo);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(find_entry(&entries, "./README").status, status::UNMERGED);
+    let text = repo.git(&["status", "--porcelain=v2", "-z"]);
+    assert_eq!(status::status_counts_from_text(&text).conflicted, 1);
+}
+
+#[test]
+fn status_parses_submodules() {
+    let repo = ScratchRepo::new();
+    repo.add_submodule("sub/module");
+    repo.write_file("sub/module/lib.txt", "changed\n");
+    repo.write_file("sub/module/extra.txt", "");
+    let entries = status_entries(&repo);
+    let entry = find_entry(&entries, "./sub/module");
+    assert_eq!(entry.status, status::WD_ONLY_MODIFIED);
+    assert_eq!(
+        entry.submodule_state,
+        Some(status::SubmoduleState {
+            commit_changed: false,
+            has_tracked_changes: true,
+            has_untracked_changes: true,
+        })
+    );
+}
+
+#[test]
+fn branches_parse_repo_output() {
+    let repo = ScratchRepo::new();
+    repo.add_bare_remote("origin");
+    repo.create_branch("merged");
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.commit_file("topic.txt", "topic\n", "Topic work");
+    repo.checkout("master");
+    repo.commit_file("README", "more\n", "Master work");
+    let all_branches_text = repo.git(&["branch", branches::BRANCH_FORMAT]);
+    let merged_branches_text = repo.git(&["branch", "--merged"]);
+    let branches = branches::parse_branches(&all_branches_text, &merged_branches_text);
+    let names: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, vec!["master", "merged", "topic"]);
+    assert!(branches[0].is_current && branches[0].is_merged);
+    assert_eq!(branches[0].rev, repo.head_rev());
+    assert_eq!(branches[0].upstream.as_deref(), Some("origin/master"));
+    assert_eq!((branches[0].ahead, branches[0].behind), (1, 0));
+    assert_eq!(branches[0].author, "Scratch Tester");
+    assert_eq!(branches[0].date.len(), "YYYY-MM-DD HH:MM".len());
+    assert_eq!(branches[0].synopsis, "Master work");
+    assert!(!branches[1].is_current && branches[1].is_merged);
+    assert_eq!(branches[1].synopsis, "Initial commit");
+    assert!(!branches[2].is_merged);
+    assert_eq!(branches[2].upstream, None);
+    assert_eq!(branches[2].synopsis, "Topic work");
+
+    repo.checkout("topic~1");
+    let all_branches_text = repo.git(&["branch", branches::BRANCH_FORMAT]);
+    let merged_branches_text = repo.git(&["branch", "--merged"]);
+    let branches = branches::parse_branches(&all_branches_text, &merged_branches_text);
+    assert_eq!(branches.len(), 4);
+    assert!(branches[0].is_current);
+    assert!(branches[0].name.starts_with("(HEAD detached at"));
+    assert_eq!(branches[0].rev, repo.head_rev());
+}
+
+#[test]
+fn tags_parse_repo_output() {
+    let repo = ScratchRepo::new();
+    repo.tag("light", None);
+    repo.commit_file("README", "more\n", "Second commit");
+    repo.tag("v1", Some("Release one"));
+    let tags = tags::parse_tags(&repo.git(&["tag", tags::TAG_FORMAT]));
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags[0].name, "light");
+    assert_eq!(tags[0].synopsis, "Initial commit");
+    assert_eq!(tags[1].name, "v1");
+    assert_eq!(tags[1].synopsis, "Release one");
+}
+
+#[test]
+fn stashes_parse_repo_output() {
+    let repo = ScratchRepo::new();
+    repo.stash("README", "changed\n", "first: stash");
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.stash("README", "changed again\n", "second");
+    let stashes = stashes::parse_stashes(&repo.git(&["stash", "list"]));
+    assert_eq!(stashes.len(), 2);
+    assert_eq!(stashes[0].name, "stash@{0}");
+    assert_eq!(stashes[0].branch, "On topic");
+    assert_eq!(stashes[0].commit, " second");
+    assert_eq!(stashes[1].name, "stash@{1}");
+    assert_eq!(stashes[1].branch, "On master");
+    assert_eq!(stashes[1].commit, " first: stash");
+}
+
+#[test]
+fn remotes_parse_repo_output() {
+    let repo = ScratchRepo::new();
+    let origin_path = repo.add_bare_remote("origin");
+    let other_path = repo.add_bare_remote("other");
+    repo.git(&["remote", "set-url", "--push", "other", "/srv/git/push.git"]);
+    let remotes = remotes::parse_remotes(&repo.git(&["remote", "-v"]));
+    assert_eq!(remotes.len(), 2);
+    assert_eq!(remotes[0].name, "origin");
+    assert_eq!(remotes[0].inbound_url, origin_path.to_string_lossy());
+    assert_eq!(remotes[0].outbound_url, origin_path.to_string_lossy());
+    assert_eq!(remotes[1].name, "other");
+    assert_eq!(remotes[1].inbound_url, other_path.to_string_lossy());
+    assert_eq!(remotes[1].outbound_url, "/srv/git/push.git");
+}
+
+#[test]
+fn remote_branches_parse_repo_output() {
+    let repo = ScratchRepo::new();
+    repo.add_bare_remote("origin");
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    repo.commit_file("topic.txt", "topic\n", "Topic work");
+    repo.git(&["push", "-q", "origin", "topic"]);
+    repo.checkout("master");
+    let clone = repo.clone_repo();
+    clone.git(&["branch", "local-master", "origin/master"]);
+    let remote_branches = branches::parse_remote_branches(
+        &clone.git(&[
+            "for-each-ref",
+            branches::REMOTE_BRANCH_FORMAT,
+            "refs/remotes",
+        ]),
+        &clone.git(&["remote"]),
+        &clone.git(&[
+            "for-each-ref",
+            branches::LOCAL_UPSTREAM_FORMAT,
+            "refs/heads",
+        ]),
+    );
+    let names: Vec<String> = remote_branches.iter().map(|rb| rb.name()).collect();
+    assert_eq!(names, vec!["origin/master", "origin/topic"]);
+    assert_eq!(remote_branches[0].local.as_deref(), Some("master"));
+    assert_eq!(remote_branches[0].synopsis, "Initial commit");
+    assert_eq!(remote_branches[1].local, None);
+    assert_eq!(remote_branches[1].synopsis, "Topic work");
+}
@@ -0,0 +1,148 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Run interactive rebases, planned with RebaseSteps, in scratch repositories
+
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::rebase::{self, RebaseAction, RebaseStep};
+use gwsm_git_core::test_support::ScratchRepo;
+
+fn topic_repo(files: &[&str]) -> ScratchRepo {
+    let repo = ScratchRepo::new();
+    repo.git(&["checkout", "-q", "-b", "topic"]);
+    for file in files.iter() {
+        repo.commit_file(file, "content\n", &format!("Add {}", file));
+    }
+    repo
+}
+
+fn planned_steps(repo: &ScratchRepo) -> Vec<RebaseStep> {
+    let argv = rebase::rebase_commits_cmd("master").argv();
+    let args: Vec<&str> = argv[1..].iter().map(|arg| arg.as_str()).collect();
+    rebase::parse_rebase_commits(&repo.git(&args))
+}
+
+fn start_rebase(repo: &ScratchRepo, steps: &[RebaseStep]) -> std::process::Output {
+    rebase::check_rebase_plan(steps).unwrap();
+    let dir = rebase::rebase_plan_dir(&repo.ws_path()).unwrap();
+    let todo_path = rebase::write_rebase_plan(steps, &dir).unwrap();
+    rebase::interactive_rebase_cmd("master", &todo_path)
+        .command(&repo.ws_path())
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn interactive_rebase_follows_plan() {
+    let repo = topic_repo(&["a.txt", "b.txt", "c.txt", "d.txt"]);
+    let steps = planned_steps(&repo);
+    let subjects: Vec<&str> = steps.iter().map(|step| step.subject.as_str()).collect();
+    assert_eq!(
+        subjects,
+        vec!["Add a.txt", "Add b.txt", "Add c.txt", "Add d.txt"]
+    );
+    assert!(steps.iter().all(|step| step.action == RebaseAction::Pick));
+
+    let message = "Add a.txt (reworded)\n\nIt's \"quoted\"; $HOME\n";
+    let plan = vec![
+        steps[2].clone(),
+        RebaseStep {
+            action: RebaseAction::Reword,
+            message: Some(message.to_string()),
+            ..steps[0].clone()
+        },
+        RebaseStep {
+            action: RebaseAction::Fixup,
+            ..steps[1].clone()
+        },
+        RebaseStep {
+            action: RebaseAction::Drop,
+            ..steps[3].clone()
+        },
+    ];
+    let output = start_rebase(&repo, &plan);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let log = repo.git(&["log", "--reverse", "--format=%B%x00", "master..HEAD"]);
+    let messages: Vec<&str> = log
+        .split('\0')
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty())
+        .collect();
+    assert_eq!(messages, vec!["Add c.txt", message.trim()]);
+    let files = repo.git(&["ls-files"]);
+    assert!(files.contains("a.txt") && files.contains("b.txt") && files.contains("c.txt"));
+    assert!(!files.contains("d.txt"));
+}
+
+#[test]
+fn planned_steps_omit_merges_and_commits_already_upstream() {
+    let repo = topic_repo(&["a.txt", "b.txt"]);
+    repo.git(&["checkout", "-q", "-b", "side", "master"]);
+    repo.commit_file("side.txt", "side\n", "Add side.txt");
+    repo.git(&["checkout", "-q", "topic"]);
+    repo.git(&["merge", "-q", "--no-edit", "side"]);
+    repo.git(&["checkout", "-q", "master"]);
+    repo.git(&["cherry-pick", "topic~1"]);
+    repo.git(&["checkout", "-q", "topic"]);
+    let subjects: Vec<String> = planned_steps(&repo)
+        .into_iter()
+        .map(|step| step.subject)
+        .collect();
+    assert_eq!(subjects, vec!["Add a.txt", "Add side.txt"]);
+}
+
+#[test]
+fn rebase_plan_can_be_removed() {
+    let repo = topic_repo(&["a.txt"]);
+    let steps = planned_steps(&repo);
+    let dir = rebase::rebase_plan_dir(&repo.ws_path()).unwrap();
+    rebase::write_rebase_plan(&steps, &dir).unwrap();
+    assert!(dir.join("git-rebase-todo").exists());
+    rebase::remove_rebase_plan(&dir).unwrap();
+    assert!(!dir.exists());
+    // and it's not an error if there's nothing to remove
+    rebase::remove_rebase_plan(&dir).unwrap();
+}
+
+#[test]
+fn interactive_rebase_stops_for_edit() {
+    let repo = topic_repo(&["a.txt", "b.txt"]);
+    let mut plan = planned_steps(&repo);
+    plan[0].action = RebaseAction::Edit;
+    let output = start_rebase(&repo, &plan);
+    assert!(output.status.success());
+    assert!(repo
+        .git(&["status"])
+        .contains("interactive rebase in progress"));
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%s"]).trim_end(),
+        "Add a.txt"
+    );
+
+    let cmd = GitCommand::new("rebase")
+        .config("core.editor=true")
+        .arg("--continue");
+    let output = cmd.command(&repo.ws_path()).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        repo.git(&["log", "-1", "--format=%s"]).trim_end(),
+        "Add b.txt"
+    );
+    assert!(!repo.git(&["status"]).contains("rebase in progress"));
+}
@@ -13,19 +13,22 @@
 //limitations under the License.
 
 use std::cell::{Cell, Ref, RefCell};
-use std::collections::HashSet;
 use std::io::Write;
-use std::process::Command;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
-use regex::Regex;
+use xml::escape;
+
+use cub_diff_lib::diff::DiffPlusParser;
+use cub_diff_lib::lines::*;
+use cub_diff_lib_gtk::diff::DiffPlusNotebook;
 
 use pw_gix::{
     glib,
     glibx::*,
     gtk::{self, prelude::*},
     gtkx::{
+        dialog::*,
         list_store::{
             BufferedUpdate, MapManagedUpdate, RequiredMapAction, Row, RowBuffer, RowBufferCore,
         },
@@ -35,9 +38,20 @@ use pw_gix::{
     wrapper::*,
 };
 
+use gwsm_git_core::branches::{
+    parse_branches, parse_remote_branches, unmerged_commits_cmd, Branch, RemoteBranch,
+    BRANCH_FORMAT, LOCAL_UPSTREAM_FORMAT, REMOTE_BRANCH_FORMAT,
+};
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::workspace::WsPath;
+
 use crate::action_icons;
-use crate::events;
+use crate::config;
+use crate::events::{self, Event, Events};
 use crate::exec::ExecConsole;
+use crate::list_view;
+use crate::merge::MergeBranch;
+use crate::rebase::RebaseBranch;
 use crate::repos;
 
 #[derive(Debug, Default)]
@@ -46,11 +60,12 @@ struct BranchesRawData {
     merged_branches_text: String,
 }
 
-fn get_raw_data() -> (BranchesRawData, Vec<u8>) {
+fn get_raw_data(ws_path: &WsPath) -> (BranchesRawData, Vec<u8>) {
     let mut hasher = Hasher::new(Algorithm::SHA256);
-    let abt_output = Command::new("git")
+    let abt_output = ws_path
+        .git()
         .arg("branch")
-        .arg("-vv")
+        .arg(BRANCH_FORMAT)
         .output()
         .expect("getting all branches text failed");
     let abt_text: String = if abt_output.status.success() {
@@ -61,7 +76,8 @@ fn get_raw_data() -> (BranchesRawData, Vec<u8>) {
     } else {
         "".to_string()
     };
-    let mbt_output = Command::new("git")
+    let mbt_output = ws_path
+        .git()
         .arg("branch")
         .arg("--merged")
         .output()
@@ -81,44 +97,69 @@ fn get_raw_data() -> (BranchesRawData, Vec<u8>) {
     (raw_data, hasher.finish())
 }
 
-lazy_static! {
-    static ref ALL_BRANCHES_RE: Regex =
-        Regex::new(r"(([^ (]+)|(\([^)]+\)))\s+([a-fA-F0-9]{7}[a-fA-F0-9]*)?\s*([^\s].*)").unwrap();
+// The (one line) descriptions of the commits on "branch" that are not in HEAD
+fn unmerged_commits(ws_path: &WsPath, branch: &str) -> Vec<String> {
+    match unmerged_commits_cmd(branch).command(ws_path).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect(),
+        _ => vec![],
+    }
 }
 
-fn extract_branch_row(line: &str, merged_set: &HashSet<&str>) -> Row {
-    let is_current = line.starts_with('*');
-    let captures = ALL_BRANCHES_RE.captures(&line[2..]).unwrap();
-    let name = captures.get(1).unwrap().as_str();
-    let rev = captures.get(4).unwrap().as_str();
-    let synopsis = captures.get(5).unwrap().as_str();
-    let is_merged = merged_set.contains(name);
+fn local_branch_exists(ws_path: &WsPath, branch: &str) -> bool {
+    ws_path
+        .git()
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("refs/heads/{branch}"))
+        .output()
+        .map_or(false, |output| output.status.success())
+}
+
+fn branch_row(branch: &Branch) -> Row {
+    let name = &branch.name;
     let mut v = vec![];
     v.push(name.to_value());
-    if is_current {
+    let name = escape::escape_str_pcdata(name).to_string();
+    if branch.is_current {
         v.push("<b><span foreground=\"green\">*</span></b>".to_value());
         v.push(format!("<b><span foreground=\"green\">{name}</span></b>").to_value());
-    } else if is_merged {
+    } else if branch.is_merged {
         v.push("".to_value());
         v.push(format!("<span foreground=\"green\">{name}</span>").to_value());
     } else {
         v.push("".to_value());
         v.push(name.to_value());
     }
-    v.push(rev.to_value());
-    v.push(synopsis.to_value());
+    v.push(branch.rev.to_value());
+    v.push(branch.synopsis.to_value());
+    let upstream = match branch.upstream {
+        Some(ref upstream) if branch.upstream_gone => format!("{upstream} (gone)"),
+        Some(ref upstream) => upstream.to_string(),
+        None => "".to_string(),
+    };
+    v.push(upstream.to_value());
+    v.push(branch.ahead.to_value());
+    v.push(branch.behind.to_value());
+    v.push(branch.date.to_value());
+    v.push(branch.author.to_value());
     v
 }
 
 struct BranchesRowBuffer {
     row_buffer_core: Rc<RefCell<RowBufferCore<BranchesRawData>>>,
+    ws_path: WsPath,
 }
 
 impl BranchesRowBuffer {
-    fn new() -> Self {
+    fn new(ws_path: &WsPath) -> Self {
         let core = RowBufferCore::<BranchesRawData>::default();
         let buffer = Self {
             row_buffer_core: Rc::new(RefCell::new(core)),
+            ws_path: ws_path.clone(),
         };
         buffer.init();
         buffer
@@ -131,23 +172,22 @@ impl RowBuffer<BranchesRawData> for BranchesRowBuffer {
     }
 
     fn set_raw_data(&self) {
-        let (raw_data, digest) = get_raw_data();
+        let (raw_data, digest) = get_raw_data(&self.ws_path);
         let mut core = self.row_buffer_core.borrow_mut();
         core.set_raw_data(raw_data, digest);
     }
 
     fn finalise(&self) {
-        let mut rows: Vec<Row> = Vec::new();
-        {
+        let rows: Vec<Row> = {
             let core = self.row_buffer_core.borrow();
-            let mut merged_set: HashSet<&str> = HashSet::new();
-            for line in core.raw_data.merged_branches_text.lines() {
-                merged_set.insert(line[2..].trim_end());
-            }
-            for line in core.raw_data.all_branches_text.lines() {
-                rows.push(extract_branch_row(line, &merged_set))
-            }
-        }
+            parse_branches(
+                &core.raw_data.all_branches_text,
+                &core.raw_data.merged_branches_text,
+            )
+            .iter()
+            .map(branch_row)
+            .collect()
+        };
         let mut core = self.row_buffer_core.borrow_mut();
         core.rows = Rc::new(rows);
         core.set_is_finalised_true();
@@ -170,10 +210,21 @@ impl BufferedUpdate<BranchesRawData, gtk::ListStore> for BranchesNameListStore {
 }
 
 impl BranchesNameListStore {
-    pub fn new() -> BranchesNameListStore {
+    pub fn new(ws_path: &WsPath) -> BranchesNameListStore {
         Self {
-            list_store: gtk::ListStore::new(&[glib::Type::String; 5]),
-            branches_row_buffer: Rc::new(RefCell::new(BranchesRowBuffer::new())),
+            list_store: gtk::ListStore::new(&[
+                glib::Type::String,
+                glib::Type::String,
+                glib::Type::String,
+                glib::Type::String,
+                glib::Type::String,
+                glib::Type::String,
+                glib::Type::U32,
+                glib::Type::U32,
+                glib::Type::String,
+                glib::Type::String,
+            ]),
+            branches_row_buffer: Rc::new(RefCell::new(BranchesRowBuffer::new(ws_path))),
         }
     }
 }
@@ -182,6 +233,7 @@ impl BranchesNameListStore {
 pub struct BranchesNameTable {
     scrolled_window: gtk::ScrolledWindow,
     view: gtk::TreeView,
+    sort_model: gtk::TreeModelSort,
     list_store: RefCell<BranchesNameListStore>,
     required_map_action: Cell<RequiredMapAction>,
     exec_console: Rc<ExecConsole>,
@@ -209,11 +261,25 @@ impl MapManagedUpdate<BranchesNameListStore, BranchesRawData, gtk::ListStore>
     }
 }
 
+impl MergeBranch for BranchesNameTable {
+    fn exec_console(&self) -> &Rc<ExecConsole> {
+        &self.exec_console
+    }
+}
+
+impl RebaseBranch for BranchesNameTable {
+    fn exec_console(&self) -> &Rc<ExecConsole> {
+        &self.exec_console
+    }
+}
+
 impl BranchesNameTable {
     pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<BranchesNameTable> {
-        let list_store = RefCell::new(BranchesNameListStore::new());
+        let list_store = RefCell::new(BranchesNameListStore::new(&exec_console.ws_path));
 
-        let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
+        // Sort in the view (rather than the store) so that updates aren't disturbed
+        let sort_model = gtk::TreeModelSort::new(&list_store.borrow().get_list_store());
+        let view = gtk::TreeView::with_model(&sort_model);
         view.set_headers_visible(true);
 
         view.get_selection().set_mode(gtk::SelectionMode::Single);
@@ -222,6 +288,7 @@ impl BranchesNameTable {
         col.set_title("Name");
         col.set_expand(false);
         col.set_resizable(false);
+        col.set_sort_column_id(0);
 
         let cell = gtk::CellRendererText::new();
         cell.set_property_editable(false);
@@ -247,10 +314,34 @@ impl BranchesNameTable {
 
         view.append_column(&col);
 
+        for (title, column) in [
+            ("Upstream", 5),
+            ("Ahead", 6),
+            ("Behind", 7),
+            ("Date", 8),
+            ("Author", 9),
+        ]
+        .iter()
+        {
+            let col = gtk::TreeViewColumn::new();
+            col.set_title(title);
+            col.set_expand(false);
+            col.set_resizable(false);
+            col.set_sort_column_id(*column);
+
+            let cell = gtk::CellRendererText::new();
+            cell.set_property_editable(false);
+            col.pack_start(&cell, false);
+            col.add_attribute(&cell, "text", *column);
+
+            view.append_column(&col);
+        }
+
         let col = gtk::TreeViewColumn::new();
         col.set_title("Synopsis");
         col.set_expand(false);
         col.set_resizable(false);
+        col.set_sort_column_id(4);
 
         let cell = gtk::CellRendererText::new();
         cell.set_property_editable(false);
@@ -278,6 +369,7 @@ impl BranchesNameTable {
         let table = Rc::new(BranchesNameTable {
             scrolled_window,
             view,
+            sort_model,
             list_store,
             required_map_action,
             exec_console: Rc::clone(exec_console),
@@ -287,11 +379,18 @@ impl BranchesNameTable {
         let table_clone = Rc::clone(&table);
         table.exec_console.event_notifier.add_notification_cb(
             events::EV_AUTO_UPDATE
+                | events::EV_CHECKOUT
                 | events::EV_BRANCHES_CHANGE
                 | events::EV_COMMIT
                 | events::EV_PULL
                 | events::EV_PUSH,
-            Box::new(move |_| table_clone.auto_update()),
+            Box::new(move |events| {
+                table_clone.auto_update();
+                let ref_names = events.ref_names();
+                if let Some(branch) = events.new_head().or(ref_names.first().copied()) {
+                    table_clone.select_branch(branch);
+                }
+            }),
         );
         let table_clone = Rc::clone(&table);
         table.view.connect_map(move |_| table_clone.on_map_action());
@@ -315,18 +414,14 @@ impl BranchesNameTable {
                 repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
             )
             .connect_activate(move |_| {
-                let selection = table_clone.view.get_selection();
-                let branch = if let Some((store, iter)) = selection.get_selected() {
-                    store.get_value(&iter, 0).get::<String>().unwrap()
-                } else {
-                    table_clone.hovered_branch.borrow().clone()
-                };
-                if let Some(branch) = branch {
-                    let cmd = format!("git checkout {}", shlex::quote(&branch));
-                    let result = table_clone
-                        .exec_console
-                        .exec_cmd(&cmd, events::EV_BRANCHES_CHANGE | events::EV_CHECKOUT);
-                    table_clone.report_any_command_problems(&cmd, &result);
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    let cmd = GitCommand::new("checkout").arg(&branch);
+                    let result = table_clone.exec_console.exec_cmd(
+                        &cmd,
+                        Events::from(events::EV_BRANCHES_CHANGE)
+                            .with(Event::Checkout(Some(branch.clone()))),
+                    );
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
                 }
             });
 
@@ -344,21 +439,164 @@ impl BranchesNameTable {
                 repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
             )
             .connect_activate(move |_| {
-                let selection = table_clone.view.get_selection();
-                let branch = if let Some((store, iter)) = selection.get_selected() {
-                    store.get_value(&iter, 0).get::<String>().unwrap()
-                } else {
-                    table_clone.hovered_branch.borrow().clone()
-                };
-                if let Some(branch) = branch {
-                    let cmd = format!("git merge {}", shlex::quote(&branch));
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    table_clone.merge_branch(&branch);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "rebase",
+                &(
+                    "Rebase Current Branch Onto",
+                    None,
+                    Some("Rebase the current branch onto the selected/indicated branch"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + repos::SAV_NOT_OP_IN_PROGRESS + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    table_clone.rebase_onto(&branch);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "rebase-interactive",
+                &(
+                    "Rebase Current Branch Onto (Interactive)",
+                    None,
+                    Some("Edit the plan for rebasing the current branch onto the selected/indicated branch and then run it"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + repos::SAV_NOT_OP_IN_PROGRESS + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    table_clone.interactive_rebase(&branch);
+                }
+            });
+
+        table.popup_menu.append_separator();
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "branch-from",
+                &(
+                    "Create Branch From Here",
+                    None,
+                    Some("Create a new branch based on the selected/indicated branch"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(base) = table_clone.get_chosen_branch() {
+                    let question = format!("Name of new branch based on \"{base}\":");
+                    let (response, name) = table_clone.ask_string_cancel_or_ok(&question);
+                    if response == gtk::ResponseType::Ok {
+                        if let Some(branch) = name {
+                            let cmd = GitCommand::new("branch").arg(&branch).arg(&base);
+                            let result = table_clone
+                                .exec_console
+                                .exec_cmd(&cmd, Event::BranchesChange(Some(branch)));
+                            table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                        }
+                    }
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "rename",
+                &("Rename", None, Some("Rename the selected/indicated branch")).into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    let question = format!("Rename branch \"{branch}\" to:");
+                    let (response, name) = table_clone.ask_string_cancel_or_ok(&question);
+                    if response == gtk::ResponseType::Ok {
+                        if let Some(new_name) = name {
+                            let cmd = GitCommand::new("branch")
+                                .arg("-m")
+                                .arg(&branch)
+                                .arg(&new_name);
+                            let result = table_clone
+                                .exec_console
+                                .exec_cmd(&cmd, Event::BranchesChange(Some(new_name)));
+                            table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                        }
+                    }
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "set-upstream",
+                &(
+                    "Set Upstream",
+                    None,
+                    Some("Set the upstream (tracked) branch of the selected/indicated branch"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    let question =
+                        format!("Upstream for branch \"{branch}\" (e.g. origin/{branch}):");
+                    let (response, upstream) = table_clone.ask_string_cancel_or_ok(&question);
+                    if response == gtk::ResponseType::Ok {
+                        if let Some(upstream) = upstream {
+                            let cmd = GitCommand::new("branch")
+                                .arg(format!("--set-upstream-to={upstream}"))
+                                .arg(&branch);
+                            let result = table_clone
+                                .exec_console
+                                .exec_cmd(&cmd, Event::BranchesChange(Some(branch)));
+                            table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                        }
+                    }
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "unset-upstream",
+                &(
+                    "Unset Upstream",
+                    None,
+                    Some("Stop the selected/indicated branch tracking its upstream branch"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    let cmd = GitCommand::new("branch")
+                        .arg("--unset-upstream")
+                        .arg(&branch);
                     let result = table_clone
                         .exec_console
-                        .exec_cmd(&cmd, events::EV_BRANCHES_CHANGE | events::EV_FILES_CHANGE);
-                    table_clone.report_any_command_problems(&cmd, &result);
+                        .exec_cmd(&cmd, Event::BranchesChange(Some(branch)));
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
                 }
             });
 
+        table.popup_menu.append_separator();
         let table_clone = Rc::clone(&table);
         table
             .popup_menu
@@ -368,18 +606,51 @@ impl BranchesNameTable {
                 repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
             )
             .connect_activate(move |_| {
-                let selection = table_clone.view.get_selection();
-                let branch = if let Some((store, iter)) = selection.get_selected() {
-                    store.get_value(&iter, 0).get::<String>().unwrap()
-                } else {
-                    table_clone.hovered_branch.borrow().clone()
-                };
-                if let Some(branch) = branch {
-                    let cmd = format!("git branch -d {}", shlex::quote(&branch));
-                    let result = table_clone
-                        .exec_console
-                        .exec_cmd(&cmd, events::EV_BRANCHES_CHANGE | events::EV_FILES_CHANGE);
-                    table_clone.report_any_command_problems(&cmd, &result);
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    let cmd = GitCommand::new("branch").arg("-d").arg(&branch);
+                    let result = table_clone.exec_console.exec_cmd(
+                        &cmd,
+                        Events::from(events::EV_FILES_CHANGE)
+                            .with(Event::BranchesChange(Some(branch.clone()))),
+                    );
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "force-delete",
+                &(
+                    "Force Delete",
+                    None,
+                    Some("Delete the selected/indicated branch even if it has unmerged commits"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(branch) = table_clone.get_chosen_branch() {
+                    let cmd = GitCommand::new("branch").arg("-D").arg(&branch);
+                    let msg = format!("Confirm: {cmd}");
+                    let unmerged = unmerged_commits(&table_clone.exec_console.ws_path, &branch);
+                    let explanation = if unmerged.is_empty() {
+                        None
+                    } else {
+                        Some(format!(
+                            "The following commits will be lost:\n{}",
+                            unmerged.join("\n")
+                        ))
+                    };
+                    if table_clone.ask_confirm_action(&msg, explanation.as_deref()) {
+                        let result = table_clone.exec_console.exec_cmd(
+                            &cmd,
+                            Events::from(events::EV_FILES_CHANGE)
+                                .with(Event::BranchesChange(Some(branch.clone()))),
+                        );
+                        table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                    }
                 }
             });
 
@@ -408,6 +679,487 @@ impl BranchesNameTable {
         self.popup_menu.update_condns(condns);
         *self.hovered_branch.borrow_mut() = branch;
     }
+
+    fn get_chosen_branch(&self) -> Option<String> {
+        let selection = self.view.get_selection();
+        if let Some((store, iter)) = selection.get_selected() {
+            store.get_value(&iter, 0).get::<String>().unwrap()
+        } else {
+            self.hovered_branch.borrow().clone()
+        }
+    }
+
+    // Select (and reveal) the named branch if it's in the table
+    fn select_branch(&self, branch: &str) {
+        let store = self.list_store.borrow().get_list_store();
+        if let Some(iter) = list_view::find_row(&store, 0, branch) {
+            if let Some(sort_iter) = self.sort_model.convert_child_iter_to_iter(&iter) {
+                list_view::select_and_show(&self.view, &sort_iter);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RemoteBranchesRawData {
+    remote_refs_text: String,
+    remotes_text: String,
+    local_branches_text: String,
+}
+
+fn get_remote_branches_raw_data(ws_path: &WsPath) -> (RemoteBranchesRawData, Vec<u8>) {
+    let mut hasher = Hasher::new(Algorithm::SHA256);
+    let mut get_text = |args: &[&str]| -> String {
+        let output = ws_path
+            .git()
+            .args(args)
+            .output()
+            .expect("getting remote branches data failed");
+        if output.status.success() {
+            hasher.write_all(&output.stdout).expect("hasher blew up!!!");
+            String::from_utf8_lossy(&output.stdout).to_string()
+        } else {
+            "".to_string()
+        }
+    };
+    let raw_data = RemoteBranchesRawData {
+        remote_refs_text: get_text(&["for-each-ref", REMOTE_BRANCH_FORMAT, "refs/remotes"]),
+        remotes_text: get_text(&["remote"]),
+        local_branches_text: get_text(&["for-each-ref", LOCAL_UPSTREAM_FORMAT, "refs/heads"]),
+    };
+    (raw_data, hasher.finish())
+}
+
+// Only the first row of each remote's group displays the remote's name
+fn remote_branch_rows(remote_branches: &[RemoteBranch]) -> Vec<Row> {
+    let mut rows = vec![];
+    let mut last_remote: Option<&str> = None;
+    for remote_branch in remote_branches.iter() {
+        let remote_markup = if last_remote == Some(remote_branch.remote.as_str()) {
+            "".to_string()
+        } else {
+            format!(
+                "<b>{}</b>",
+                escape::escape_str_pcdata(&remote_branch.remote)
+            )
+        };
+        last_remote = Some(&remote_branch.remote);
+        rows.push(vec![
+            remote_branch.name().to_value(),
+            remote_branch.remote.to_value(),
+            remote_branch.branch.to_value(),
+            remote_branch.local.as_deref().unwrap_or("").to_value(),
+            remote_markup.to_value(),
+            remote_branch.rev.to_value(),
+            remote_branch.synopsis.to_value(),
+        ]);
+    }
+    rows
+}
+
+struct RemoteBranchesRowBuffer {
+    row_buffer_core: Rc<RefCell<RowBufferCore<RemoteBranchesRawData>>>,
+    ws_path: WsPath,
+}
+
+impl RemoteBranchesRowBuffer {
+    fn new(ws_path: &WsPath) -> Self {
+        let core = RowBufferCore::<RemoteBranchesRawData>::default();
+        let buffer = Self {
+            row_buffer_core: Rc::new(RefCell::new(core)),
+            ws_path: ws_path.clone(),
+        };
+        buffer.init();
+        buffer
+    }
+}
+
+impl RowBuffer<RemoteBranchesRawData> for RemoteBranchesRowBuffer {
+    fn get_core(&self) -> Rc<RefCell<RowBufferCore<RemoteBranchesRawData>>> {
+        self.row_buffer_core.clone()
+    }
+
+    fn set_raw_data(&self) {
+        let (raw_data, digest) = get_remote_branches_raw_data(&self.ws_path);
+        let mut core = self.row_buffer_core.borrow_mut();
+        core.set_raw_data(raw_data, digest);
+    }
+
+    fn finalise(&self) {
+        let rows: Vec<Row> = {
+            let core = self.row_buffer_core.borrow();
+            remote_branch_rows(&parse_remote_branches(
+                &core.raw_data.remote_refs_text,
+                &core.raw_data.remotes_text,
+                &core.raw_data.local_branches_text,
+            ))
+        };
+        let mut core = self.row_buffer_core.borrow_mut();
+        core.rows = Rc::new(rows);
+        core.set_is_finalised_true();
+    }
+}
+
+struct RemoteBranchesListStore {
+    list_store: gtk::ListStore,
+    remote_branches_row_buffer: Rc<RefCell<RemoteBranchesRowBuffer>>,
+}
+
+impl BufferedUpdate<RemoteBranchesRawData, gtk::ListStore> for RemoteBranchesListStore {
+    fn get_list_store(&self) -> gtk::ListStore {
+        self.list_store.clone()
+    }
+
+    fn get_row_buffer(&self) -> Rc<RefCell<dyn RowBuffer<RemoteBranchesRawData>>> {
+        self.remote_branches_row_buffer.clone()
+    }
+}
+
+impl RemoteBranchesListStore {
+    pub fn new(ws_path: &WsPath) -> RemoteBranchesListStore {
+        Self {
+            list_store: gtk::ListStore::new(&[glib::Type::String; 7]),
+            remote_branches_row_buffer: Rc::new(RefCell::new(RemoteBranchesRowBuffer::new(
+                ws_path,
+            ))),
+        }
+    }
+}
+
+// The remote, branch and local counterpart of a remote-tracking branch
+struct ChosenRemoteBranch {
+    name: String,
+    remote: String,
+    branch: String,
+    local: Option<String>,
+}
+
+#[derive(PWO, Wrapper)]
+pub struct RemoteBranchesTable {
+    scrolled_window: gtk::ScrolledWindow,
+    view: gtk::TreeView,
+    list_store: RefCell<RemoteBranchesListStore>,
+    required_map_action: Cell<RequiredMapAction>,
+    exec_console: Rc<ExecConsole>,
+    popup_menu: ManagedMenu,
+    hovered_branch: RefCell<Option<String>>,
+}
+
+impl MapManagedUpdate<RemoteBranchesListStore, RemoteBranchesRawData, gtk::ListStore>
+    for RemoteBranchesTable
+{
+    fn buffered_update(&self) -> Ref<'_, RemoteBranchesListStore> {
+        self.list_store.borrow()
+    }
+
+    fn is_mapped(&self) -> bool {
+        self.view.get_mapped()
+    }
+
+    fn get_required_map_action(&self) -> RequiredMapAction {
+        self.required_map_action.get()
+    }
+
+    fn set_required_map_action(&self, action: RequiredMapAction) {
+        self.required_map_action.set(action);
+    }
+}
+
+impl RemoteBranchesTable {
+    pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<RemoteBranchesTable> {
+        let list_store = RefCell::new(RemoteBranchesListStore::new(&exec_console.ws_path));
+
+        let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
+        view.set_headers_visible(true);
+
+        view.get_selection().set_mode(gtk::SelectionMode::Single);
+
+        for (title, attribute, column) in [
+            ("Remote", "markup", 4),
+            ("Branch", "text", 2),
+            ("Local", "text", 3),
+            ("Rev", "text", 5),
+            ("Synopsis", "text", 6),
+        ]
+        .iter()
+        {
+            let col = gtk::TreeViewColumn::new();
+            col.set_title(title);
+            col.set_expand(false);
+            col.set_resizable(false);
+
+            let cell = gtk::CellRendererText::new();
+            cell.set_property_editable(false);
+            col.pack_start(&cell, false);
+            col.add_attribute(&cell, attribute, *column);
+
+            view.append_column(&col);
+        }
+
+        view.show_all();
+
+        list_store.borrow().repopulate();
+
+        let required_map_action = Cell::new(RequiredMapAction::Nothing);
+
+        let popup_menu = ManagedMenuBuilder::new()
+            .widget_states_controlled(WidgetStatesControlled::Sensitivity)
+            .selection(&view.get_selection())
+            .change_notifier(&exec_console.changed_condns_notifier)
+            .build();
+
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.add(&view);
+
+        let table = Rc::new(RemoteBranchesTable {
+            scrolled_window,
+            view,
+            list_store,
+            required_map_action,
+            exec_console: Rc::clone(exec_console),
+            popup_menu,
+            hovered_branch: RefCell::new(None),
+        });
+        let table_clone = Rc::clone(&table);
+        table.exec_console.event_notifier.add_notification_cb(
+            events::EV_AUTO_UPDATE
+                | events::EV_BRANCHES_CHANGE
+                | events::EV_PULL
+                | events::EV_PUSH
+                | events::EV_REMOTES_CHANGE,
+            Box::new(move |_| table_clone.auto_update()),
+        );
+        let table_clone = Rc::clone(&table);
+        table.view.connect_map(move |_| table_clone.on_map_action());
+        let table_clone = Rc::clone(&table);
+        table.exec_console.event_notifier.add_notification_cb(
+            events::EV_CHANGE_DIR,
+            Box::new(move |_| table_clone.repopulate()),
+        );
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "checkout",
+                &(
+                    "Checkout As Local Branch",
+                    None,
+                    Some("Create (and switch to) a local branch tracking the selected/indicated remote branch"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(chosen) = table_clone.get_chosen_remote_branch() {
+                    let ws_path = &table_clone.exec_console.ws_path;
+                    let mut local = chosen.branch.clone();
+                    while local_branch_exists(ws_path, &local) {
+                        let question = format!(
+                            "A local branch \"{local}\" already exists.\nName for the local branch tracking \"{}\":",
+                            chosen.name
+                        );
+                        match table_clone.ask_string_cancel_or_ok(&question) {
+                            (gtk::ResponseType::Ok, Some(name)) => local = name,
+                            _ => return,
+                        }
+                    }
+                    let cmd = GitCommand::new("checkout")
+                        .arg("-b")
+                        .arg(&local)
+                        .arg("--track")
+                        .arg(&chosen.name);
+                    let result = table_clone.exec_console.exec_cmd(
+                        &cmd,
+                        Events::from(Event::BranchesChange(Some(local.clone())))
+                            .with(Event::Checkout(Some(local))),
+                    );
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "compare",
+                &(
+                    "Compare With Local",
+                    None,
+                    Some("Show the differences between the selected/indicated remote branch and its local counterpart"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(chosen) = table_clone.get_chosen_remote_branch() {
+                    match chosen.local {
+                        Some(ref local) => table_clone.show_comparison(local, &chosen.name),
+                        None => {
+                            let msg = format!("\"{}\" has no local counterpart.", chosen.name);
+                            table_clone.inform_user(&msg, None)
+                        }
+                    }
+                }
+            });
+
+        table.popup_menu.append_separator();
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "delete",
+                &(
+                    "Delete From Remote",
+                    None,
+                    Some("Delete the selected/indicated branch from its remote repository"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(chosen) = table_clone.get_chosen_remote_branch() {
+                    let cmd = GitCommand::new("push")
+                        .arg("--delete")
+                        .arg(&chosen.remote)
+                        .arg(&chosen.branch);
+                    let msg = format!("Confirm: {cmd}");
+                    let explanation = format!(
+                        "Branch \"{}\" will be deleted from remote \"{}\" for everyone.",
+                        chosen.branch, chosen.remote
+                    );
+                    if table_clone.ask_confirm_action(&msg, Some(&explanation)) {
+                        let cursor = table_clone.show_busy();
+                        let result = table_clone
+                            .exec_console
+                            .exec_cmd(&cmd, events::EV_PUSH | events::EV_BRANCHES_CHANGE);
+                        table_clone.unshow_busy(cursor);
+                        table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                    }
+                }
+            });
+
+        let table_clone = table.clone();
+        table.view.connect_button_press_event(move |view, event| {
+            if event.get_button() == 3 {
+                let branch = get_row_item_for_event!(view, event, String, 0);
+                table_clone.set_hovered_branch(branch);
+                table_clone.popup_menu.popup_at_event(event);
+                return Inhibit(true);
+            } else if event.get_button() == 2 {
+                table_clone.view.get_selection().unselect_all();
+                return Inhibit(true);
+            }
+            Inhibit(false)
+        });
+
+        table
+    }
+
+    fn set_hovered_branch(&self, branch: Option<String>) {
+        let condns = self
+            .view
+            .get_selection()
+            .get_masked_conditions_with_hover_ok(branch.is_some());
+        self.popup_menu.update_condns(condns);
+        *self.hovered_branch.borrow_mut() = branch;
+    }
+
+    fn get_chosen_remote_branch(&self) -> Option<ChosenRemoteBranch> {
+        let store = self.list_store.borrow().get_list_store();
+        let iter = if let Some((_, iter)) = self.view.get_selection().get_selected() {
+            iter
+        } else {
+            let hovered_branch = self.hovered_branch.borrow().clone()?;
+            list_view::find_row(&store, 0, &hovered_branch)?
+        };
+        let get_string = |column: i32| -> String {
+            store
+                .get_value(&iter, column)
+                .get::<String>()
+                .unwrap()
+                .unwrap_or_default()
+        };
+        let local = get_string(3);
+        Some(ChosenRemoteBranch {
+            name: get_string(0),
+            remote: get_string(1),
+            branch: get_string(2),
+            local: if local.is_empty() { None } else { Some(local) },
+        })
+    }
+
+    fn show_comparison(&self, local: &str, remote_branch: &str) {
+        let ws_path = &self.exec_console.ws_path;
+        let counts = ws_path
+            .git()
+            .arg("rev-list")
+            .arg("--left-right")
+            .arg("--count")
+            .arg(format!("{local}...{remote_branch}"))
+            .output()
+            .expect("\"git rev-list --left-right --count\" blew up!!!");
+        let summary = String::from_utf8_lossy(&counts.stdout)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let summary = match summary.split_once(' ') {
+            Some((ahead, behind)) => format!(
+                "\"{local}\" has {ahead} commit(s) not in \"{remote_branch}\" which has {behind} commit(s) not in \"{local}\"."
+            ),
+            None => format!("Unable to compare \"{local}\" and \"{remote_branch}\"."),
+        };
+        let output = ws_path
+            .git()
+            .arg("diff")
+            .arg("--no-ext-diff")
+            .arg("--src-prefix=a/")
+            .arg("--dst-prefix=b/")
+            .arg(local)
+            .arg(remote_branch)
+            .output()
+            .expect("\"git diff <local> <remote branch>\" blew up!!!");
+        let text = String::from_utf8_lossy(&output.stdout).to_string();
+        let lines = Lines::from_string(&text);
+        let diff_plus_parser = DiffPlusParser::new();
+        match diff_plus_parser.parse_lines(&lines) {
+            Ok(ref diff_pluses) => {
+                let diff_notebook = DiffPlusNotebook::new(1);
+                diff_notebook.repopulate(diff_pluses);
+                let subtitle = format!("compare: {local} {remote_branch}");
+                let title = config::window_title(ws_path, Some(&subtitle));
+                let dialog = self
+                    .new_dialog_builder()
+                    .title(&title)
+                    .destroy_with_parent(true)
+                    .build();
+                dialog.add_button("Close", gtk::ResponseType::Close);
+                dialog.get_content_area().pack_start(
+                    &gtk::Label::new(Some(&summary)),
+                    false,
+                    false,
+                    0,
+                );
+                dialog
+                    .get_content_area()
+                    .pack_start(diff_notebook.pwo(), true, true, 0);
+                dialog.get_content_area().pack_start(
+                    diff_notebook.tws_count_display().pwo(),
+                    false,
+                    false,
+                    0,
+                );
+                dialog.set_size_from_recollections("remote:branch:compare:dialog", (600, 300));
+                dialog.get_content_area().show_all();
+                dialog.show()
+            }
+            Err(err) => {
+                let msg = format!("{local} {remote_branch}: Malformed diff text");
+                self.report_error(&msg, &err);
+            }
+        }
+    }
 }
 
 #[derive(PWO, Wrapper)]
@@ -470,15 +1222,19 @@ impl BranchButton {
         if result == gtk::ResponseType::Ok {
             let branch_name = branch_name.get_text();
             if checkout_new_branch.get_active() {
-                let cmd = format!("git checkout -b {branch_name}");
+                let cmd = GitCommand::new("checkout").arg("-b").arg(&branch_name);
+                let result = self.exec_console.exec_cmd(
+                    &cmd,
+                    Events::from(Event::BranchesChange(Some(branch_name.to_string())))
+                        .with(Event::Checkout(Some(branch_name.to_string()))),
+                );
+                self.report_any_command_problems(&cmd.to_string(), &result);
+            } else {
+                let cmd = GitCommand::new("branch").arg(&branch_name);
                 let result = self
                     .exec_console
-                    .exec_cmd(&cmd, events::EV_BRANCHES_CHANGE | events::EV_CHECKOUT);
-                self.report_any_command_problems(&cmd, &result);
-            } else {
-                let cmd = format!("git branch {branch_name}");
-                let result = self.exec_console.exec_cmd(&cmd, events::EV_BRANCHES_CHANGE);
-                self.report_any_command_problems(&cmd, &result);
+                    .exec_cmd(&cmd, Event::BranchesChange(Some(branch_name.to_string())));
+                self.report_any_command_problems(&cmd.to_string(), &result);
             }
         }
         unsafe { dialog.destroy() };
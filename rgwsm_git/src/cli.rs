@@ -0,0 +1,115 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+
+use crate::config::APP_NAME;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct CliOptions {
+    pub workspace_dir: Option<String>,
+    pub config_dir: Option<String>,
+    pub open_window: Option<String>,
+}
+
+fn workspace_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("workspace")
+        .value_name("DIR")
+        .index(1)
+        .help("The git workspace (or directory) to open")
+}
+
+fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    App::new(APP_NAME)
+        .version(crate_version!())
+        .about("A GUI for managing git workspaces")
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .arg(
+            Arg::with_name("config_dir")
+                .long("config-dir")
+                .value_name("DIR")
+                .takes_value(true)
+                .global(true)
+                .help("Use DIR for configuration data (overrides RGWSM_GIT_CONFIG_DIR)"),
+        )
+        .arg(workspace_arg())
+        .subcommand(
+            SubCommand::with_name("commit")
+                .about("Open straight to the commit window")
+                .arg(workspace_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Open straight to the diff window")
+                .arg(workspace_arg()),
+        )
+}
+
+fn options_from_matches(matches: &ArgMatches<'_>) -> CliOptions {
+    let mut options = CliOptions {
+        workspace_dir: matches.value_of("workspace").map(|s| s.to_string()),
+        config_dir: matches.value_of("config_dir").map(|s| s.to_string()),
+        open_window: None,
+    };
+    if let (name, Some(sub_matches)) = matches.subcommand() {
+        options.open_window = Some(name.to_string());
+        if let Some(dir) = sub_matches.value_of("workspace") {
+            options.workspace_dir = Some(dir.to_string());
+        }
+        if let Some(dir) = sub_matches.value_of("config_dir") {
+            options.config_dir = Some(dir.to_string());
+        }
+    }
+    options
+}
+
+// Exits the process after printing help or version information (or usage errors).
+pub fn get_cli_options() -> CliOptions {
+    options_from_matches(&cli_app().get_matches())
+}
+
+// For arguments forwarded from another invocation (which has already validated them)
+pub fn parse_cli_options(args: &[String]) -> Result<CliOptions, clap::Error> {
+    Ok(options_from_matches(
+        &cli_app().get_matches_from_safe(args)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> CliOptions {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        parse_cli_options(&args).unwrap()
+    }
+
+    #[test]
+    fn cli_options_work() {
+        assert_eq!(parse(&["rgwsm_git"]), CliOptions::default());
+        let options = parse(&["rgwsm_git", "--config-dir", "/tmp/cfg", "~/SRC/repo"]);
+        assert_eq!(options.workspace_dir, Some("~/SRC/repo".to_string()));
+        assert_eq!(options.config_dir, Some("/tmp/cfg".to_string()));
+        assert_eq!(options.open_window, None);
+        let options = parse(&["rgwsm_git", "commit", "repo"]);
+        assert_eq!(options.workspace_dir, Some("repo".to_string()));
+        assert_eq!(options.open_window, Some("commit".to_string()));
+        let options = parse(&["rgwsm_git", "diff", "--config-dir", "cfg"]);
+        assert_eq!(options.config_dir, Some("cfg".to_string()));
+        assert_eq!(options.open_window, Some("diff".to_string()));
+        assert!(cli_app()
+            .get_matches_from_safe(&["rgwsm_git", "--bogus"])
+            .is_err());
+    }
+}
@@ -14,7 +14,6 @@
 
 use std::cell::RefCell;
 use std::io::Write;
-use std::process::Command;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
@@ -30,10 +29,14 @@ use pw_gix::{
     wrapper::*,
 };
 
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::workspace::WsPath;
+
 use crate::action_icons;
 use crate::config;
 use crate::events;
 use crate::exec::ExecConsole;
+use crate::hunks::{HunkSelectionWidget, StageHunks};
 use crate::message::last_commit_message;
 use crate::repos;
 
@@ -65,7 +68,8 @@ impl CommitButton {
         db.window
             .set_geometry_from_recollections("commit:window", (700, 600));
         db.window.set_destroy_with_parent(true);
-        db.window.set_title(&config::window_title(Some("commit")));
+        db.window
+            .set_title(&config::window_title(&exec_console.ws_path, Some("commit")));
         db.window.connect_delete_event(move |w, _| {
             w.hide_on_delete();
             Inhibit(true)
@@ -78,9 +82,10 @@ impl CommitButton {
         exec_console.event_notifier.add_notification_cb(
             events::EV_CHANGE_DIR,
             Box::new(move |_| {
-                db_clone
-                    .window
-                    .set_title(&config::window_title(Some("diff")));
+                db_clone.window.set_title(&config::window_title(
+                    &db_clone._exec_console.ws_path,
+                    Some("commit"),
+                ));
             }),
         );
 
@@ -90,12 +95,20 @@ impl CommitButton {
 
         db
     }
+
+    pub fn present_window(&self) {
+        self.window.present();
+    }
 }
 
 #[derive(PWO, Wrapper)]
 struct IndexDiffWidget {
     v_box: gtk::Box,
+    choose_hunks_button: gtk::ToggleButton,
+    unstage_chosen_button: gtk::Button,
+    diff_stack: gtk::Stack,
     diff_notebook: Rc<DiffPlusNotebook>,
+    hunk_selection: Rc<HunkSelectionWidget>,
     current_digest: RefCell<Vec<u8>>,
     exec_console: Rc<ExecConsole>,
     diff_plus_parser: DiffPlusParser,
@@ -106,18 +119,43 @@ impl IndexDiffWidget {
         let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
         let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         h_box.pack_start(&gtk::Label::new(Some("Diffs")), false, false, 0);
+        let choose_hunks_button = gtk::ToggleButton::with_label("Choose Hunks");
+        choose_hunks_button.set_tooltip_text(Some(
+            "Choose hunks or lines from the staged diff to be unstaged.",
+        ));
+        h_box.pack_start(&choose_hunks_button, false, false, 0);
+        let unstage_chosen_button = gtk::Button::with_label("Unstage Chosen");
+        unstage_chosen_button
+            .set_tooltip_text(Some("Remove the chosen hunks or lines from the index."));
+        unstage_chosen_button.set_sensitive(false);
+        h_box.pack_start(&unstage_chosen_button, false, false, 0);
         v_box.pack_start(&h_box, false, false, 0);
         let diff_notebook = DiffPlusNotebook::new(1);
         h_box.pack_end(diff_notebook.tws_count_display().pwo(), false, false, 0);
-        v_box.pack_start(diff_notebook.pwo(), true, true, 0);
+        let hunk_selection = HunkSelectionWidget::new();
+        let diff_stack = gtk::Stack::new();
+        diff_stack.add_named(diff_notebook.pwo(), "diffs");
+        diff_stack.add_named(hunk_selection.pwo(), "hunks");
+        v_box.pack_start(&diff_stack, true, true, 0);
         let idw = Rc::new(Self {
             v_box,
+            choose_hunks_button,
+            unstage_chosen_button,
+            diff_stack,
             diff_notebook,
+            hunk_selection,
             current_digest: RefCell::new(Vec::new()),
             exec_console: Rc::clone(exec_console),
             diff_plus_parser: DiffPlusParser::new(),
         });
 
+        let idw_clone = Rc::clone(&idw);
+        idw.choose_hunks_button
+            .connect_toggled(move |button| idw_clone.set_choosing_hunks(button.get_active()));
+        let idw_clone = Rc::clone(&idw);
+        idw.unstage_chosen_button
+            .connect_clicked(move |_| idw_clone.apply_chosen_hunks(true));
+
         let idw_clone = Rc::clone(&idw);
         idw.exec_console.event_notifier.add_notification_cb(
             events::EV_AUTO_UPDATE | events::EV_CHECKOUT | events::EV_FILES_CHANGE,
@@ -139,10 +177,22 @@ impl IndexDiffWidget {
         idw
     }
 
+    fn set_choosing_hunks(&self, choosing: bool) {
+        if choosing {
+            self.update_hunk_selection(true);
+            self.diff_stack.set_visible_child_name("hunks");
+        } else {
+            self.diff_stack.set_visible_child_name("diffs");
+        }
+        self.unstage_chosen_button.set_sensitive(choosing);
+    }
+
     fn get_diff_text(&self) -> (String, Vec<u8>) {
-        let mut cmd = Command::new("git");
+        let mut cmd = self.exec_console.ws_path.git();
         cmd.arg("diff")
             .arg("--no-ext-diff")
+            .arg("--src-prefix=a/")
+            .arg("--dst-prefix=b/")
             .arg("-M")
             .arg("--staged");
         let output = cmd.output().expect("\"git diff\" blew up");
@@ -159,6 +209,9 @@ impl IndexDiffWidget {
     }
 
     fn repopulate(&self) {
+        if self.choose_hunks_button.get_active() {
+            self.update_hunk_selection(true);
+        }
         let (text, new_digest) = self.get_diff_text();
         *self.current_digest.borrow_mut() = new_digest;
         let lines = Lines::from_string(&text);
@@ -172,6 +225,9 @@ impl IndexDiffWidget {
     }
 
     fn update(&self) {
+        if self.choose_hunks_button.get_active() {
+            self.update_hunk_selection(true);
+        }
         let (text, new_digest) = self.get_diff_text();
         let go_ahead = new_digest != *self.current_digest.borrow();
         if go_ahead {
@@ -188,6 +244,16 @@ impl IndexDiffWidget {
     }
 }
 
+impl StageHunks for IndexDiffWidget {
+    fn exec_console(&self) -> &Rc<ExecConsole> {
+        &self.exec_console
+    }
+
+    fn hunk_selection(&self) -> &Rc<HunkSelectionWidget> {
+        &self.hunk_selection
+    }
+}
+
 #[derive(PWO, Wrapper)]
 struct CommitWidget {
     v_box: gtk::Box,
@@ -199,8 +265,9 @@ struct CommitWidget {
     signoff_option_button: gtk::CheckButton,
 }
 
-fn get_name_and_email_string() -> String {
-    let output = Command::new("git")
+fn get_name_and_email_string(ws_path: &WsPath) -> String {
+    let output = ws_path
+        .git()
         .arg("config")
         .arg("user.name")
         .output()
@@ -210,7 +277,8 @@ fn get_name_and_email_string() -> String {
     } else {
         "user name".to_string()
     };
-    let output = Command::new("git")
+    let output = ws_path
+        .git()
         .arg("config")
         .arg("user.email")
         .output()
@@ -223,13 +291,13 @@ fn get_name_and_email_string() -> String {
     format!("{} <{}>", name.trim_end(), email.trim_end())
 }
 
-fn insert_acked_by_at_cursor(buffer: &gtk::TextBuffer) {
-    let text = format!("Acked-by: {}", get_name_and_email_string());
+fn insert_acked_by_at_cursor(ws_path: &WsPath, buffer: &gtk::TextBuffer) {
+    let text = format!("Acked-by: {}", get_name_and_email_string(ws_path));
     buffer.insert_at_cursor(&text)
 }
 
-fn insert_signed_off_by_at_cursor(buffer: &gtk::TextBuffer) {
-    let text = format!("Signed-off-by: {}", get_name_and_email_string());
+fn insert_signed_off_by_at_cursor(ws_path: &WsPath, buffer: &gtk::TextBuffer) {
+    let text = format!("Signed-off-by: {}", get_name_and_email_string(ws_path));
     buffer.insert_at_cursor(&text)
 }
 
@@ -261,7 +329,7 @@ impl CommitWidget {
         cw.exec_console.managed_buttons.add_widget(
             "exec_commit",
             &cw.exec_button,
-            repos::SAV_IN_REPO,
+            repos::SAV_IN_REPO + repos::SAV_NOT_HAS_CONFLICTS,
         );
         cw.exec_console.managed_check_buttons.add_widget(
             "amend_option",
@@ -281,7 +349,7 @@ impl CommitWidget {
                     .text_view
                     .get_buffer()
                     .expect("get_buffer() failed");
-                buffer.insert_at_cursor(&last_commit_message());
+                buffer.insert_at_cursor(&last_commit_message(&cw_clone.exec_console.ws_path));
             }
         });
 
@@ -297,17 +365,11 @@ impl CommitWidget {
                 .get_text(&start, &end, false)
                 .expect("get_text() failed");
             if text.len() > 0 {
-                let cmd = if cw_clone.signoff_option_button.get_active() {
-                    if cw_clone.amend_option_button.get_active() {
-                        format!("git commit --signoff --amend -m {}", shlex::quote(&text))
-                    } else {
-                        format!("git commit --signoff -m {}", shlex::quote(&text))
-                    }
-                } else if cw_clone.amend_option_button.get_active() {
-                    format!("git commit --amend -m {}", shlex::quote(&text))
-                } else {
-                    format!("git commit -m {}", shlex::quote(&text))
-                };
+                let cmd = GitCommand::new("commit")
+                    .arg_if(cw_clone.signoff_option_button.get_active(), "--signoff")
+                    .arg_if(cw_clone.amend_option_button.get_active(), "--amend")
+                    .arg("-m")
+                    .arg(text);
                 let result = cw_clone.exec_console.exec_cmd(&cmd, events::EV_COMMIT);
                 if let Ok(ref output) = result {
                     if output.status.success() {
@@ -315,7 +377,7 @@ impl CommitWidget {
                     }
                 }
                 cw_clone.amend_option_button.set_active(false);
-                cw_clone.report_any_command_problems(&cmd, &result);
+                cw_clone.report_any_command_problems(&cmd.to_string(), &result);
             } else {
                 cw_clone.warn_user("Commit message is empty!", None);
             }
@@ -324,15 +386,20 @@ impl CommitWidget {
         cw.text_view.set_monospace(true);
         cw.text_view.set_show_right_margin(true);
         cw.text_view.set_right_margin_position(71);
-        cw.text_view.connect_populate_popup(|view, widget| {
+        let ws_path = cw.exec_console.ws_path.clone();
+        cw.text_view.connect_populate_popup(move |view, widget| {
             if let Ok(ref menu) = widget.clone().downcast::<gtk::Menu>() {
                 let mi = gtk::MenuItem::with_label("Insert Acked-by");
                 let buffer = view.get_buffer().unwrap();
-                mi.connect_activate(move |_| insert_acked_by_at_cursor(&buffer));
+                let ws_path_clone = ws_path.clone();
+                mi.connect_activate(move |_| insert_acked_by_at_cursor(&ws_path_clone, &buffer));
                 menu.append(&mi);
                 let mi = gtk::MenuItem::with_label("Insert Signed-off-by");
                 let buffer = view.get_buffer().unwrap();
-                mi.connect_activate(move |_| insert_signed_off_by_at_cursor(&buffer));
+                let ws_path_clone = ws_path.clone();
+                mi.connect_activate(move |_| {
+                    insert_signed_off_by_at_cursor(&ws_path_clone, &buffer)
+                });
                 menu.append(&mi);
                 menu.show_all();
             }
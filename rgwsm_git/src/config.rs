@@ -15,9 +15,10 @@
 use std::env;
 use std::path::PathBuf;
 
-use pw_pathux::{self, str_path};
+use gwsm_git_core::workspace::WsPath;
 
 pub const APP_NAME: &str = "rgwsm_git";
+pub const APP_ID: &str = "com.github.pwil3058.rgwsm_git";
 
 const DEFAULT_CONFIG_DIR_PATH: &str = "~/.config/rgwsm_git";
 
@@ -34,12 +35,8 @@ pub fn abs_default_config_dir_path() -> PathBuf {
     }
 }
 
-pub fn window_title(sub_title: Option<&str>) -> String {
-    let curr_dir = if let Ok(dir_path) = str_path::str_path_current_dir_rel_home() {
-        dir_path
-    } else {
-        str_path::str_path_current_dir_or_panic()
-    };
+pub fn window_title(ws_path: &WsPath, sub_title: Option<&str>) -> String {
+    let curr_dir = ws_path.path_string_rel_home();
     if let Some(sub_title) = sub_title {
         format!("{sub_title}::{APP_NAME}: {curr_dir}")
     } else {
@@ -47,24 +44,31 @@ pub fn window_title(sub_title: Option<&str>) -> String {
     }
 }
 
+// Equivalent to setting the RGWSM_GIT_CONFIG_DIR environment variable
+pub fn set_config_dir_path_override(dir_path: &str) {
+    env::set_var(DCDP_OVERRIDE_ENVAR, dir_path);
+}
+
+pub fn expand_config_dir_path(dir_path: &str) -> PathBuf {
+    if dir_path.is_empty() {
+        abs_default_config_dir_path()
+    } else if dir_path.starts_with('~') {
+        match pw_pathux::expand_home_dir(&PathBuf::from(dir_path)) {
+            Some(expanded_dir) => expanded_dir,
+            None => panic!(
+                "{:?}: line {:?}: config dir path expansion failed",
+                file!(),
+                line!()
+            ),
+        }
+    } else {
+        PathBuf::from(dir_path)
+    }
+}
+
 pub fn get_config_dir_path() -> PathBuf {
     match env::var(DCDP_OVERRIDE_ENVAR) {
-        Ok(dir_path) => {
-            if dir_path.is_empty() {
-                abs_default_config_dir_path()
-            } else if dir_path.starts_with('~') {
-                match pw_pathux::expand_home_dir(&PathBuf::from(dir_path)) {
-                    Some(expanded_dir) => expanded_dir,
-                    None => panic!(
-                        "{:?}: line {:?}: config dir path expansion failed",
-                        file!(),
-                        line!()
-                    ),
-                }
-            } else {
-                PathBuf::from(dir_path)
-            }
-        }
+        Ok(dir_path) => expand_config_dir_path(&dir_path),
         Err(_) => abs_default_config_dir_path(),
     }
 }
@@ -0,0 +1,637 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, Ref, RefCell};
+use std::io::Write;
+use std::rc::Rc;
+
+use crypto_hash::{Algorithm, Hasher};
+
+use pw_gix::{
+    glib,
+    glibx::*,
+    gtk::{self, prelude::*},
+    gtkx::{
+        list_store::{
+            BufferedUpdate, MapManagedUpdate, RequiredMapAction, Row, RowBuffer, RowBufferCore,
+        },
+        menu_ng::{ManagedMenu, ManagedMenuBuilder},
+    },
+    sav_state::*,
+    wrapper::*,
+};
+
+use gwsm_git_core::files::{self, Side};
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::status::{self, StatusEntry};
+use gwsm_git_core::workspace::WsPath;
+
+use crate::edit;
+use crate::events::{self, Event, EventSet};
+use crate::exec::ExecConsole;
+use crate::rebase;
+use crate::repos;
+
+const OPERATION_EVENTS: EventSet = events::EV_FILES_CHANGE
+    .union(events::EV_COMMIT)
+    .union(events::EV_BRANCHES_CHANGE)
+    .union(events::EV_CHECKOUT);
+
+fn get_raw_data(ws_path: &WsPath) -> (String, Vec<u8>) {
+    let mut hasher = Hasher::new(Algorithm::SHA256);
+    let output = ws_path
+        .git()
+        .arg("status")
+        .arg("--porcelain=v2")
+        .arg("-z")
+        .arg("--untracked-files=no")
+        .arg("--ignore-submodules=all")
+        .output()
+        .expect("getting unmerged files text failed");
+    let text: String = if output.status.success() {
+        hasher.write_all(&output.stdout).expect("hasher blew up!!!");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        "".to_string()
+    };
+    (text, hasher.finish())
+}
+
+fn describe_conflict(xy: &str) -> &'static str {
+    match xy {
+        "DD" => "both deleted",
+        "AU" => "added by us",
+        "UD" => "deleted by them",
+        "UA" => "added by them",
+        "DU" => "deleted by us",
+        "AA" => "both added",
+        "UU" => "both modified",
+        _ => "unknown",
+    }
+}
+
+// Extract (path, XY, description) for the unmerged entries in
+// the output of "git status --porcelain=v2 -z"
+fn extract_conflicts(text: &str) -> Vec<(String, String, &'static str)> {
+    status::parse_porcelain_v2(text)
+        .into_iter()
+        .filter(|entry| status::status_is_unmerged(&entry.status))
+        .map(|StatusEntry { path, status, .. }| {
+            let description = describe_conflict(&status);
+            let path = path
+                .strip_prefix(&format!(".{}", std::path::MAIN_SEPARATOR))
+                .map(|path| path.to_string())
+                .unwrap_or(path);
+            (path, status, description)
+        })
+        .collect()
+}
+
+// A merge tool can only be used (without a terminal to answer questions
+// about deleted files) when both sides of the conflict have content
+fn merge_tool_applies(xy: &str) -> bool {
+    xy == status::UNMERGED || xy == status::UNMERGED_ADDED
+}
+
+// The configured GUI merge tool, if any
+fn gui_merge_tool(ws_path: &WsPath) -> Option<String> {
+    let output = ws_path
+        .git()
+        .arg("config")
+        .arg("--get")
+        .arg("merge.guitool")
+        .output()
+        .ok()?;
+    let tool = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() && !tool.is_empty() {
+        Some(tool)
+    } else {
+        None
+    }
+}
+
+struct ConflictsRowBuffer {
+    row_buffer_core: Rc<RefCell<RowBufferCore<String>>>,
+    ws_path: WsPath,
+}
+
+impl ConflictsRowBuffer {
+    fn new(ws_path: &WsPath) -> Self {
+        let core = RowBufferCore::<String>::default();
+        let buffer = Self {
+            row_buffer_core: Rc::new(RefCell::new(core)),
+            ws_path: ws_path.clone(),
+        };
+        buffer.init();
+        buffer
+    }
+}
+
+impl RowBuffer<String> for ConflictsRowBuffer {
+    fn get_core(&self) -> Rc<RefCell<RowBufferCore<String>>> {
+        self.row_buffer_core.clone()
+    }
+
+    fn set_raw_data(&self) {
+        let (raw_data, digest) = get_raw_data(&self.ws_path);
+        let mut core = self.row_buffer_core.borrow_mut();
+        core.set_raw_data(raw_data, digest);
+    }
+
+    fn finalise(&self) {
+        let mut core = self.row_buffer_core.borrow_mut();
+        let rows: Vec<Row> = extract_conflicts(&core.raw_data)
+            .iter()
+            .map(|(path, xy, description)| {
+                vec![path.to_value(), xy.to_value(), description.to_value()]
+            })
+            .collect();
+        core.rows = Rc::new(rows);
+        core.set_is_finalised_true();
+    }
+}
+
+struct ConflictsListStore {
+    list_store: gtk::ListStore,
+    conflicts_row_buffer: Rc<RefCell<ConflictsRowBuffer>>,
+}
+
+impl BufferedUpdate<String, gtk::ListStore> for ConflictsListStore {
+    fn get_list_store(&self) -> gtk::ListStore {
+        self.list_store.clone()
+    }
+
+    fn get_row_buffer(&self) -> Rc<RefCell<dyn RowBuffer<String>>> {
+        self.conflicts_row_buffer.clone()
+    }
+}
+
+impl ConflictsListStore {
+    pub fn new(ws_path: &WsPath) -> ConflictsListStore {
+        Self {
+            list_store: gtk::ListStore::new(&[glib::Type::String; 3]),
+            conflicts_row_buffer: Rc::new(RefCell::new(ConflictsRowBuffer::new(ws_path))),
+        }
+    }
+}
+
+#[derive(PWO, Wrapper)]
+pub struct ConflictsPanel {
+    v_box: gtk::Box,
+    view: gtk::TreeView,
+    list_store: RefCell<ConflictsListStore>,
+    required_map_action: Cell<RequiredMapAction>,
+    operation_label: gtk::Label,
+    continue_button: gtk::Button,
+    skip_button: gtk::Button,
+    abort_button: gtk::Button,
+    exec_console: Rc<ExecConsole>,
+    popup_menu: ManagedMenu,
+    hovered_conflict: RefCell<Option<(String, String)>>,
+}
+
+impl MapManagedUpdate<ConflictsListStore, String, gtk::ListStore> for ConflictsPanel {
+    fn buffered_update(&self) -> Ref<'_, ConflictsListStore> {
+        self.list_store.borrow()
+    }
+
+    fn is_mapped(&self) -> bool {
+        self.view.get_mapped()
+    }
+
+    fn get_required_map_action(&self) -> RequiredMapAction {
+        self.required_map_action.get()
+    }
+
+    fn set_required_map_action(&self, action: RequiredMapAction) {
+        self.required_map_action.set(action);
+    }
+}
+
+impl ConflictsPanel {
+    pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<ConflictsPanel> {
+        let list_store = RefCell::new(ConflictsListStore::new(&exec_console.ws_path));
+
+        let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
+        view.set_headers_visible(true);
+
+        view.get_selection().set_mode(gtk::SelectionMode::Single);
+
+        for (title, index) in [("Path", 0), ("Status", 1), ("Conflict", 2)].iter() {
+            let col = gtk::TreeViewColumn::new();
+            col.set_title(title);
+            col.set_expand(*index == 0);
+            col.set_resizable(true);
+
+            let cell = gtk::CellRendererText::new();
+            cell.set_property_editable(false);
+            col.pack_start(&cell, false);
+            col.add_attribute(&cell, "text", *index);
+
+            view.append_column(&col);
+        }
+
+        view.show_all();
+
+        list_store.borrow().repopulate();
+
+        let required_map_action = Cell::new(RequiredMapAction::Nothing);
+
+        let popup_menu = ManagedMenuBuilder::new()
+            .widget_states_controlled(WidgetStatesControlled::Sensitivity)
+            .selection(&view.get_selection())
+            .change_notifier(&exec_console.changed_condns_notifier)
+            .build();
+
+        let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        let operation_label = gtk::Label::new(None);
+        h_box.pack_start(&operation_label, false, false, 0);
+        let abort_button = gtk::Button::with_label("Abort");
+        abort_button.set_tooltip_text(Some("Abort the operation in progress"));
+        h_box.pack_end(&abort_button, false, false, 0);
+        let skip_button = gtk::Button::with_label("Skip");
+        skip_button.set_tooltip_text(Some(
+            "Skip the commit that the operation in progress has stopped at",
+        ));
+        h_box.pack_end(&skip_button, false, false, 0);
+        let continue_button = gtk::Button::with_label("Continue");
+        continue_button.set_tooltip_text(Some("Continue the operation in progress"));
+        h_box.pack_end(&continue_button, false, false, 0);
+        v_box.pack_start(&h_box, false, false, 0);
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.add(&view);
+        v_box.pack_start(&scrolled_window, true, true, 0);
+        v_box.show_all();
+
+        let panel = Rc::new(ConflictsPanel {
+            v_box,
+            view,
+            list_store,
+            required_map_action,
+            operation_label,
+            continue_button,
+            skip_button,
+            abort_button,
+            exec_console: Rc::clone(exec_console),
+            popup_menu,
+            hovered_conflict: RefCell::new(None),
+        });
+        panel.update_operation_controls();
+
+        let panel_clone = Rc::clone(&panel);
+        panel.exec_console.event_notifier.add_notification_cb(
+            events::EV_AUTO_UPDATE
+                | events::EV_FILES_CHANGE
+                | events::EV_CHECKOUT
+                | events::EV_COMMIT
+                | events::EV_PULL,
+            Box::new(move |_| {
+                panel_clone.auto_update();
+                panel_clone.update_operation_controls();
+            }),
+        );
+        let panel_clone = Rc::clone(&panel);
+        panel.view.connect_map(move |_| panel_clone.on_map_action());
+        let panel_clone = Rc::clone(&panel);
+        panel.exec_console.event_notifier.add_notification_cb(
+            events::EV_CHANGE_DIR,
+            Box::new(move |_| {
+                panel_clone.repopulate();
+                panel_clone.update_operation_controls();
+            }),
+        );
+
+        let panel_clone = Rc::clone(&panel);
+        panel.continue_button.connect_clicked(move |_| {
+            if let Some(operation) =
+                repos::get_operation_in_progress(&panel_clone.exec_console.ws_path)
+            {
+                if let Some(cmd) = operation.continue_cmd() {
+                    let cursor = panel_clone.show_busy();
+                    let result = panel_clone.exec_console.exec_cmd(&cmd, OPERATION_EVENTS);
+                    panel_clone.unshow_busy(cursor);
+                    panel_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            }
+            rebase::remove_finished_rebase_plan(&panel_clone.exec_console.ws_path);
+            panel_clone.update_operation_controls();
+        });
+
+        let panel_clone = Rc::clone(&panel);
+        panel.skip_button.connect_clicked(move |_| {
+            if let Some(operation) =
+                repos::get_operation_in_progress(&panel_clone.exec_console.ws_path)
+            {
+                if let Some(cmd) = operation.skip_cmd() {
+                    let msg = format!("Skip the current commit of the {}?", operation.name());
+                    if panel_clone.ask_confirm_action(&msg, None) {
+                        let cursor = panel_clone.show_busy();
+                        let result = panel_clone.exec_console.exec_cmd(&cmd, OPERATION_EVENTS);
+                        panel_clone.unshow_busy(cursor);
+                        panel_clone.report_any_command_problems(&cmd.to_string(), &result);
+                    }
+                }
+            }
+            rebase::remove_finished_rebase_plan(&panel_clone.exec_console.ws_path);
+            panel_clone.update_operation_controls();
+        });
+
+        let panel_clone = Rc::clone(&panel);
+        panel.abort_button.connect_clicked(move |_| {
+            if let Some(operation) =
+                repos::get_operation_in_progress(&panel_clone.exec_console.ws_path)
+            {
+                let msg = format!("Abort the {} in progress?", operation.name());
+                if panel_clone.ask_confirm_action(&msg, None) {
+                    let cmd = operation.abort_cmd();
+                    let cursor = panel_clone.show_busy();
+                    let result = panel_clone.exec_console.exec_cmd(&cmd, OPERATION_EVENTS);
+                    panel_clone.unshow_busy(cursor);
+                    panel_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            }
+            rebase::remove_finished_rebase_plan(&panel_clone.exec_console.ws_path);
+            panel_clone.update_operation_controls();
+        });
+
+        let panel_clone = Rc::clone(&panel);
+        panel
+            .popup_menu
+            .append_item(
+                "ours",
+                &(
+                    "Take Ours",
+                    None,
+                    Some("Resolve the conflict using our version of the file"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some((path, xy)) = panel_clone.get_chosen_conflict() {
+                    panel_clone.take_side(&path, xy.starts_with('D'), Side::Ours);
+                }
+            });
+
+        let panel_clone = Rc::clone(&panel);
+        panel
+            .popup_menu
+            .append_item(
+                "theirs",
+                &(
+                    "Take Theirs",
+                    None,
+                    Some("Resolve the conflict using their version of the file"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some((path, xy)) = panel_clone.get_chosen_conflict() {
+                    panel_clone.take_side(&path, xy.ends_with('D'), Side::Theirs);
+                }
+            });
+
+        let panel_clone = Rc::clone(&panel);
+        panel
+            .popup_menu
+            .append_item(
+                "mergetool",
+                &(
+                    "Merge Tool",
+                    None,
+                    Some("Resolve the conflict using the configured GUI merge tool (merge.guitool)"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some((path, xy)) = panel_clone.get_chosen_conflict() {
+                    if !merge_tool_applies(&xy) {
+                        let msg = format!("\"{}\" is {}.", path, describe_conflict(&xy));
+                        let explanation = "Use \"Take Ours\" or \"Take Theirs\" instead.";
+                        panel_clone.inform_user(&msg, Some(explanation));
+                        return;
+                    }
+                    let tool = match gui_merge_tool(&panel_clone.exec_console.ws_path) {
+                        Some(tool) => tool,
+                        None => {
+                            let explanation = "Set \"merge.guitool\" (e.g. \"git config --global merge.guitool meld\") to use one.";
+                            panel_clone.inform_user("No GUI merge tool is configured.", Some(explanation));
+                            return;
+                        }
+                    };
+                    let cmd = GitCommand::new("mergetool")
+                        .arg(format!("--tool={}", tool))
+                        .args(&["--no-prompt", "--"])
+                        .arg(&path);
+                    let cursor = panel_clone.show_busy();
+                    let result = panel_clone
+                        .exec_console
+                        .exec_cmd(&cmd, Event::FilesChange(vec![path.clone()]));
+                    panel_clone.unshow_busy(cursor);
+                    panel_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let panel_clone = Rc::clone(&panel);
+        panel
+            .popup_menu
+            .append_item(
+                "edit",
+                &("Edit", None, Some("Edit the file in its assigned editor")).into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some((path, _)) = panel_clone.get_chosen_conflict() {
+                    panel_clone.edit_file(&path);
+                }
+            });
+
+        let panel_clone = Rc::clone(&panel);
+        panel
+            .popup_menu
+            .append_item(
+                "resolved",
+                &(
+                    "Mark Resolved",
+                    None,
+                    Some("Mark the conflict as resolved by adding the file to the index"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some((path, _)) = panel_clone.get_chosen_conflict() {
+                    let cmd = files::add_cmd(&[&path]);
+                    let result = panel_clone
+                        .exec_console
+                        .exec_cmd(&cmd, Event::FilesChange(vec![path.clone()]));
+                    panel_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let panel_clone = panel.clone();
+        panel.view.connect_button_press_event(move |view, event| {
+            if event.get_button() == 3 {
+                let conflict = get_row_item_for_event!(view, event, String, 0).map(|path| {
+                    let xy = get_row_item_for_event!(view, event, String, 1).unwrap_or_default();
+                    (path, xy)
+                });
+                panel_clone.set_hovered_conflict(conflict);
+                panel_clone.popup_menu.popup_at_event(event);
+                return Inhibit(true);
+            } else if event.get_button() == 2 {
+                panel_clone.view.get_selection().unselect_all();
+                return Inhibit(true);
+            }
+            Inhibit(false)
+        });
+
+        panel
+    }
+
+    fn update_operation_controls(&self) {
+        if let Some(operation) = repos::get_operation_in_progress(&self.exec_console.ws_path) {
+            let text = format!("{} in progress", operation.name());
+            self.operation_label.set_text(&text);
+            self.continue_button
+                .set_sensitive(operation.continue_cmd().is_some());
+            self.skip_button
+                .set_sensitive(operation.skip_cmd().is_some());
+            self.abort_button.set_sensitive(true);
+        } else {
+            self.operation_label.set_text("");
+            self.continue_button.set_sensitive(false);
+            self.skip_button.set_sensitive(false);
+            self.abort_button.set_sensitive(false);
+        }
+    }
+
+    fn set_hovered_conflict(&self, conflict: Option<(String, String)>) {
+        let condns = self
+            .view
+            .get_selection()
+            .get_masked_conditions_with_hover_ok(conflict.is_some());
+        self.popup_menu.update_condns(condns);
+        *self.hovered_conflict.borrow_mut() = conflict;
+    }
+
+    fn get_chosen_conflict(&self) -> Option<(String, String)> {
+        let selection = self.view.get_selection();
+        if let Some((store, iter)) = selection.get_selected() {
+            let path = store.get_value(&iter, 0).get::<String>().unwrap()?;
+            let xy = store
+                .get_value(&iter, 1)
+                .get::<String>()
+                .unwrap()
+                .unwrap_or_default();
+            Some((path, xy))
+        } else {
+            self.hovered_conflict.borrow().clone()
+        }
+    }
+
+    // If the chosen side deleted the file then taking that side means removing it.
+    fn take_side(&self, path: &str, side_deleted: bool, side: Side) {
+        let cmds = files::take_side_cmds(path, side, side_deleted);
+        for cmd in cmds.iter() {
+            let result = self
+                .exec_console
+                .exec_cmd(cmd, Event::FilesChange(vec![path.to_string()]));
+            let failed = match result {
+                Ok(ref output) => !output.status.success(),
+                Err(_) => true,
+            };
+            self.report_any_command_problems(&cmd.to_string(), &result);
+            if failed {
+                break;
+            }
+        }
+    }
+
+    fn edit_file(&self, path: &str) {
+        match edit::get_assigned_editor(path) {
+            Ok(editor) => {
+                let ws_path = &self.exec_console.ws_path;
+                if let Err(err) = ws_path.command(&editor).arg(path).spawn() {
+                    let msg = format!("{} {}: failed", shlex::quote(&editor), shlex::quote(path));
+                    self.report_error(&msg, &err);
+                }
+            }
+            Err(err) => {
+                let msg = "Error accessing editor assignment table";
+                self.report_error(msg, &err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gwsm_git_core::test_support::ScratchRepo;
+
+    #[test]
+    fn extract_conflicts_works() {
+        let text = "1 .M N... 100644 100644 100644 aaaa bbbb clean.txt\0\
+                    u UU N... 100644 100644 100644 100644 aaaa bbbb cccc both modified.txt\0\
+                    u DU N... 100644 000000 100644 100644 aaaa 0000 cccc gone.txt\0";
+        let conflicts = extract_conflicts(text);
+        assert_eq!(
+            conflicts,
+            vec![
+                (
+                    "both modified.txt".to_string(),
+                    "UU".to_string(),
+                    "both modified"
+                ),
+                ("gone.txt".to_string(), "DU".to_string(), "deleted by us"),
+            ]
+        );
+        assert!(merge_tool_applies(&conflicts[0].1));
+        assert!(!merge_tool_applies(&conflicts[1].1));
+    }
+
+    #[test]
+    fn extract_conflicts_from_repo_works() {
+        let repo = ScratchRepo::new();
+        let ws_path = repo.ws_path();
+        assert!(extract_conflicts(&get_raw_data(&ws_path).0).is_empty());
+        repo.commit_file("doomed.txt", "doomed\n", "Add doomed file");
+        repo.git(&["checkout", "-q", "-b", "theirs"]);
+        repo.git(&["rm", "-q", "doomed.txt"]);
+        repo.commit_file("README", "their change\n", "Their changes");
+        repo.checkout("master");
+        repo.write_file("doomed.txt", "reprieved\n");
+        repo.git(&["add", "doomed.txt"]);
+        repo.commit_file("README", "our change\n", "Our changes");
+        assert!(!repo.git_output(&["merge", "theirs"]).status.success());
+        let conflicts = extract_conflicts(&get_raw_data(&ws_path).0);
+        assert_eq!(
+            conflicts,
+            vec![
+                ("README".to_string(), "UU".to_string(), "both modified"),
+                (
+                    "doomed.txt".to_string(),
+                    "UD".to_string(),
+                    "deleted by them"
+                ),
+            ]
+        );
+    }
+}
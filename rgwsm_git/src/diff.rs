@@ -14,7 +14,6 @@
 
 use std::cell::RefCell;
 use std::io::Write;
-use std::process::Command;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
@@ -33,6 +32,7 @@ use crate::action_icons;
 use crate::config;
 use crate::events;
 use crate::exec::ExecConsole;
+use crate::hunks::{HunkSelectionWidget, StageHunks};
 use crate::repos;
 
 #[derive(PWO)]
@@ -66,7 +66,8 @@ impl DiffButton {
         db.window
             .set_geometry_from_recollections("ws::diff:display", (700, 500));
         db.window.set_destroy_with_parent(true);
-        db.window.set_title(&config::window_title(Some("diff")));
+        db.window
+            .set_title(&config::window_title(&exec_console.ws_path, Some("diff")));
         db.window.connect_delete_event(move |w, _| {
             w.hide_on_delete();
             Inhibit(true)
@@ -79,9 +80,10 @@ impl DiffButton {
         exec_console.event_notifier.add_notification_cb(
             events::EV_CHANGE_DIR,
             Box::new(move |_| {
-                db_clone
-                    .window
-                    .set_title(&config::window_title(Some("diff")));
+                db_clone.window.set_title(&config::window_title(
+                    &db_clone._exec_console.ws_path,
+                    Some("diff"),
+                ));
             }),
         );
 
@@ -91,6 +93,10 @@ impl DiffButton {
 
         db
     }
+
+    pub fn present_window(&self) {
+        self.window.present();
+    }
 }
 
 #[derive(PWO, Wrapper)]
@@ -99,7 +105,11 @@ struct WdDiffTextWidget {
     diff_rb: gtk::RadioButton,
     diff_staged_rb: gtk::RadioButton,
     diff_head_rb: gtk::RadioButton,
+    choose_hunks_button: gtk::ToggleButton,
+    stage_chosen_button: gtk::Button,
+    diff_stack: gtk::Stack,
     diff_notebook: Rc<DiffPlusNotebook>,
+    hunk_selection: Rc<HunkSelectionWidget>,
     current_digest: RefCell<Vec<u8>>,
     exec_console: Rc<ExecConsole>,
     diff_plus_parser: DiffPlusParser,
@@ -116,16 +126,33 @@ impl WdDiffTextWidget {
         h_box.pack_start(&diff_rb, false, false, 0);
         h_box.pack_start(&diff_staged_rb, false, false, 0);
         h_box.pack_start(&diff_head_rb, false, false, 0);
+        let choose_hunks_button = gtk::ToggleButton::with_label("Choose Hunks");
+        choose_hunks_button.set_tooltip_text(Some(
+            "Choose hunks or lines from the working directory diff (git diff) to be staged.",
+        ));
+        h_box.pack_start(&choose_hunks_button, false, false, 0);
+        let stage_chosen_button = gtk::Button::with_label("Stage Chosen");
+        stage_chosen_button.set_tooltip_text(Some("Add the chosen hunks or lines to the index."));
+        stage_chosen_button.set_sensitive(false);
+        h_box.pack_start(&stage_chosen_button, false, false, 0);
         v_box.pack_start(&h_box, false, false, 0);
         let diff_notebook = DiffPlusNotebook::new(1);
         h_box.pack_end(diff_notebook.tws_count_display().pwo(), false, false, 0);
-        v_box.pack_start(diff_notebook.pwo(), true, true, 0);
+        let hunk_selection = HunkSelectionWidget::new();
+        let diff_stack = gtk::Stack::new();
+        diff_stack.add_named(diff_notebook.pwo(), "diffs");
+        diff_stack.add_named(hunk_selection.pwo(), "hunks");
+        v_box.pack_start(&diff_stack, true, true, 0);
         let wdtw = Rc::new(Self {
             v_box,
             diff_rb,
             diff_staged_rb,
             diff_head_rb,
+            choose_hunks_button,
+            stage_chosen_button,
+            diff_stack,
             diff_notebook,
+            hunk_selection,
             current_digest: RefCell::new(Vec::new()),
             exec_console: Rc::clone(exec_console),
             diff_plus_parser: DiffPlusParser::new(),
@@ -138,6 +165,12 @@ impl WdDiffTextWidget {
             }
         });
         let wdtw_clone = Rc::clone(&wdtw);
+        wdtw.choose_hunks_button
+            .connect_toggled(move |button| wdtw_clone.set_choosing_hunks(button.get_active()));
+        let wdtw_clone = Rc::clone(&wdtw);
+        wdtw.stage_chosen_button
+            .connect_clicked(move |_| wdtw_clone.apply_chosen_hunks(false));
+        let wdtw_clone = Rc::clone(&wdtw);
         wdtw.diff_staged_rb.connect_toggled(move |rb| {
             if rb.get_active() {
                 wdtw_clone.update();
@@ -167,9 +200,27 @@ impl WdDiffTextWidget {
         wdtw
     }
 
+    // Hunks are always chosen from "git diff" so the other diffs are disabled
+    fn set_choosing_hunks(&self, choosing: bool) {
+        if choosing {
+            self.update_hunk_selection(false);
+            self.diff_stack.set_visible_child_name("hunks");
+        } else {
+            self.diff_stack.set_visible_child_name("diffs");
+        }
+        self.stage_chosen_button.set_sensitive(choosing);
+        for rb in [&self.diff_rb, &self.diff_staged_rb, &self.diff_head_rb].iter() {
+            rb.set_sensitive(!choosing);
+        }
+    }
+
     fn get_diff_text(&self) -> (String, Vec<u8>) {
-        let mut cmd = Command::new("git");
-        cmd.arg("diff").arg("--no-ext-diff").arg("-M");
+        let mut cmd = self.exec_console.ws_path.git();
+        cmd.arg("diff")
+            .arg("--no-ext-diff")
+            .arg("--src-prefix=a/")
+            .arg("--dst-prefix=b/")
+            .arg("-M");
         if self.diff_staged_rb.get_active() {
             cmd.arg("--staged");
         } else if self.diff_head_rb.get_active() {
@@ -189,6 +240,9 @@ impl WdDiffTextWidget {
     }
 
     fn repopulate(&self) {
+        if self.choose_hunks_button.get_active() {
+            self.update_hunk_selection(false);
+        }
         let (text, new_digest) = self.get_diff_text();
         *self.current_digest.borrow_mut() = new_digest;
         let lines = Lines::from_string(&text);
@@ -202,6 +256,9 @@ impl WdDiffTextWidget {
     }
 
     fn update(&self) {
+        if self.choose_hunks_button.get_active() {
+            self.update_hunk_selection(false);
+        }
         let (text, new_digest) = self.get_diff_text();
         let go_ahead = new_digest != *self.current_digest.borrow();
         if go_ahead {
@@ -217,3 +274,13 @@ impl WdDiffTextWidget {
         }
     }
 }
+
+impl StageHunks for WdDiffTextWidget {
+    fn exec_console(&self) -> &Rc<ExecConsole> {
+        &self.exec_console
+    }
+
+    fn hunk_selection(&self) -> &Rc<HunkSelectionWidget> {
+        &self.hunk_selection
+    }
+}
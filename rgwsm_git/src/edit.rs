@@ -13,17 +13,10 @@
 // limitations under the License.
 
 use std::cell::Cell;
-use std::convert::From;
 use std::env;
-use std::error::Error;
-use std::fmt;
-use std::fs::File;
-use std::io::{self, Read, Write};
 use std::path::PathBuf;
 use std::rc::Rc;
 
-use glob::{Pattern, PatternError};
-
 use pw_gix::{
     glib,
     gtk::{self, prelude::*},
@@ -32,65 +25,11 @@ use pw_gix::{
     wrapper::*,
 };
 
+use gwsm_git_core::editors::{self, EditorTableError};
+
 use crate::config;
 use crate::repos;
 
-#[derive(Debug)]
-pub enum EditorTableError {
-    InputOutput(io::Error),
-    GlobPattern(PatternError),
-    SerdeJson(serde_json::Error),
-}
-
-impl fmt::Display for EditorTableError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "EditorTableError is here!")
-    }
-}
-
-impl Error for EditorTableError {
-    fn description(&self) -> &str {
-        match self {
-            EditorTableError::InputOutput(_) => "I/O Error accessing editor assignment table",
-            EditorTableError::GlobPattern(_) => {
-                "Glob Pattern Error accessing editor assignment table"
-            }
-            EditorTableError::SerdeJson(_) => "Serde Json Error accessing editor assignment table",
-        }
-    }
-
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            EditorTableError::InputOutput(err) => Some(err),
-            EditorTableError::GlobPattern(err) => Some(err),
-            EditorTableError::SerdeJson(err) => Some(err),
-        }
-    }
-}
-
-impl From<io::Error> for EditorTableError {
-    fn from(error: io::Error) -> Self {
-        EditorTableError::InputOutput(error)
-    }
-}
-
-impl From<PatternError> for EditorTableError {
-    fn from(error: PatternError) -> Self {
-        EditorTableError::GlobPattern(error)
-    }
-}
-
-impl From<serde_json::Error> for EditorTableError {
-    fn from(error: serde_json::Error) -> Self {
-        EditorTableError::SerdeJson(error)
-    }
-}
-
-#[cfg(target_family = "unix")]
-const PATH_SEP: char = ':';
-#[cfg(target_os = "windows")]
-const PATH_SEP: char = ';';
-
 #[cfg(target_family = "unix")]
 const DEFAULT_EDITOR: &str = "gedit";
 #[cfg(target_os = "windows")]
@@ -111,18 +50,11 @@ fn editor_assignment_table_filepath() -> PathBuf {
 }
 
 fn read_editor_assignment_table() -> Result<Vec<(String, String)>, EditorTableError> {
-    let mut file = File::open(editor_assignment_table_filepath())?;
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
-    let v: Vec<(String, String)> = serde_json::from_str(&buffer)?;
-    Ok(v)
+    editors::read_editor_assignment_table(&editor_assignment_table_filepath())
 }
 
 fn write_editor_assignment_table(table: &[(String, String)]) -> Result<usize, EditorTableError> {
-    let data = serde_json::to_string(table)?;
-    let mut file = File::create(editor_assignment_table_filepath())?;
-    let nbytes = file.write(data.as_bytes())?;
-    Ok(nbytes)
+    editors::write_editor_assignment_table(&editor_assignment_table_filepath(), table)
 }
 
 pub fn init_editor_assignment_table() {
@@ -133,18 +65,13 @@ pub fn init_editor_assignment_table() {
 
 pub fn get_assigned_editor(file_path: &str) -> Result<String, EditorTableError> {
     let editor_assignment_table = read_editor_assignment_table()?;
-    for (globs, editor) in editor_assignment_table.iter() {
-        for glob in globs.split(PATH_SEP) {
-            let pattern = Pattern::new(glob)?;
-            if pattern.matches(file_path) {
-                return Ok(editor.to_string());
-            }
-        }
+    match editors::find_assigned_editor(&editor_assignment_table, file_path)? {
+        Some(editor) => Ok(editor.to_string()),
+        None => Ok(default_editor()),
     }
-    Ok(default_editor())
 }
 
-const SAV_MODIFIED: u64 = repos::SAV_HAS_SUBMODULES << 1;
+const SAV_MODIFIED: u64 = repos::SAV_NEXT_REPO_CONDN;
 const SAV_NOT_MODIFIED: u64 = SAV_MODIFIED << 1;
 const SAV_MODIFIED_MASK: u64 = SAV_MODIFIED | SAV_NOT_MODIFIED;
 
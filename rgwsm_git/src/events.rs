@@ -13,21 +13,196 @@
 // limitations under the License.
 
 use std::cell::{Cell, RefCell};
+use std::ops::{BitOr, BitOrAssign};
 use std::rc::Rc;
 
-pub const EV_AUTO_UPDATE: u64 = 1 << 0;
-pub const EV_CHANGE_DIR: u64 = 1 << 1;
-pub const EV_CHECKOUT: u64 = 1 << 2;
-pub const EV_FILES_CHANGE: u64 = 1 << 3;
-pub const EV_BRANCHES_CHANGE: u64 = 1 << 4;
-pub const EV_COMMIT: u64 = 1 << 5;
-pub const EV_PULL: u64 = 1 << 6;
-pub const EV_PUSH: u64 = 1 << 7;
-pub const EV_REMOTES_CHANGE: u64 = 1 << 8;
-pub const EV_STASHES_CHANGE: u64 = 1 << 9;
-pub const EV_TAGS_CHANGE: u64 = 1 << 10;
-
-pub type EventNotification = (u64, u64, Box<dyn Fn(u64)>);
+// A set of event kinds.  Used by subscribers to say which events they're
+// interested in and by notifiers when there's no more to say than the kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventSet(u64);
+
+pub const EV_NONE: EventSet = EventSet(0);
+pub const EV_AUTO_UPDATE: EventSet = EventSet(1 << 0);
+pub const EV_CHANGE_DIR: EventSet = EventSet(1 << 1);
+pub const EV_CHECKOUT: EventSet = EventSet(1 << 2);
+pub const EV_FILES_CHANGE: EventSet = EventSet(1 << 3);
+pub const EV_BRANCHES_CHANGE: EventSet = EventSet(1 << 4);
+pub const EV_COMMIT: EventSet = EventSet(1 << 5);
+pub const EV_PULL: EventSet = EventSet(1 << 6);
+pub const EV_PUSH: EventSet = EventSet(1 << 7);
+pub const EV_REMOTES_CHANGE: EventSet = EventSet(1 << 8);
+pub const EV_STASHES_CHANGE: EventSet = EventSet(1 << 9);
+pub const EV_TAGS_CHANGE: EventSet = EventSet(1 << 10);
+
+const EV_ALL: [EventSet; 11] = [
+    EV_AUTO_UPDATE,
+    EV_CHANGE_DIR,
+    EV_CHECKOUT,
+    EV_FILES_CHANGE,
+    EV_BRANCHES_CHANGE,
+    EV_COMMIT,
+    EV_PULL,
+    EV_PUSH,
+    EV_REMOTES_CHANGE,
+    EV_STASHES_CHANGE,
+    EV_TAGS_CHANGE,
+];
+
+impl EventSet {
+    // For use in constant expressions
+    pub const fn union(self, other: EventSet) -> EventSet {
+        EventSet(self.0 | other.0)
+    }
+
+    pub fn without(self, other: EventSet) -> EventSet {
+        EventSet(self.0 & !other.0)
+    }
+
+    pub fn intersects(self, other: EventSet) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for EventSet {
+    type Output = EventSet;
+
+    fn bitor(self, other: EventSet) -> EventSet {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for EventSet {
+    fn bitor_assign(&mut self, other: EventSet) {
+        self.0 |= other.0
+    }
+}
+
+// An event and (where the notifier knows it) the detail of what happened.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    AutoUpdate,
+    ChangeDir,
+    // the new HEAD (branch, tag or commit) if known
+    Checkout(Option<String>),
+    // the changed paths (empty if unknown)
+    FilesChange(Vec<String>),
+    // the created, deleted or changed branch if known
+    BranchesChange(Option<String>),
+    Commit,
+    Pull,
+    Push,
+    RemotesChange,
+    StashesChange,
+    // the created or deleted tag if known
+    TagsChange(Option<String>),
+}
+
+impl Event {
+    fn without_payload(event_set: EventSet) -> Option<Event> {
+        match event_set {
+            EV_AUTO_UPDATE => Some(Event::AutoUpdate),
+            EV_CHANGE_DIR => Some(Event::ChangeDir),
+            EV_CHECKOUT => Some(Event::Checkout(None)),
+            EV_FILES_CHANGE => Some(Event::FilesChange(vec![])),
+            EV_BRANCHES_CHANGE => Some(Event::BranchesChange(None)),
+            EV_COMMIT => Some(Event::Commit),
+            EV_PULL => Some(Event::Pull),
+            EV_PUSH => Some(Event::Push),
+            EV_REMOTES_CHANGE => Some(Event::RemotesChange),
+            EV_STASHES_CHANGE => Some(Event::StashesChange),
+            EV_TAGS_CHANGE => Some(Event::TagsChange(None)),
+            _ => None,
+        }
+    }
+
+    pub fn event_set(&self) -> EventSet {
+        match self {
+            Event::AutoUpdate => EV_AUTO_UPDATE,
+            Event::ChangeDir => EV_CHANGE_DIR,
+            Event::Checkout(_) => EV_CHECKOUT,
+            Event::FilesChange(_) => EV_FILES_CHANGE,
+            Event::BranchesChange(_) => EV_BRANCHES_CHANGE,
+            Event::Commit => EV_COMMIT,
+            Event::Pull => EV_PULL,
+            Event::Push => EV_PUSH,
+            Event::RemotesChange => EV_REMOTES_CHANGE,
+            Event::StashesChange => EV_STASHES_CHANGE,
+            Event::TagsChange(_) => EV_TAGS_CHANGE,
+        }
+    }
+}
+
+// The events delivered together by a single notification
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Events(Vec<Event>);
+
+impl Events {
+    pub fn with(mut self, event: Event) -> Self {
+        self.0.push(event);
+        self
+    }
+
+    pub fn event_set(&self) -> EventSet {
+        self.0
+            .iter()
+            .fold(EV_NONE, |event_set, event| event_set | event.event_set())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn new_head(&self) -> Option<&str> {
+        self.0.iter().find_map(|event| match event {
+            Event::Checkout(Some(head)) => Some(head.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn ref_names(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter_map(|event| match event {
+                Event::BranchesChange(Some(name)) | Event::TagsChange(Some(name)) => {
+                    Some(name.as_str())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl From<EventSet> for Events {
+    fn from(event_set: EventSet) -> Self {
+        Events(
+            EV_ALL
+                .iter()
+                .filter(|ev| event_set.intersects(**ev))
+                .filter_map(|ev| Event::without_payload(*ev))
+                .collect(),
+        )
+    }
+}
+
+impl From<Event> for Events {
+    fn from(event: Event) -> Self {
+        Events(vec![event])
+    }
+}
+
+impl From<Vec<Event>> for Events {
+    fn from(events: Vec<Event>) -> Self {
+        Events(events)
+    }
+}
+
+pub type EventCallback = Rc<dyn Fn(&Events)>;
+pub type EventNotification = (u64, EventSet, EventCallback);
+
 pub struct EventNotifier {
     callbacks: RefCell<Vec<EventNotification>>,
     next_token: Cell<u64>,
@@ -40,26 +215,51 @@ impl EventNotifier {
             next_token: Cell::new(0),
         })
     }
-    pub fn add_notification_cb(&self, events: u64, callback: Box<dyn Fn(u64)>) -> u64 {
+
+    // The returned token can be used to unsubscribe
+    pub fn add_notification_cb(&self, events: EventSet, callback: Box<dyn Fn(&Events)>) -> u64 {
         let token = self.next_token.get();
         self.next_token.set(token + 1);
 
-        self.callbacks.borrow_mut().push((token, events, callback));
+        self.callbacks
+            .borrow_mut()
+            .push((token, events, Rc::from(callback)));
 
         token
     }
 
-    pub fn _del_notification_cb(&self, token: u64) {
+    pub fn del_notification_cb(&self, token: u64) -> bool {
         let position = self.callbacks.borrow().iter().position(|x| x.0 == token);
         if let Some(position) = position {
             let _cb = self.callbacks.borrow_mut().remove(position);
+            true
+        } else {
+            false
         }
     }
 
-    pub fn notify_events(&self, events: u64) {
-        for (_, registered_events, callback) in self.callbacks.borrow().iter() {
-            if registered_events & events != 0 {
-                callback(events)
+    // e.g. when the subscribers' window is destroyed
+    pub fn del_all_notification_cbs(&self) {
+        let _cbs: Vec<EventNotification> = self.callbacks.borrow_mut().drain(..).collect();
+    }
+
+    // Callbacks are called in the order that they were added.  They may add
+    // or delete callbacks: additions take effect for the next notification
+    // and deleted callbacks that haven't been called yet won't be.
+    pub fn notify_events<E: Into<Events>>(&self, events: E) {
+        let events = events.into();
+        let event_set = events.event_set();
+        let interested: Vec<(u64, EventCallback)> = self
+            .callbacks
+            .borrow()
+            .iter()
+            .filter(|(_, registered_events, _)| registered_events.intersects(event_set))
+            .map(|(token, _, callback)| (*token, Rc::clone(callback)))
+            .collect();
+        for (token, callback) in interested {
+            let still_registered = self.callbacks.borrow().iter().any(|x| x.0 == token);
+            if still_registered {
+                callback(&events)
             }
         }
     }
@@ -67,8 +267,98 @@ impl EventNotifier {
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+
+    fn recorder(log: &Rc<RefCell<Vec<(u64, EventSet)>>>, id: u64) -> Box<dyn Fn(&Events)> {
+        let log = Rc::clone(log);
+        Box::new(move |events| log.borrow_mut().push((id, events.event_set())))
+    }
+
+    #[test]
+    fn event_sets_work() {
+        let set = EV_FILES_CHANGE | EV_COMMIT;
+        assert!(set.intersects(EV_COMMIT));
+        assert!(!set.intersects(EV_PULL | EV_PUSH));
+        assert!(EV_NONE.is_empty());
+        let events = Events::from(set);
+        assert_eq!(
+            events,
+            Events::from(vec![Event::FilesChange(vec![]), Event::Commit])
+        );
+        assert_eq!(events.event_set(), set);
+    }
+
+    #[test]
+    fn event_payloads_work() {
+        let events = Events::from(Event::Checkout(Some("topic".to_string())))
+            .with(Event::BranchesChange(Some("topic".to_string())))
+            .with(Event::FilesChange(vec!["a.rs".to_string()]));
+        assert_eq!(events.new_head(), Some("topic"));
+        assert_eq!(events.ref_names(), vec!["topic"]);
+        assert_eq!(
+            events.event_set(),
+            EV_CHECKOUT | EV_BRANCHES_CHANGE | EV_FILES_CHANGE
+        );
+    }
 
     #[test]
-    fn it_works() {}
+    fn dispatch_order_and_masking_work() {
+        let notifier = EventNotifier::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        notifier.add_notification_cb(EV_FILES_CHANGE, recorder(&log, 0));
+        notifier.add_notification_cb(EV_COMMIT | EV_CHECKOUT, recorder(&log, 1));
+        notifier.add_notification_cb(EV_FILES_CHANGE | EV_COMMIT, recorder(&log, 2));
+        notifier.notify_events(EV_COMMIT);
+        notifier.notify_events(EV_FILES_CHANGE);
+        notifier.notify_events(EV_PULL);
+        notifier.notify_events(Event::Checkout(None));
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (1, EV_COMMIT),
+                (2, EV_COMMIT),
+                (0, EV_FILES_CHANGE),
+                (2, EV_FILES_CHANGE),
+                (1, EV_CHECKOUT),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsubscribe_works() {
+        let notifier = EventNotifier::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        let token_0 = notifier.add_notification_cb(EV_COMMIT, recorder(&log, 0));
+        let token_1 = notifier.add_notification_cb(EV_COMMIT, recorder(&log, 1));
+        assert!(notifier.del_notification_cb(token_0));
+        assert!(!notifier.del_notification_cb(token_0));
+        notifier.notify_events(EV_COMMIT);
+        assert_eq!(*log.borrow(), vec![(1, EV_COMMIT)]);
+        assert!(notifier.del_notification_cb(token_1));
+        notifier.notify_events(EV_COMMIT);
+        assert_eq!(log.borrow().len(), 1);
+
+        notifier.add_notification_cb(EV_COMMIT, recorder(&log, 2));
+        notifier.del_all_notification_cbs();
+        notifier.notify_events(EV_COMMIT);
+        assert_eq!(log.borrow().len(), 1);
+    }
+
+    #[test]
+    fn unsubscribe_during_dispatch_works() {
+        let notifier = EventNotifier::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        let notifier_clone = Rc::clone(&notifier);
+        // token 1 will be the second callback
+        notifier.add_notification_cb(
+            EV_COMMIT,
+            Box::new(move |_| {
+                notifier_clone.del_notification_cb(1);
+            }),
+        );
+        notifier.add_notification_cb(EV_COMMIT, recorder(&log, 1));
+        notifier.add_notification_cb(EV_COMMIT, recorder(&log, 2));
+        notifier.notify_events(EV_COMMIT);
+        assert_eq!(*log.borrow(), vec![(2, EV_COMMIT)]);
+    }
 }
@@ -12,16 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::env;
-use std::io;
-use std::process::{self, Command, Output};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Output, Stdio};
 use std::rc::Rc;
-use std::time::SystemTime;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use chrono::prelude::*;
 use xml::escape;
 
 use pw_gix::{
+    gdk,
     gtk::{self, prelude::*},
     recollections,
     sav_state::*,
@@ -29,23 +35,126 @@ use pw_gix::{
     wrapper::*,
 };
 
-use pw_pathux::str_path::*;
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::workspace::WsPath;
 
 use crate::action_icons;
-use crate::events::{self, EventNotifier};
+use crate::events::{self, EventNotifier, Events};
 use crate::repos;
+use crate::txn_log::{self, LogEntry, OutcomeFilter};
+
+enum ChildOutput {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+// How long to keep collecting output after the child exits.  A grandchild
+// (e.g. ssh) that outlives git may hold the pipes open indefinitely.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The reader thread is detached and ends when the pipe closes (or when its
+// output is no longer wanted).
+fn forward_lines<R, F>(reader: R, sender: mpsc::Sender<ChildOutput>, wrap: F)
+where
+    R: Read + Send + 'static,
+    F: Fn(Vec<u8>) -> ChildOutput + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if sender.send(wrap(line)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn append_markup_to(text_view: &gtk::TextView, markup: &str) {
+    let bfr = text_view.get_buffer().expect("failed to find text buffer");
+    let mut model_iter = bfr.get_end_iter();
+    bfr.insert_markup(&mut model_iter, markup);
+    if let Some(eobuf) = bfr.create_mark(Some("eobuf"), &bfr.get_end_iter(), false) {
+        text_view.scroll_to_mark(&eobuf, 0.001, false, 0.0, 0.0);
+    };
+}
+
+fn entry_markup(entry: &LogEntry) -> String {
+    let mut markup = format!(
+        r###"<span foreground="black" weight="bold" font_family="monospace">{}: </span>"###,
+        escape::escape_str_attribute(&entry.timestamp)
+    );
+    markup += &format!(
+        r###"<span foreground="black" font_family="monospace">{}</span>"###,
+        escape::escape_str_attribute(&entry.cmd)
+    );
+    let colour = if entry.succeeded() {
+        "#00AA00"
+    } else {
+        "#AA0000"
+    };
+    markup += &format!(
+        "\n<span foreground=\"{}\" style=\"italic\" font_family=\"monospace\">[{}] in {}</span>\n",
+        colour,
+        escape::escape_str_attribute(&entry.outcome_description()),
+        escape::escape_str_attribute(&entry.cwd)
+    );
+    markup += &format!(
+        r###"<span foreground="black" font_family="monospace">{}</span>"###,
+        escape::escape_str_attribute(&entry.stdout)
+    );
+    markup += &format!(
+        r###"<span foreground="#AA0000" font_family="monospace">{}</span>"###,
+        escape::escape_str_attribute(&entry.stderr)
+    );
+    markup
+}
 
 #[derive(PWO, Wrapper)]
 pub struct ExecConsole {
+    v_box: gtk::Box,
+    stack: gtk::Stack,
     scrolled_window: gtk::ScrolledWindow,
     text_view: gtk::TextView,
+    search_view: gtk::TextView,
+    search_entry: gtk::SearchEntry,
+    outcome_filter: gtk::ComboBoxText,
+    previous_sessions: gtk::CheckButton,
+    session: String,
+    session_entries: RefCell<Vec<LogEntry>>,
+    // where each of this session's entries starts in the live log
+    live_marks: RefCell<Vec<gtk::TextMark>>,
+    search_entries: RefCell<Vec<(gtk::TextMark, LogEntry)>>,
+    // the session whose header was last written to the search results
+    search_session: RefCell<String>,
+    // the entries in the log file (so that it's only read when it changes)
+    log_cache: RefCell<Option<(PathBuf, Vec<LogEntry>)>>,
+    popup_menu: gtk::Menu,
+    rerun_menu_item: gtk::MenuItem,
+    copy_cmd_menu_item: gtk::MenuItem,
+    copy_output_menu_item: gtk::MenuItem,
+    export_menu_item: gtk::MenuItem,
+    chosen_entry: RefCell<Option<LogEntry>>,
+    spinner: gtk::Spinner,
+    running_label: gtk::Label,
+    cancel_button: gtk::Button,
+    running: Cell<bool>,
+    cmd_notified: Cell<Option<Instant>>,
+    cancel_requested: Rc<Cell<bool>>,
     pub update_button: gtk::Button,
     pub event_notifier: Rc<EventNotifier>,
     pub changed_condns_notifier: Rc<ChangedCondnsNotifier>,
     pub managed_buttons: Rc<ConditionalWidgetGroups<gtk::Button>>,
     pub managed_check_buttons: Rc<ConditionalWidgetGroups<gtk::CheckButton>>,
     pub managed_menu_items: Rc<ConditionalWidgetGroups<gtk::MenuItem>>,
+    pub ws_path: WsPath,
     auto_update: Rc<timeout::ControlledTimeoutCycle>,
+    polling_required: Cell<bool>,
 }
 
 impl ExecConsole {
@@ -67,25 +176,162 @@ impl ExecConsole {
             Some(&changed_condns_notifier),
         );
         let adj: Option<&gtk::Adjustment> = None;
+        let session = format!(
+            "{}-{}",
+            Local::now().format("%Y-%m-%d-%H-%M-%S%.3f"),
+            process::id()
+        );
         let ec = Rc::new(Self {
+            v_box: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            stack: gtk::Stack::new(),
             scrolled_window: gtk::ScrolledWindow::new(adj, adj),
             text_view: gtk::TextView::new(),
+            search_view: gtk::TextView::new(),
+            search_entry: gtk::SearchEntry::new(),
+            outcome_filter: gtk::ComboBoxText::new(),
+            previous_sessions: gtk::CheckButton::with_label("Previous Sessions"),
+            session,
+            session_entries: RefCell::new(vec![]),
+            live_marks: RefCell::new(vec![]),
+            search_entries: RefCell::new(vec![]),
+            search_session: RefCell::new(String::new()),
+            log_cache: RefCell::new(None),
+            popup_menu: gtk::Menu::new(),
+            rerun_menu_item: gtk::MenuItem::with_label("Re-run"),
+            copy_cmd_menu_item: gtk::MenuItem::with_label("Copy command"),
+            copy_output_menu_item: gtk::MenuItem::with_label("Copy output"),
+            export_menu_item: gtk::MenuItem::with_label("Export session as shell script"),
+            chosen_entry: RefCell::new(None),
+            spinner: gtk::Spinner::new(),
+            running_label: gtk::Label::new(None),
+            cancel_button: gtk::Button::with_label("Cancel"),
+            running: Cell::new(false),
+            cmd_notified: Cell::new(None),
+            cancel_requested: Rc::new(Cell::new(false)),
             update_button: gtk::Button::with_label("Update"),
             event_notifier: EventNotifier::new(),
             changed_condns_notifier,
             managed_buttons,
             managed_check_buttons,
             managed_menu_items,
+            ws_path: WsPath::default(),
             auto_update: timeout::ControlledTimeoutCycle::new("Auto Update", true, 10),
+            polling_required: Cell::new(true),
         });
         ec.text_view.set_editable(false);
         ec.scrolled_window
             .set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Always);
         ec.scrolled_window.add(&ec.text_view);
+        ec.search_view.set_editable(false);
+        let search_scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        search_scrolled_window.set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Always);
+        search_scrolled_window.add(&ec.search_view);
+        ec.stack.add_named(&ec.scrolled_window, "live");
+        ec.stack.add_named(&search_scrolled_window, "search");
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        h_box.pack_start(&ec.spinner, false, false, 0);
+        h_box.pack_start(&ec.running_label, false, false, 0);
+        h_box.pack_end(&ec.cancel_button, false, false, 0);
+        ec.v_box.pack_start(&h_box, false, false, 0);
+        ec.search_entry
+            .set_tooltip_text(Some("Search logged commands and their output"));
+        ec.outcome_filter.append(Some("all"), "All");
+        ec.outcome_filter.append(Some("succeeded"), "Succeeded");
+        ec.outcome_filter.append(Some("failed"), "Failed");
+        ec.outcome_filter.set_active_id(Some("all"));
+        ec.previous_sessions.set_tooltip_text(Some(
+            "Include commands from earlier sessions in this repository",
+        ));
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        h_box.pack_start(&ec.search_entry, true, true, 0);
+        h_box.pack_start(&ec.outcome_filter, false, false, 0);
+        h_box.pack_start(&ec.previous_sessions, false, false, 0);
+        ec.v_box.pack_start(&h_box, false, false, 0);
+        ec.v_box.pack_start(&ec.stack, true, true, 0);
+        ec.v_box.show_all();
+        ec.stack.set_visible_child_name("live");
+        ec.cancel_button
+            .set_tooltip_text(Some("Kill the currently running command"));
+        ec.cancel_button.set_sensitive(false);
+        let cancel_requested = Rc::clone(&ec.cancel_requested);
+        ec.cancel_button
+            .connect_clicked(move |_| cancel_requested.set(true));
         ec.append_bold("% ");
 
+        let ec_clone = Rc::clone(&ec);
+        ec.search_entry
+            .connect_search_changed(move |_| ec_clone.update_search_results());
+        let ec_clone = Rc::clone(&ec);
+        ec.outcome_filter
+            .connect_changed(move |_| ec_clone.update_search_results());
+        let ec_clone = Rc::clone(&ec);
+        ec.previous_sessions.connect_toggled(move |_| {
+            // other windows may have added to the log
+            ec_clone.log_cache.replace(None);
+            ec_clone.update_search_results()
+        });
+        let ec_clone = Rc::clone(&ec);
+        ec.event_notifier.add_notification_cb(
+            events::EV_CHANGE_DIR,
+            Box::new(move |_| ec_clone.update_search_results()),
+        );
+
+        ec.rerun_menu_item
+            .set_tooltip_text(Some("Run the command again in the current directory"));
+        ec.export_menu_item.set_tooltip_text(Some(
+            "Save the git commands run in this session as a shell script",
+        ));
+        ec.popup_menu.append(&ec.rerun_menu_item);
+        ec.popup_menu.append(&ec.copy_cmd_menu_item);
+        ec.popup_menu.append(&ec.copy_output_menu_item);
+        ec.popup_menu.append(&gtk::SeparatorMenuItem::new());
+        ec.popup_menu.append(&ec.export_menu_item);
+        ec.popup_menu.show_all();
+        let ec_clone = Rc::clone(&ec);
+        ec.rerun_menu_item.connect_activate(move |_| {
+            let cmd = ec_clone
+                .chosen_entry
+                .borrow()
+                .as_ref()
+                .and_then(|entry| GitCommand::from_argv(&entry.argv));
+            if let Some(cmd) = cmd {
+                let result = ec_clone.exec_cmd(&cmd, events::EV_AUTO_UPDATE);
+                ec_clone.report_any_command_problems(&cmd.to_string(), &result);
+            }
+        });
+        let ec_clone = Rc::clone(&ec);
+        ec.copy_cmd_menu_item.connect_activate(move |_| {
+            if let Some(ref entry) = *ec_clone.chosen_entry.borrow() {
+                gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&entry.cmd);
+            }
+        });
+        let ec_clone = Rc::clone(&ec);
+        ec.copy_output_menu_item.connect_activate(move |_| {
+            if let Some(ref entry) = *ec_clone.chosen_entry.borrow() {
+                gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&entry.output());
+            }
+        });
+        let ec_clone = Rc::clone(&ec);
+        ec.export_menu_item
+            .connect_activate(move |_| ec_clone.export_session_script());
+        for text_view in [&ec.text_view, &ec.search_view].iter() {
+            let ec_clone = Rc::clone(&ec);
+            text_view.connect_button_press_event(move |view, event| {
+                if event.get_button() == 3 {
+                    ec_clone.popup_entry_menu(view, event);
+                    return Inhibit(true);
+                }
+                Inhibit(false)
+            });
+        }
+
         let ec_clone = Rc::clone(&ec);
         ec.auto_update.register_callback(Box::new(move || {
+            // a working file system watcher makes polling unnecessary
+            if !ec_clone.polling_required.get() {
+                return;
+            }
+            ec_clone.check_repo_states();
             ec_clone
                 .event_notifier
                 .notify_events(events::EV_AUTO_UPDATE);
@@ -96,6 +342,7 @@ impl ExecConsole {
         ec.update_button.set_image_position(gtk::PositionType::Top);
         let ec_clone = Rc::clone(&ec);
         ec.update_button.connect_clicked(move |_| {
+            ec_clone.check_repo_states();
             ec_clone
                 .event_notifier
                 .notify_events(events::EV_AUTO_UPDATE)
@@ -106,21 +353,16 @@ impl ExecConsole {
         ec
     }
 
+    pub fn set_polling_required(&self, polling_required: bool) {
+        self.polling_required.set(polling_required);
+    }
+
     pub fn auto_update_check_item(&self) -> gtk::CheckMenuItem {
         self.auto_update.check_menu_item()
     }
 
     fn append_markup(&self, markup: &str) {
-        let bfr = self
-            .text_view
-            .get_buffer()
-            .expect("failed to find text buffer");
-        let mut model_iter = bfr.get_end_iter();
-        bfr.insert_markup(&mut model_iter, markup);
-        if let Some(eobuf) = bfr.create_mark(Some("eobuf"), &bfr.get_end_iter(), false) {
-            self.text_view
-                .scroll_to_mark(&eobuf, 0.001, false, 0.0, 0.0);
-        };
+        append_markup_to(&self.text_view, markup);
     }
 
     fn append_bold(&self, text: &str) {
@@ -164,51 +406,384 @@ impl ExecConsole {
         self.append_markup(&markup);
     }
 
-    pub fn exec_cmd(&self, cmd: &str, events: u64) -> io::Result<Output> {
+    fn set_running(&self, cmd: Option<&str>) {
+        if let Some(cmd) = cmd {
+            self.running.set(true);
+            self.cancel_requested.set(false);
+            self.running_label.set_text(&format!("Running: {cmd}"));
+            self.spinner.start();
+            self.cancel_button.set_sensitive(true);
+        } else {
+            self.running.set(false);
+            self.running_label.set_text("");
+            self.spinner.stop();
+            self.cancel_button.set_sensitive(false);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.get()
+    }
+
+    // Whether the most recent command notified its events less than "settle"
+    // ago (so that recent file system changes have already been accounted for)
+    pub fn cmd_notified_within(&self, settle: Duration) -> bool {
+        self.cmd_notified
+            .get()
+            .map_or(false, |notified| notified.elapsed() < settle)
+    }
+
+    // Run the command in a child process and keep the GUI alive (processing
+    // pending events) while its output is streamed into the transaction log.
+    pub fn exec_cmd<E: Into<Events>>(&self, git_cmd: &GitCommand, events: E) -> io::Result<Output> {
+        if self.running.get() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "another command is still running",
+            ));
+        }
+        let cmd = git_cmd.to_string();
+        let start_mark = self.end_mark(&self.text_view);
         let dt = DateTime::<Local>::from(SystemTime::now());
-        self.append_bold(&format!("{}: ", dt.format("%Y-%m-%d-%H-%M-%S")));
-        self.append_cmd(cmd);
+        let timestamp = dt.format("%Y-%m-%d-%H-%M-%S").to_string();
+        self.append_bold(&format!("{timestamp}: "));
+        self.append_cmd(&cmd);
         yield_to_pending_events!();
-        let cmd_line = shlex::split(cmd).unwrap();
-        let output = Command::new(&cmd_line[0]).args(&cmd_line[1..]).output()?;
+        let started = Instant::now();
+        let mut child = match git_cmd
+            .command(&self.ws_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
         {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            self.append_stdout(&stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            self.append_stderr(&stderr);
-            self.append_bold("% ");
+            Ok(child) => child,
+            Err(err) => {
+                self.append_stderr(&format!("{err}\n"));
+                self.append_bold("% ");
+                self.log_transaction(
+                    LogEntry {
+                        session: self.session.clone(),
+                        timestamp,
+                        cwd: self.ws_path.path_string(),
+                        cmd,
+                        argv: git_cmd.argv(),
+                        exit_code: None,
+                        duration_ms: started.elapsed().as_millis() as u64,
+                        stdout: String::new(),
+                        stderr: format!("{err}\n"),
+                    },
+                    start_mark,
+                );
+                return Err(err);
+            }
+        };
+        self.set_running(Some(&cmd));
+        let (sender, receiver) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, sender.clone(), ChildOutput::Stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, sender, ChildOutput::Stderr);
         }
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        let mut killed = false;
+        let status = loop {
+            for chunk in receiver.try_iter() {
+                self.append_child_output(chunk, &mut stdout, &mut stderr);
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => (),
+                Err(err) => break Err(err),
+            }
+            if self.cancel_requested.get() && !killed {
+                killed = child.kill().is_ok();
+            }
+            yield_to_pending_events!();
+            thread::sleep(Duration::from_millis(20));
+        };
+        // The channel disconnects once both readers have reached end of file
+        let deadline = Instant::now() + OUTPUT_DRAIN_TIMEOUT;
+        loop {
+            match receiver.try_recv() {
+                Ok(chunk) => self.append_child_output(chunk, &mut stdout, &mut stderr),
+                Err(mpsc::TryRecvError::Disconnected) => break,
+                Err(mpsc::TryRecvError::Empty) => {
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+                    yield_to_pending_events!();
+                    thread::sleep(Duration::from_millis(20));
+                }
+            }
+        }
+        if let Err(ref err) = status {
+            let text = format!("{err}\n");
+            self.append_stderr(&text);
+            stderr.extend(text.into_bytes());
+        }
+        if killed {
+            self.append_stderr("Cancelled by user.\n");
+        }
+        self.append_bold("% ");
+        self.set_running(None);
+        // the command may have started or finished a merge, rebase, etc.
+        self.check_repo_states();
         yield_to_pending_events!();
-        if output.status.success() && events != 0 {
+        self.log_transaction(
+            LogEntry {
+                session: self.session.clone(),
+                timestamp,
+                cwd: self.ws_path.path_string(),
+                cmd,
+                argv: git_cmd.argv(),
+                exit_code: status.as_ref().ok().and_then(|status| status.code()),
+                duration_ms: started.elapsed().as_millis() as u64,
+                stdout: String::from_utf8_lossy(&stdout).to_string(),
+                stderr: String::from_utf8_lossy(&stderr).to_string(),
+            },
+            start_mark,
+        );
+        let status = status?;
+        let output = Output {
+            status,
+            stdout,
+            stderr,
+        };
+        let events = events.into();
+        if output.status.success() && !events.is_empty() {
+            self.cmd_notified.set(Some(Instant::now()));
             self.event_notifier.notify_events(events)
         }
         Ok(output)
     }
 
+    fn append_child_output(&self, chunk: ChildOutput, stdout: &mut Vec<u8>, stderr: &mut Vec<u8>) {
+        match chunk {
+            ChildOutput::Stdout(bytes) => {
+                self.append_stdout(&String::from_utf8_lossy(&bytes));
+                stdout.extend(bytes);
+            }
+            ChildOutput::Stderr(bytes) => {
+                self.append_stderr(&String::from_utf8_lossy(&bytes));
+                stderr.extend(bytes);
+            }
+        }
+    }
+
+    // The repository (or directory if not in a repository) whose log is in use
+    fn log_dir_path(&self) -> String {
+        let ws_path = self.ws_path.path_string();
+        repos::get_repo_workdir_for_path(&ws_path).unwrap_or(ws_path)
+    }
+
+    fn log_transaction(&self, entry: LogEntry, start_mark: gtk::TextMark) {
+        let log_path = txn_log::log_file_path(&self.log_dir_path());
+        match txn_log::append_entry(&log_path, &entry) {
+            Ok(false) => {
+                if let Some((path, entries)) = self.log_cache.borrow_mut().as_mut() {
+                    if path == &log_path {
+                        entries.push(entry.capped());
+                    }
+                }
+            }
+            Ok(true) => {
+                self.log_cache.replace(None);
+            }
+            Err(err) => {
+                self.append_stderr(&format!("Failed to save transaction log entry: {err}\n"));
+                self.append_bold("% ");
+            }
+        }
+        // only the new entry needs adding to any search results on display
+        if self.stack.get_visible_child_name().as_deref() == Some("search")
+            && entry.matches(&self.search_entry.get_text(), self.outcome_filter())
+        {
+            self.show_search_result(&entry, self.previous_sessions.get_active());
+        }
+        self.session_entries.borrow_mut().push(entry);
+        self.live_marks.borrow_mut().push(start_mark);
+    }
+
+    fn refresh_log_cache(&self, log_path: &Path) {
+        let mut log_cache = self.log_cache.borrow_mut();
+        if log_cache
+            .as_ref()
+            .map_or(true, |(path, _)| path != log_path)
+        {
+            let entries = txn_log::read_entries(log_path).unwrap_or_default();
+            *log_cache = Some((log_path.to_path_buf(), entries));
+        }
+    }
+
+    fn outcome_filter(&self) -> OutcomeFilter {
+        match self.outcome_filter.get_active_id().as_deref() {
+            Some("succeeded") => OutcomeFilter::Succeeded,
+            Some("failed") => OutcomeFilter::Failed,
+            _ => OutcomeFilter::All,
+        }
+    }
+
+    // Show the live log unless the user is searching/filtering in which case
+    // show the matching entries (including earlier sessions' if requested).
+    fn update_search_results(&self) {
+        let search = self.search_entry.get_text().to_string();
+        let outcome = self.outcome_filter();
+        let previous_sessions = self.previous_sessions.get_active();
+        if search.is_empty() && outcome == OutcomeFilter::All && !previous_sessions {
+            self.stack.set_visible_child_name("live");
+            return;
+        }
+        let bfr = self
+            .search_view
+            .get_buffer()
+            .expect("failed to find text buffer");
+        for (mark, _) in self.search_entries.borrow_mut().drain(..) {
+            bfr.delete_mark(&mark);
+        }
+        bfr.set_text("");
+        self.search_session.borrow_mut().clear();
+        let matches: Vec<LogEntry> = if previous_sessions {
+            let log_path = txn_log::log_file_path(&self.log_dir_path());
+            self.refresh_log_cache(&log_path);
+            match self.log_cache.borrow().as_ref() {
+                Some((_, entries)) => entries
+                    .iter()
+                    .filter(|e| e.matches(&search, outcome))
+                    .cloned()
+                    .collect(),
+                None => vec![],
+            }
+        } else {
+            self.session_entries
+                .borrow()
+                .iter()
+                .filter(|e| e.matches(&search, outcome))
+                .cloned()
+                .collect()
+        };
+        for entry in matches.iter() {
+            self.show_search_result(entry, previous_sessions);
+        }
+        self.stack.set_visible_child_name("search");
+    }
+
+    fn show_search_result(&self, entry: &LogEntry, previous_sessions: bool) {
+        if previous_sessions && *self.search_session.borrow() != entry.session {
+            self.search_session.replace(entry.session.clone());
+            let markup = format!(
+                r###"<span foreground="#0000AA" weight="bold" font_family="monospace">Session {}</span>"###,
+                escape::escape_str_attribute(&entry.session)
+            );
+            append_markup_to(&self.search_view, &markup);
+            append_markup_to(&self.search_view, "\n");
+        }
+        let start_mark = self.end_mark(&self.search_view);
+        self.search_entries
+            .borrow_mut()
+            .push((start_mark, entry.clone()));
+        append_markup_to(&self.search_view, &entry_markup(entry));
+    }
+
+    // A mark that stays put (i.e. before) when text is appended
+    fn end_mark(&self, text_view: &gtk::TextView) -> gtk::TextMark {
+        let bfr = text_view.get_buffer().expect("failed to find text buffer");
+        bfr.create_mark(None, &bfr.get_end_iter(), true)
+            .expect("failed to create text mark")
+    }
+
+    fn entry_at_event(
+        &self,
+        text_view: &gtk::TextView,
+        event: &gdk::EventButton,
+    ) -> Option<LogEntry> {
+        let (x, y) = event.get_position();
+        let (x, y) =
+            text_view.window_to_buffer_coords(gtk::TextWindowType::Text, x as i32, y as i32);
+        let offset = text_view.get_iter_at_location(x, y)?.get_offset();
+        let bfr = text_view.get_buffer()?;
+        let starts_before =
+            |mark: &gtk::TextMark| bfr.get_iter_at_mark(mark).get_offset() <= offset;
+        if text_view == &self.text_view {
+            let index = self.live_marks.borrow().iter().rposition(starts_before)?;
+            self.session_entries.borrow().get(index).cloned()
+        } else {
+            self.search_entries
+                .borrow()
+                .iter()
+                .rev()
+                .find(|(mark, _)| starts_before(mark))
+                .map(|(_, entry)| entry.clone())
+        }
+    }
+
+    fn popup_entry_menu(&self, text_view: &gtk::TextView, event: &gdk::EventButton) {
+        let entry = self.entry_at_event(text_view, event);
+        let rerunnable = entry
+            .as_ref()
+            .map_or(false, |entry| GitCommand::from_argv(&entry.argv).is_some());
+        self.rerun_menu_item
+            .set_sensitive(rerunnable && !self.is_running());
+        self.copy_cmd_menu_item.set_sensitive(entry.is_some());
+        self.copy_output_menu_item.set_sensitive(entry.is_some());
+        self.export_menu_item
+            .set_sensitive(!self.session_entries.borrow().is_empty());
+        *self.chosen_entry.borrow_mut() = entry;
+        self.popup_menu.popup_at_pointer(Some(event));
+    }
+
+    fn export_session_script(&self) {
+        if let Some(path) = self.browse_path(
+            Some("Script Path"),
+            None,
+            gtk::FileChooserAction::Save,
+            true,
+        ) {
+            let script = txn_log::session_script(&self.session, &self.session_entries.borrow());
+            let result = fs::write(&path, script)
+                .and_then(|_| fs::set_permissions(&path, fs::Permissions::from_mode(0o755)));
+            if let Err(err) = result {
+                let msg = format!("Error exporting session to \"{}\"", path.to_string_lossy());
+                self.report_error(&msg, &err);
+            }
+        }
+    }
+
     pub fn in_repo(&self) -> bool {
-        repos::is_repo_workdir(".")
+        repos::is_repo_workdir(&self.ws_path.path_string())
     }
 
     pub fn check_repo_states(&self) {
-        let masked_condns = repos::get_repo_condns();
+        let masked_condns = repos::get_repo_condns(&self.ws_path);
         self.changed_condns_notifier
             .notify_changed_condns(masked_condns);
     }
 
+    // Change this console's workspace directory (the process's current directory is
+    // left alone as other windows may have different workspaces).  Relative paths
+    // are interpreted relative to the current workspace directory.
     pub fn chdir(&self, new_dir_path: &str) {
-        // TODO: add notification of events to chdir()
         self.append_cmd(&format!("chdir {}", shlex::quote(new_dir_path)));
-        let mut adj_dir_path: String = new_dir_path.to_string();
+        let mut adj_dir_path: String = self.ws_path.join(new_dir_path);
         let mut adjusted = false;
         let mut in_repo = false;
-        if repos::is_repo_workdir(new_dir_path) {
+        if repos::is_repo_workdir(&adj_dir_path) {
             in_repo = true;
-        } else if let Some(path) = repos::get_repo_workdir_for_path(new_dir_path) {
+        } else if let Some(path) = repos::get_repo_workdir_for_path(&adj_dir_path) {
             adj_dir_path = path;
             adjusted = true;
             in_repo = true;
         }
-        match env::set_current_dir(&adj_dir_path) {
+        let result = fs::canonicalize(&adj_dir_path).and_then(|abs_path| {
+            if abs_path.is_dir() {
+                Ok(abs_path)
+            } else {
+                Err(io::Error::new(io::ErrorKind::Other, "Not a directory"))
+            }
+        });
+        match result {
             Err(err) => {
                 let stderr = format!("{err}\n");
                 self.append_stderr(&stderr);
@@ -216,7 +791,8 @@ impl ExecConsole {
                 let msg = format!("chdir {} failed", shlex::quote(new_dir_path));
                 self.report_error(&msg, &err);
             }
-            Ok(_) => {
+            Ok(abs_path) => {
+                self.ws_path.set(&abs_path);
                 if in_repo {
                     if adjusted {
                         let string = format!(
@@ -230,10 +806,9 @@ impl ExecConsole {
                         self.append_stdout("Now in valid repo directory.\n");
                         self.append_bold("% ");
                     }
-                    if let Ok(abs_path) = ".".path_absolute() {
-                        recollections::remember("last:git:ws:dir", &abs_path);
-                        repos::add_to_known_repos(&abs_path).expect("saving known repos failed");
-                    }
+                    let abs_path = self.ws_path.path_string();
+                    recollections::remember("last:git:ws:dir", &abs_path);
+                    repos::add_to_known_repos(&abs_path).expect("saving known repos failed");
                 } else {
                     self.append_bold("% ");
                 }
@@ -269,10 +844,10 @@ pub fn create_files_menu(exec_console: &Rc<ExecConsole>) -> gtk::MenuItem {
         .add_widget("init", &init_menu_item, repos::SAV_NOT_IN_REPO);
     let ec_clone = Rc::clone(exec_console);
     init_menu_item.connect_activate(move |_| {
-        let cmd = "git init";
-        let result = ec_clone.exec_cmd(cmd, events::EV_CHANGE_DIR);
+        let cmd = GitCommand::new("init");
+        let result = ec_clone.exec_cmd(&cmd, events::EV_CHANGE_DIR);
         ec_clone.check_repo_states();
-        ec_clone.report_any_command_problems(cmd, &result);
+        ec_clone.report_any_command_problems(&cmd.to_string(), &result);
     });
     menu.append(&init_menu_item);
 
@@ -298,7 +873,7 @@ pub fn create_friends_menu(exec_console: &Rc<ExecConsole>) -> gtk::MenuItem {
         let menu_item = gtk::MenuItem::with_label(friend);
         let ec_clone = Rc::clone(exec_console);
         menu_item.connect_activate(move |_| {
-            if let Err(err) = Command::new(friend).arg(".").spawn() {
+            if let Err(err) = ec_clone.ws_path.command(friend).arg(".").spawn() {
                 let msg = format!("Error running \"{friend}\"");
                 ec_clone.report_error(&msg, &err);
             }
@@ -12,19 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// TODO: make a porcelain version 2 implementation of this functionality
-
 use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::iter::Iterator;
-use std::path::MAIN_SEPARATOR;
-use std::process::Command;
 use std::rc::Rc;
 use std::slice::Iter;
 
 use crypto_hash::{Algorithm, Hasher};
-use regex::Regex;
 
 use pw_gix::{
     fs_db::{FsDbIfce, FsObjectIfce, TreeRowOps},
@@ -36,33 +31,8 @@ use pw_gix::{
 use pw_pathux::str_path::*;
 use pw_pathux::UsableDirEntry;
 
-const NO_STATUS: &str = "";
-const UNMODIFIED: &str = "  ";
-const WD_ONLY_MODIFIED: &str = " M";
-const WD_ONLY_DELETED: &str = " D";
-const MODIFIED: &str = "M ";
-const MODIFIED_MODIFIED: &str = "MM";
-const MODIFIED_DELETED: &str = "MD";
-const ADDED: &str = "A ";
-const ADDED_MODIFIED: &str = "AM";
-const ADDED_DELETED: &str = "AD";
-const DELETED: &str = "D ";
-const DELETED_MODIFIED: &str = "DM";
-const RENAMED: &str = "R ";
-const RENAMED_MODIFIED: &str = "RM";
-const RENAMED_DELETED: &str = "RD";
-const COPIED: &str = "C ";
-const COPIED_MODIFIED: &str = "CM";
-const COPIED_DELETED: &str = "CD";
-const UNMERGED: &str = "UU";
-const UNMERGED_ADDED: &str = "AA";
-const UNMERGED_ADDED_US: &str = "AU";
-const UNMERGED_ADDED_THEM: &str = "UA";
-const UNMERGED_DELETED: &str = "DD";
-const UNMERGED_DELETED_US: &str = "DU";
-const UNMERGED_DELETED_THEM: &str = "DA";
-const NOT_TRACKED: &str = "??";
-const IGNORED: &str = "!!";
+use gwsm_git_core::status::*;
+use gwsm_git_core::workspace::WsPath;
 
 lazy_static! {
     static ref SCM_FS_DB_ROW_SPEC: [glib::Type; 9] =
@@ -127,10 +97,11 @@ pub const FOREGROUND: i32 = 6;
 pub const STYLE: i32 = 7;
 pub const IS_DIR: i32 = 8;
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct RelatedFileData {
-    file_path: String,
-    relation: String,
+fn set_file_status<FSOI: ScmFsoDataIfce>(fso_data: &mut FSOI, file_status: &StatusEntry) {
+    fso_data.set_status(&file_status.status);
+    fso_data.set_related_file_data(&file_status.related_file_data);
+    fso_data.set_rename_score(file_status.rename_score);
+    fso_data.set_submodule_state(file_status.submodule_state);
 }
 
 #[derive(Debug, Clone)]
@@ -140,6 +111,8 @@ pub struct ScmFsoData {
     status: String,
     clean_status: String,
     related_file_data: Option<RelatedFileData>,
+    rename_score: Option<u8>,
+    submodule_state: Option<SubmoduleState>,
     is_dir: bool,
 }
 
@@ -147,6 +120,8 @@ pub trait ScmFsoDataIfce {
     fn set_status(&mut self, status: &str);
     fn set_clean_status(&mut self, clean_status: &str);
     fn set_related_file_data(&mut self, related_file_data: &Option<RelatedFileData>);
+    fn set_rename_score(&mut self, rename_score: Option<u8>);
+    fn set_submodule_state(&mut self, submodule_state: Option<SubmoduleState>);
     fn is_visible(&self, show_hidden: bool, hide_clean: bool) -> bool;
 }
 
@@ -163,6 +138,14 @@ impl ScmFsoDataIfce for ScmFsoData {
         self.related_file_data = related_file_data.clone();
     }
 
+    fn set_rename_score(&mut self, rename_score: Option<u8>) {
+        self.rename_score = rename_score;
+    }
+
+    fn set_submodule_state(&mut self, submodule_state: Option<SubmoduleState>) {
+        self.submodule_state = submodule_state;
+    }
+
     fn is_visible(&self, show_hidden: bool, hide_clean: bool) -> bool {
         if self.is_dir {
             if show_hidden {
@@ -191,6 +174,14 @@ impl ScmFsoDataIfce for ScmFsoData {
 }
 
 impl ScmFsoData {
+    pub fn rename_score(&self) -> Option<u8> {
+        self.rename_score
+    }
+
+    pub fn submodule_state(&self) -> Option<SubmoduleState> {
+        self.submodule_state
+    }
+
     fn is_hidden_dir(&self) -> bool {
         if self.name.starts_with('.') {
             !SIGNIFICANT_SET.contains(&self.status.as_str())
@@ -265,6 +256,8 @@ impl FsObjectIfce for ScmFsoData {
             status: NO_STATUS.to_string(),
             clean_status: NO_STATUS.to_string(),
             related_file_data: None,
+            rename_score: None,
+            submodule_state: None,
             is_dir,
         }
     }
@@ -276,6 +269,8 @@ impl FsObjectIfce for ScmFsoData {
             status: NO_STATUS.to_string(),
             clean_status: NO_STATUS.to_string(),
             related_file_data: None,
+            rename_score: None,
+            submodule_state: None,
             is_dir: dir_entry.is_dir(),
         }
     }
@@ -491,11 +486,12 @@ lazy_static! {
     };
 }
 
-fn is_ignored_path(path: &str) -> bool {
+fn is_ignored_path(ws_path: &WsPath, path: &str) -> bool {
     // TODO: examine caching ignore::gitignore::Gitignore instances
     use ignore::gitignore::Gitignore;
-    if ".gitignore".path_is_file() {
-        let (git_ignore, _) = Gitignore::new(".gitignore");
+    let gitignore_path = ws_path.join(".gitignore");
+    if gitignore_path.path_is_file() {
+        let (git_ignore, _) = Gitignore::new(&gitignore_path);
         git_ignore.matched(path, true).is_ignore()
     } else {
         let (git_ignore, _) = Gitignore::global();
@@ -506,6 +502,7 @@ fn is_ignored_path(path: &str) -> bool {
 fn first_status_in_set(
     status_list: &[&'static str],
     status_set: &HashSet<&str>,
+    ws_path: &WsPath,
     path: Option<&str>,
 ) -> &'static str {
     for status in status_list.iter() {
@@ -514,9 +511,9 @@ fn first_status_in_set(
         }
     }
     let ignored = if let Some(path) = path {
-        is_ignored_path(path)
+        is_ignored_path(ws_path, path)
     } else {
-        is_ignored_path(".")
+        is_ignored_path(ws_path, ".")
     };
     if ignored {
         IGNORED
@@ -525,7 +522,7 @@ fn first_status_in_set(
     }
 }
 
-type FileStatusData = Rc<HashMap<String, (String, Option<RelatedFileData>)>>;
+type FileStatusData = Rc<HashMap<String, StatusEntry>>;
 
 #[derive(Debug, Clone)]
 struct Snapshot {
@@ -534,11 +531,13 @@ struct Snapshot {
     relevant_keys: Vec<String>,
     status: String,
     clean_status: String,
+    ws_path: WsPath,
 }
 
 impl Snapshot {
     fn iter(&self) -> SnapshotIterator<'_> {
         SnapshotIterator {
+            ws_path: self.ws_path.clone(),
             num_dir_components: self.num_dir_components,
             file_status_data: Rc::clone(&self.file_status_data),
             relevant_keys_iter: self.relevant_keys.iter(),
@@ -555,23 +554,35 @@ impl Snapshot {
             .collect();
         let mut status_set = HashSet::new();
         for key in relevant_keys.iter() {
-            let (status, _) = self.file_status_data.get(key).unwrap();
-            status_set.insert(status.as_str());
+            let file_status = self.file_status_data.get(key).unwrap();
+            status_set.insert(file_status.status.as_str());
         }
-        let status = first_status_in_set(&ORDERED_DIR_STATUS_LIST, &status_set, Some(dir_path));
-        let clean_status =
-            first_status_in_set(&ORDERED_DIR_CLEAN_STATUS_LIST, &status_set, Some(dir_path));
+        let ws_path = &self.ws_path;
+        let status = first_status_in_set(
+            &ORDERED_DIR_STATUS_LIST,
+            &status_set,
+            ws_path,
+            Some(dir_path),
+        );
+        let clean_status = first_status_in_set(
+            &ORDERED_DIR_CLEAN_STATUS_LIST,
+            &status_set,
+            ws_path,
+            Some(dir_path),
+        );
         Self {
             num_dir_components: dir_path.path_components().len(),
             file_status_data: Rc::clone(&self.file_status_data),
             relevant_keys,
             status: status.to_string(),
             clean_status: clean_status.to_string(),
+            ws_path: ws_path.clone(),
         }
     }
 }
 
 struct SnapshotIterator<'a> {
+    ws_path: WsPath,
     num_dir_components: usize,
     file_status_data: FileStatusData,
     relevant_keys_iter: Iter<'a, String>,
@@ -579,7 +590,7 @@ struct SnapshotIterator<'a> {
 }
 
 impl<'a> Iterator for SnapshotIterator<'a> {
-    type Item = (String, String, bool, String, Option<RelatedFileData>);
+    type Item = (String, String, bool, StatusEntry);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -589,21 +600,16 @@ impl<'a> Iterator for SnapshotIterator<'a> {
                     // This is me and I'm a submodule
                     continue;
                 }
-                let (status, related_file_data) = self.file_status_data.get(file_path).unwrap();
+                let file_status = self.file_status_data.get(file_path).unwrap();
                 let path = components[..self.num_dir_components + 1].to_string_path();
-                let is_dir = components.len() > self.num_dir_components + 1 || path.path_is_dir();
+                let is_dir = components.len() > self.num_dir_components + 1
+                    || self.ws_path.join(&path).path_is_dir();
                 let name = components[self.num_dir_components].to_string();
                 if self.already_seen.contains(&name) {
                     continue;
                 };
                 self.already_seen.insert(name.clone());
-                return Some((
-                    name,
-                    path,
-                    is_dir,
-                    status.to_string(),
-                    related_file_data.clone(),
-                ));
+                return Some((name, path, is_dir, file_status.clone()));
             } else {
                 return None;
             }
@@ -611,10 +617,15 @@ impl<'a> Iterator for SnapshotIterator<'a> {
     }
 }
 
-fn get_snapshot_text() -> (String, Vec<u8>) {
-    let output = Command::new("git")
+// The "# branch.*" headers aren't needed here but are for the status bar
+// which shares the snapshot's text rather than running "git status" again.
+fn get_snapshot_text(ws_path: &WsPath) -> (String, Vec<u8>) {
+    let output = ws_path
+        .git()
         .arg("status")
-        .arg("--porcelain")
+        .arg("--porcelain=v2")
+        .arg("--branch")
+        .arg("-z")
         .arg("--ignored")
         .arg("--untracked=all")
         .arg("--ignore-submodules=none")
@@ -632,53 +643,10 @@ fn get_snapshot_text() -> (String, Vec<u8>) {
     }
 }
 
-lazy_static! {
-    static ref GIT_FILE_DATA_RE: Regex =
-        Regex::new(r###"(("([^"]+)")|(\S+))( -> (("([^"]+)")|(\S+)))?"###).unwrap();
-}
-
-macro_rules! parse_line {
-    ( $line:ident ) => {{
-        let captures = GIT_FILE_DATA_RE.captures(&$line[3..]).unwrap();
-        let path = if let Some(path) = captures.get(3) {
-            path
-        } else {
-            captures.get(4).unwrap()
-        };
-        let related_file_data = if captures.get(5).is_some() {
-            let file_path = if let Some(path) = captures.get(8) {
-                path
-            } else {
-                captures.get(9).unwrap()
-            };
-            Some(RelatedFileData {
-                file_path: file_path.as_str().to_string(),
-                relation: "->".to_string(),
-            })
-        } else {
-            None
-        };
-        (
-            // git doesn't include "./" but we have to for read_dir() so add for compatibility
-            format!(".{}{}", MAIN_SEPARATOR, path.as_str()),
-            $line[..2].to_string(),
-            related_file_data,
-        )
-    }};
-}
-
-fn extract_snapshot_from_text(text: &str) -> Snapshot {
-    let mut rfd_data: Vec<(String, RelatedFileData)> = vec![];
-    let mut file_status_data: HashMap<String, (String, Option<RelatedFileData>)> = HashMap::new();
-    for line in text.lines() {
-        let (file_path, status, related_file_data) = parse_line!(line);
-        if let Some(ref rfd) = related_file_data {
-            rfd_data.push((file_path.to_string(), rfd.clone()));
-        }
-        file_status_data.insert(
-            file_path.to_string(),
-            (status.to_string(), related_file_data),
-        );
+fn extract_snapshot_from_text(text: &str, ws_path: &WsPath) -> Snapshot {
+    let mut file_status_data: HashMap<String, StatusEntry> = HashMap::new();
+    for entry in parse_porcelain_v2(text) {
+        file_status_data.insert(entry.path.clone(), entry);
     }
     // TODO: add "goes to" related file data
     //for file_path, related_file_path in related_file_path_data:
@@ -696,11 +664,15 @@ fn extract_snapshot_from_text(text: &str) -> Snapshot {
     let status: String;
     let clean_status: String;
     {
-        let status_set: HashSet<&str> =
-            file_status_data.values().map(|(a, _)| a.as_str()).collect();
-        status = first_status_in_set(&ORDERED_DIR_STATUS_LIST, &status_set, None).to_string();
+        let status_set: HashSet<&str> = file_status_data
+            .values()
+            .map(|x| x.status.as_str())
+            .collect();
+        status =
+            first_status_in_set(&ORDERED_DIR_STATUS_LIST, &status_set, ws_path, None).to_string();
         clean_status =
-            first_status_in_set(&ORDERED_DIR_CLEAN_STATUS_LIST, &status_set, None).to_string();
+            first_status_in_set(&ORDERED_DIR_CLEAN_STATUS_LIST, &status_set, ws_path, None)
+                .to_string();
     }
     Snapshot {
         num_dir_components: 1,
@@ -708,6 +680,7 @@ fn extract_snapshot_from_text(text: &str) -> Snapshot {
         relevant_keys,
         status,
         clean_status,
+        ws_path: ws_path.clone(),
     }
 }
 
@@ -747,11 +720,16 @@ where
         }
     }
 
+    // The directory's path in the file system (our paths are relative to the workspace)
+    fn fs_path(&self) -> String {
+        self.snapshot.ws_path.join(&self.path)
+    }
+
     fn current_hash_digest(&self) -> Vec<u8> {
         let mut hasher = Hasher::new(Algorithm::SHA256);
-        if let Ok(dir_entries) = UsableDirEntry::get_entries(&self.path) {
+        if let Ok(dir_entries) = UsableDirEntry::get_entries(&self.fs_path()) {
             for dir_entry in dir_entries {
-                let path = dir_entry.path().to_string_lossy().into_owned();
+                let path = self.path.path_join(&dir_entry.file_name());
                 hasher.write_all(&path.into_bytes()).unwrap()
             }
         }
@@ -778,26 +756,25 @@ where
 
     fn populate(&mut self) {
         let mut hasher = Hasher::new(Algorithm::SHA256);
-        if let Ok(dir_entries) = UsableDirEntry::get_entries(&self.path) {
+        if let Ok(dir_entries) = UsableDirEntry::get_entries(&self.fs_path()) {
             let mut dirs_map = HashMap::new();
             let mut files_map = HashMap::new();
             for dir_entry in dir_entries {
-                let path = dir_entry.path().to_string_path();
-                hasher.write_all(&path.into_bytes()).unwrap();
                 let name = dir_entry.file_name();
+                let path = self.path.path_join(&name);
+                hasher.write_all(path.as_bytes()).unwrap();
                 if dir_entry.is_dir() {
-                    let path = dir_entry.path().to_string_lossy().into_owned();
-                    dirs_map.insert(name, FSOI::from_dir_entry(&dir_entry));
+                    dirs_map.insert(name.clone(), FSOI::new(&name, &path, true));
                     let snapshot = self.snapshot.narrowed_for_dir_path(&path);
                     self.sub_dirs.insert(
-                        dir_entry.file_name(),
+                        name,
                         GitFsDbDir::<FSOI>::new(&path, snapshot, self.show_hidden, self.hide_clean),
                     );
                 } else {
-                    files_map.insert(name, FSOI::from_dir_entry(&dir_entry));
+                    files_map.insert(name.clone(), FSOI::new(&name, &path, false));
                 }
             }
-            for (name, path, is_dir, status, related_file_data) in self.snapshot.iter() {
+            for (name, path, is_dir, file_status) in self.snapshot.iter() {
                 if is_dir {
                     let snapshot = self.snapshot.narrowed_for_dir_path(&path);
                     let status = snapshot.status.clone();
@@ -808,10 +785,12 @@ where
                         let dir_dat = dirs_map.get_mut(&name).expect("stupid rules");
                         dir_dat.set_status(&status);
                         dir_dat.set_clean_status(&clean_status);
+                        dir_dat.set_submodule_state(file_status.submodule_state);
                     } else {
                         let mut dir_dat = FSOI::new(&name, &path, is_dir);
                         dir_dat.set_status(&status);
                         dir_dat.set_clean_status(&clean_status);
+                        dir_dat.set_submodule_state(file_status.submodule_state);
                         dirs_map.insert(name.clone(), dir_dat);
                         self.sub_dirs.insert(
                             name,
@@ -824,12 +803,10 @@ where
                         );
                     }
                 } else if let Some(file_data) = files_map.get_mut(&name) {
-                    file_data.set_status(&status);
-                    file_data.set_related_file_data(&related_file_data);
+                    set_file_status(file_data, &file_status);
                 } else {
                     let mut file_data = FSOI::new(&name, &path, is_dir);
-                    file_data.set_status(&status);
-                    file_data.set_related_file_data(&related_file_data);
+                    set_file_status(&mut file_data, &file_status);
                     dirs_map.insert(name, file_data);
                 }
             }
@@ -899,6 +876,20 @@ where
     }
 }
 
+// The file trees create their databases with with_ws_path() so that the first
+// snapshot is taken in the window's workspace.  FsDbIfce::new() only exists to
+// satisfy that trait and yields an empty database (without running git).
+pub trait WsFsDbIfce {
+    fn with_ws_path(ws_path: &WsPath) -> Self
+    where
+        Self: Sized;
+
+    // The "git status" output (if any) that the database was last updated from
+    fn status_text(&self) -> Option<Rc<String>> {
+        None
+    }
+}
+
 pub struct GitFsDb<FSOI>
 where
     FSOI: FsObjectIfce + ScmFsoDataIfce + Clone,
@@ -906,6 +897,8 @@ where
     base_dir: RefCell<GitFsDbDir<FSOI>>,
     curr_dir: RefCell<String>, // so we can tell if there's a change of current directory
     snapshot_digest: RefCell<Vec<u8>>,
+    snapshot_text: RefCell<Rc<String>>,
+    ws_path: RefCell<WsPath>,
 }
 
 impl<FSOI> FsDbIfce<FSOI> for GitFsDb<FSOI>
@@ -921,15 +914,7 @@ where
     }
 
     fn new() -> Self {
-        let curr_dir = str_path_current_dir_or_panic();
-        let (text, snapshot_digest) = get_snapshot_text();
-        let snapshot = extract_snapshot_from_text(&text);
-        let base_dir = GitFsDbDir::<FSOI>::new(".", snapshot, false, false); // paths are relative
-        Self {
-            base_dir: RefCell::new(base_dir),
-            curr_dir: RefCell::new(curr_dir),
-            snapshot_digest: RefCell::new(snapshot_digest),
-        }
+        Self::from_snapshot_text(&WsPath::default(), String::new(), vec![])
     }
 
     fn dir_contents(
@@ -954,12 +939,14 @@ where
             self.reset();
             true
         } else {
-            let (text, digest) = get_snapshot_text();
+            let ws_path = self.ws_path.borrow();
+            let (text, digest) = get_snapshot_text(&ws_path);
             let mut snapshot_digest = self.snapshot_digest.borrow_mut();
             if digest != *snapshot_digest {
                 *snapshot_digest = digest;
-                let snapshot = extract_snapshot_from_text(&text);
+                let snapshot = extract_snapshot_from_text(&text, &ws_path);
                 *self.base_dir.borrow_mut() = GitFsDbDir::new(".", snapshot, false, false);
+                *self.snapshot_text.borrow_mut() = Rc::new(text);
                 true
             } else if !self.base_dir.borrow_mut().is_current() {
                 let mut base_dir = self.base_dir.borrow_mut();
@@ -973,20 +960,53 @@ where
     }
 
     fn reset(&self) {
-        let (text, digest) = get_snapshot_text();
-        let snapshot = extract_snapshot_from_text(&text);
+        let ws_path = self.ws_path.borrow();
+        let (text, digest) = get_snapshot_text(&ws_path);
+        let snapshot = extract_snapshot_from_text(&text, &ws_path);
         *self.snapshot_digest.borrow_mut() = digest;
-        *self.curr_dir.borrow_mut() = str_path_current_dir_or_panic();
+        *self.snapshot_text.borrow_mut() = Rc::new(text);
+        *self.curr_dir.borrow_mut() = ws_path.path_string();
         *self.base_dir.borrow_mut() = GitFsDbDir::new(".", snapshot, false, false);
     }
 }
 
+impl<FSOI> WsFsDbIfce for GitFsDb<FSOI>
+where
+    FSOI: FsObjectIfce + ScmFsoDataIfce + Clone,
+{
+    fn with_ws_path(ws_path: &WsPath) -> Self {
+        let (text, snapshot_digest) = get_snapshot_text(ws_path);
+        Self::from_snapshot_text(ws_path, text, snapshot_digest)
+    }
+
+    fn status_text(&self) -> Option<Rc<String>> {
+        Some(self.snapshot_text())
+    }
+}
+
 impl<FSOI> GitFsDb<FSOI>
 where
     FSOI: FsObjectIfce + ScmFsoDataIfce + Clone,
 {
+    fn from_snapshot_text(ws_path: &WsPath, text: String, snapshot_digest: Vec<u8>) -> Self {
+        let snapshot = extract_snapshot_from_text(&text, ws_path);
+        let base_dir = GitFsDbDir::<FSOI>::new(".", snapshot, false, false); // paths are relative
+        Self {
+            base_dir: RefCell::new(base_dir),
+            curr_dir: RefCell::new(ws_path.path_string()),
+            snapshot_digest: RefCell::new(snapshot_digest),
+            snapshot_text: RefCell::new(Rc::new(text)),
+            ws_path: RefCell::new(ws_path.clone()),
+        }
+    }
+
+    // The "git status --porcelain=v2 --branch -z" output of the latest update
+    pub fn snapshot_text(&self) -> Rc<String> {
+        Rc::clone(&self.snapshot_text.borrow())
+    }
+
     fn curr_dir_changed(&self) -> bool {
-        *self.curr_dir.borrow() != str_path_current_dir_or_panic()
+        *self.curr_dir.borrow() != self.ws_path.borrow().path_string()
     }
 
     fn check_visibility(&self, show_hidden: bool, hide_clean: bool) {
@@ -1047,14 +1067,15 @@ where
     fn add_file(
         &mut self,
         path_components: &[StrPathComponent],
-        status: &str,
-        related_file_data: &Option<RelatedFileData>,
+        file_status: &StatusEntry,
+        ws_path: &WsPath,
     ) {
+        let status = file_status.status.as_str();
         self.status_set.insert(status.to_string());
         let name = path_components[0].to_string();
         let path = self.path.path_join(&name);
         // NB: handle the case where this may be a submodule directory
-        if path_components.len() > 1 || path.path_is_dir() {
+        if path_components.len() > 1 || ws_path.join(&path).path_is_dir() {
             if !self.sub_dirs.contains_key(&name) {
                 let dir = Self::new(&path, status, self.hide_clean);
                 self.sub_dirs.insert(name.to_string(), dir);
@@ -1062,14 +1083,13 @@ where
             if path_components.len() > 1 {
                 self.sub_dirs.get_mut(&name).expect("wtf?").add_file(
                     &path_components[1..],
-                    status,
-                    related_file_data,
+                    file_status,
+                    ws_path,
                 );
             }
         } else {
             let mut file_data = FSOI::new(&name, &path, false);
-            file_data.set_status(status);
-            file_data.set_related_file_data(related_file_data);
+            set_file_status(&mut file_data, file_status);
             self.files_data_unfiltered.push(file_data);
         }
     }
@@ -1143,12 +1163,15 @@ where
 {
     base_dir: RefCell<GitIndexDbDir<FSOI>>,
     populated_digest: RefCell<Vec<u8>>,
+    ws_path: RefCell<WsPath>,
 }
 
-fn get_digest_text() -> (String, Vec<u8>) {
-    let output = Command::new("git")
+fn get_digest_text(ws_path: &WsPath) -> (String, Vec<u8>) {
+    let output = ws_path
+        .git()
         .arg("status")
-        .arg("--porcelain")
+        .arg("--porcelain=v2")
+        .arg("-z")
         .arg("--untracked-files=no")
         .arg("--ignore-submodules=none")
         .output()
@@ -1179,14 +1202,11 @@ where
 
     fn new() -> Self {
         let base_dir = GitIndexDbDir::<FSOI>::new(".", NO_STATUS, false); // paths are relative
-        let gib = Self {
+        Self {
             base_dir: RefCell::new(base_dir),
             populated_digest: RefCell::new(vec![]),
-        };
-        let (text, text_digest) = get_digest_text();
-        gib.populate(&text, &text_digest);
-
-        gib
+            ws_path: RefCell::new(WsPath::default()),
+        }
     }
 
     fn dir_contents(
@@ -1207,7 +1227,7 @@ where
     }
 
     fn update_if_necessary(&self) -> bool {
-        let (text, digest) = get_digest_text();
+        let (text, digest) = get_digest_text(&self.ws_path.borrow());
         if digest != *self.populated_digest.borrow() {
             self.populate(&text, &digest);
             true
@@ -1217,11 +1237,25 @@ where
     }
 
     fn reset(&self) {
-        let (text, digest) = get_digest_text();
+        let (text, digest) = get_digest_text(&self.ws_path.borrow());
         self.populate(&text, &digest);
     }
 }
 
+impl<FSOI> WsFsDbIfce for GitIndexDb<FSOI>
+where
+    FSOI: FsObjectIfce + ScmFsoDataIfce + Clone,
+{
+    fn with_ws_path(ws_path: &WsPath) -> Self {
+        let gib = Self::new();
+        *gib.ws_path.borrow_mut() = ws_path.clone();
+        let (text, text_digest) = get_digest_text(ws_path);
+        gib.populate(&text, &text_digest);
+
+        gib
+    }
+}
+
 impl<FSOI> GitIndexDb<FSOI>
 where
     FSOI: FsObjectIfce + ScmFsoDataIfce + Clone,
@@ -1238,13 +1272,12 @@ where
         let mut base_dir = self.base_dir.borrow_mut();
         let hide_clean = base_dir.hide_clean;
         *base_dir = GitIndexDbDir::new(".", NO_STATUS, hide_clean);
-        for line in text.lines() {
-            if line.starts_with(' ') {
+        for entry in parse_porcelain_v2(text) {
+            if entry.status.starts_with(' ') {
                 continue; // not in the index
             }
-            let (file_path, status, related_file_data) = parse_line!(line);
-            let path_components = file_path.path_components();
-            base_dir.add_file(&path_components[1..], &status, &related_file_data)
+            let path_components = entry.path.path_components();
+            base_dir.add_file(&path_components[1..], &entry, &self.ws_path.borrow())
         }
         base_dir.finalize();
         *self.populated_digest.borrow_mut() = digest.to_vec();
@@ -0,0 +1,650 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, Ref, RefCell};
+use std::io::Write;
+use std::rc::Rc;
+
+use crypto_hash::{Algorithm, Hasher};
+
+use cub_diff_lib::diff::DiffPlusParser;
+use cub_diff_lib::lines::*;
+use cub_diff_lib_gtk::diff::DiffPlusNotebook;
+
+use pw_gix::{
+    glib,
+    glibx::*,
+    gtk::{self, prelude::*},
+    gtkx::{
+        list_store::{
+            BufferedUpdate, MapManagedUpdate, RequiredMapAction, Row, RowBuffer, RowBufferCore,
+        },
+        menu_ng::{ManagedMenu, ManagedMenuBuilder},
+        paned::RememberPosition,
+    },
+    sav_state::*,
+    wrapper::*,
+};
+
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::workspace::WsPath;
+
+use crate::events::{self, Event, Events};
+use crate::exec::ExecConsole;
+use crate::rebase::RebaseBranch;
+use crate::repos;
+use crate::tags::CreatTag;
+
+// Number of commits loaded initially and for each subsequent "More" request
+const HISTORY_CHUNK: usize = 500;
+
+fn get_raw_data(ws_path: &WsPath, max_count: usize) -> (String, Vec<u8>) {
+    let mut hasher = Hasher::new(Algorithm::SHA256);
+    let output = ws_path
+        .git()
+        .arg("log")
+        .arg("--topo-order")
+        .arg("--date=short")
+        .arg("--format=%H%x00%P%x00%h%x00%an%x00%ad%x00%s")
+        .arg(format!("--max-count={max_count}"))
+        .output()
+        .expect("getting commit history text failed");
+    let text: String = if output.status.success() {
+        hasher.write_all(&output.stdout).expect("hasher blew up!!!");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        "".to_string()
+    };
+    (text, hasher.finish())
+}
+
+// Allocate each commit to a lane and return a textual representation of the lanes
+// that are active at that commit.  Commits must be in topological order.
+#[derive(Debug, Default)]
+struct LaneGraph {
+    lanes: Vec<Option<String>>,
+}
+
+impl LaneGraph {
+    fn next_row(&mut self, commit: &str, parents: &[&str]) -> String {
+        let col = match self
+            .lanes
+            .iter()
+            .position(|x| x.as_ref().map(|s| s.as_str()) == Some(commit))
+        {
+            Some(col) => col,
+            None => self.free_lane(),
+        };
+        let mut graph = String::new();
+        for (i, lane) in self.lanes.iter().enumerate() {
+            if i == col {
+                graph.push('●');
+            } else if lane.as_ref().map(|s| s.as_str()) == Some(commit) {
+                graph.push(if i < col { '└' } else { '┘' });
+            } else if lane.is_some() {
+                graph.push('│');
+            } else {
+                graph.push(' ');
+            }
+        }
+        for lane in self.lanes.iter_mut() {
+            if lane.as_ref().map(|s| s.as_str()) == Some(commit) {
+                *lane = None;
+            }
+        }
+        if let Some((first, others)) = parents.split_first() {
+            self.lanes[col] = Some(first.to_string());
+            for parent in others {
+                if !self
+                    .lanes
+                    .iter()
+                    .any(|x| x.as_ref().map(|s| s.as_str()) == Some(parent))
+                {
+                    let free = self.free_lane();
+                    self.lanes[free] = Some(parent.to_string());
+                }
+            }
+        }
+        while let Some(None) = self.lanes.last() {
+            self.lanes.pop();
+        }
+        graph.trim_end().to_string()
+    }
+
+    fn free_lane(&mut self) -> usize {
+        if let Some(free) = self.lanes.iter().position(|x| x.is_none()) {
+            free
+        } else {
+            self.lanes.push(None);
+            self.lanes.len() - 1
+        }
+    }
+}
+
+fn extract_history_rows(text: &str) -> Vec<Row> {
+    let mut lane_graph = LaneGraph::default();
+    let mut rows = vec![];
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split('\0').collect();
+        if fields.len() != 6 {
+            continue;
+        }
+        let parents: Vec<&str> = fields[1].split_whitespace().collect();
+        let graph = lane_graph.next_row(fields[0], &parents);
+        rows.push(vec![
+            fields[0].to_value(),
+            graph.to_value(),
+            fields[2].to_value(),
+            fields[3].to_value(),
+            fields[4].to_value(),
+            fields[5].to_value(),
+        ]);
+    }
+    rows
+}
+
+struct HistoryRowBuffer {
+    row_buffer_core: Rc<RefCell<RowBufferCore<String>>>,
+    max_count: Rc<Cell<usize>>,
+    ws_path: WsPath,
+}
+
+impl HistoryRowBuffer {
+    fn new(max_count: &Rc<Cell<usize>>, ws_path: &WsPath) -> Self {
+        let core = RowBufferCore::<String>::default();
+        let buffer = Self {
+            row_buffer_core: Rc::new(RefCell::new(core)),
+            max_count: Rc::clone(max_count),
+            ws_path: ws_path.clone(),
+        };
+        buffer.init();
+        buffer
+    }
+}
+
+impl RowBuffer<String> for HistoryRowBuffer {
+    fn get_core(&self) -> Rc<RefCell<RowBufferCore<String>>> {
+        self.row_buffer_core.clone()
+    }
+
+    fn set_raw_data(&self) {
+        let (raw_data, digest) = get_raw_data(&self.ws_path, self.max_count.get());
+        let mut core = self.row_buffer_core.borrow_mut();
+        core.set_raw_data(raw_data, digest);
+    }
+
+    fn finalise(&self) {
+        let mut core = self.row_buffer_core.borrow_mut();
+        let rows = extract_history_rows(&core.raw_data);
+        core.rows = Rc::new(rows);
+        core.set_is_finalised_true();
+    }
+}
+
+struct HistoryListStore {
+    list_store: gtk::ListStore,
+    history_row_buffer: Rc<RefCell<HistoryRowBuffer>>,
+}
+
+impl BufferedUpdate<String, gtk::ListStore> for HistoryListStore {
+    fn get_list_store(&self) -> gtk::ListStore {
+        self.list_store.clone()
+    }
+
+    fn get_row_buffer(&self) -> Rc<RefCell<dyn RowBuffer<String>>> {
+        self.history_row_buffer.clone()
+    }
+}
+
+impl HistoryListStore {
+    pub fn new(max_count: &Rc<Cell<usize>>, ws_path: &WsPath) -> HistoryListStore {
+        Self {
+            list_store: gtk::ListStore::new(&[glib::Type::String; 6]),
+            history_row_buffer: Rc::new(RefCell::new(HistoryRowBuffer::new(max_count, ws_path))),
+        }
+    }
+}
+
+#[derive(PWO, Wrapper)]
+pub struct HistoryTable {
+    paned: gtk::Paned,
+    view: gtk::TreeView,
+    list_store: RefCell<HistoryListStore>,
+    required_map_action: Cell<RequiredMapAction>,
+    max_count: Rc<Cell<usize>>,
+    more_button: gtk::Button,
+    diff_notebook: Rc<DiffPlusNotebook>,
+    diff_plus_parser: DiffPlusParser,
+    exec_console: Rc<ExecConsole>,
+    popup_menu: ManagedMenu,
+    hovered_commit: RefCell<Option<String>>,
+}
+
+impl CreatTag for HistoryTable {
+    fn exec_console(&self) -> &Rc<ExecConsole> {
+        &self.exec_console
+    }
+}
+
+impl RebaseBranch for HistoryTable {
+    fn exec_console(&self) -> &Rc<ExecConsole> {
+        &self.exec_console
+    }
+}
+
+impl MapManagedUpdate<HistoryListStore, String, gtk::ListStore> for HistoryTable {
+    fn buffered_update(&self) -> Ref<'_, HistoryListStore> {
+        self.list_store.borrow()
+    }
+
+    fn is_mapped(&self) -> bool {
+        self.view.get_mapped()
+    }
+
+    fn get_required_map_action(&self) -> RequiredMapAction {
+        self.required_map_action.get()
+    }
+
+    fn set_required_map_action(&self, action: RequiredMapAction) {
+        self.required_map_action.set(action);
+    }
+}
+
+impl HistoryTable {
+    pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<HistoryTable> {
+        let max_count = Rc::new(Cell::new(HISTORY_CHUNK));
+        let list_store = RefCell::new(HistoryListStore::new(&max_count, &exec_console.ws_path));
+
+        let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
+        view.set_headers_visible(true);
+
+        view.get_selection().set_mode(gtk::SelectionMode::Single);
+
+        let col = gtk::TreeViewColumn::new();
+        col.set_title("Graph");
+        col.set_expand(false);
+        col.set_resizable(true);
+
+        let cell = gtk::CellRendererText::new();
+        cell.set_property_editable(false);
+        cell.set_property_family(Some("monospace"));
+        col.pack_start(&cell, false);
+        col.add_attribute(&cell, "text", 1);
+
+        view.append_column(&col);
+
+        for (title, index) in [("SHA", 2), ("Author", 3), ("Date", 4), ("Subject", 5)].iter() {
+            let col = gtk::TreeViewColumn::new();
+            col.set_title(title);
+            col.set_expand(*index == 5);
+            col.set_resizable(true);
+
+            let cell = gtk::CellRendererText::new();
+            cell.set_property_editable(false);
+            col.pack_start(&cell, false);
+            col.add_attribute(&cell, "text", *index);
+
+            view.append_column(&col);
+        }
+
+        view.show_all();
+
+        list_store.borrow().repopulate();
+
+        let required_map_action = Cell::new(RequiredMapAction::Nothing);
+
+        let popup_menu = ManagedMenuBuilder::new()
+            .widget_states_controlled(WidgetStatesControlled::Sensitivity)
+            .selection(&view.get_selection())
+            .change_notifier(&exec_console.changed_condns_notifier)
+            .build();
+
+        let paned = gtk::Paned::new(gtk::Orientation::Vertical);
+        let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.add(&view);
+        v_box.pack_start(&scrolled_window, true, true, 0);
+        let more_button = gtk::Button::with_label("More");
+        more_button.set_tooltip_text(Some("Load more of the commit history"));
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        h_box.pack_end(&more_button, false, false, 0);
+        v_box.pack_start(&h_box, false, false, 0);
+        paned.add1(&v_box);
+        let diff_notebook = DiffPlusNotebook::new(1);
+        let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        v_box.pack_start(diff_notebook.pwo(), true, true, 0);
+        v_box.pack_start(diff_notebook.tws_count_display().pwo(), false, false, 0);
+        paned.add2(&v_box);
+        paned.show_all();
+        paned.set_position_from_recollections("history:paned:position", 200);
+
+        let table = Rc::new(HistoryTable {
+            paned,
+            view,
+            list_store,
+            required_map_action,
+            max_count,
+            more_button,
+            diff_notebook,
+            diff_plus_parser: DiffPlusParser::new(),
+            exec_console: Rc::clone(exec_console),
+            popup_menu,
+            hovered_commit: RefCell::new(None),
+        });
+        let table_clone = Rc::clone(&table);
+        table.exec_console.event_notifier.add_notification_cb(
+            events::EV_AUTO_UPDATE
+                | events::EV_BRANCHES_CHANGE
+                | events::EV_CHECKOUT
+                | events::EV_COMMIT
+                | events::EV_PULL,
+            Box::new(move |_| table_clone.auto_update()),
+        );
+        let table_clone = Rc::clone(&table);
+        table.view.connect_map(move |_| table_clone.on_map_action());
+        let table_clone = Rc::clone(&table);
+        table.exec_console.event_notifier.add_notification_cb(
+            events::EV_CHANGE_DIR,
+            Box::new(move |_| {
+                table_clone.max_count.set(HISTORY_CHUNK);
+                table_clone.repopulate();
+                table_clone.diff_notebook.repopulate(&vec![]);
+            }),
+        );
+
+        let table_clone = Rc::clone(&table);
+        table.more_button.connect_clicked(move |_| {
+            let max_count = table_clone.max_count.get();
+            table_clone.max_count.set(max_count + HISTORY_CHUNK);
+            table_clone.repopulate();
+        });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .view
+            .get_selection()
+            .connect_changed(move |selection| {
+                if let Some((store, iter)) = selection.get_selected() {
+                    if let Some(commit) = store.get_value(&iter, 0).get::<String>().unwrap() {
+                        table_clone.show_commit_diff(&commit);
+                    }
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "checkout",
+                &(
+                    "Checkout",
+                    None,
+                    Some("Check out the selected/indicated commit as a detached HEAD"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + repos::SAV_NOT_OP_IN_PROGRESS + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(commit) = table_clone.get_chosen_commit() {
+                    let cmd = GitCommand::new("checkout").arg("--detach").arg(&commit);
+                    let result = table_clone.exec_console.exec_cmd(
+                        &cmd,
+                        Events::from(events::EV_BRANCHES_CHANGE)
+                            .with(Event::Checkout(Some(commit.clone()))),
+                    );
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "cherry-pick",
+                &(
+                    "Cherry-pick",
+                    None,
+                    Some("Apply the selected/indicated commit to the current branch"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + repos::SAV_NOT_OP_IN_PROGRESS + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(commit) = table_clone.get_chosen_commit() {
+                    let cmd = GitCommand::new("cherry-pick").arg(&commit);
+                    let result = table_clone
+                        .exec_console
+                        .exec_cmd(&cmd, events::EV_COMMIT | events::EV_FILES_CHANGE);
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "revert",
+                &(
+                    "Revert",
+                    None,
+                    Some("Create a commit reversing the selected/indicated commit"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + repos::SAV_NOT_OP_IN_PROGRESS + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(commit) = table_clone.get_chosen_commit() {
+                    let cmd = GitCommand::new("revert").arg("--no-edit").arg(&commit);
+                    let result = table_clone
+                        .exec_console
+                        .exec_cmd(&cmd, events::EV_COMMIT | events::EV_FILES_CHANGE);
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "rebase-interactive",
+                &(
+                    "Rebase From Here (Interactive)",
+                    None,
+                    Some("Edit the plan for rebasing the commits after the selected/indicated commit and then run it"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + repos::SAV_NOT_OP_IN_PROGRESS + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(commit) = table_clone.get_chosen_commit() {
+                    table_clone.interactive_rebase(&commit);
+                }
+            });
+
+        table.popup_menu.append_separator();
+        for mode in ["soft", "mixed", "hard"].iter() {
+            let table_clone = Rc::clone(&table);
+            let label = format!("Reset --{mode}");
+            let tooltip =
+                format!("Reset the current branch to the selected/indicated commit (--{mode})");
+            table
+                .popup_menu
+                .append_item(
+                    &format!("reset-{mode}"),
+                    &(label.as_str(), None, Some(tooltip.as_str())).into(),
+                    repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+                )
+                .connect_activate(move |_| {
+                    if let Some(commit) = table_clone.get_chosen_commit() {
+                        let cmd = GitCommand::new("reset")
+                            .arg(format!("--{mode}"))
+                            .arg(&commit);
+                        let msg = format!("Confirm: {cmd}");
+                        let explanation = if *mode == "hard" {
+                            Some("Uncommitted changes in the working directory will be lost!")
+                        } else {
+                            None
+                        };
+                        if table_clone.ask_confirm_action(&msg, explanation) {
+                            let result = table_clone.exec_console.exec_cmd(
+                                &cmd,
+                                events::EV_COMMIT
+                                    | events::EV_BRANCHES_CHANGE
+                                    | events::EV_FILES_CHANGE,
+                            );
+                            table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                        }
+                    }
+                });
+        }
+
+        table.popup_menu.append_separator();
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "tag",
+                &(
+                    "Tag",
+                    None,
+                    Some("Set a new tag on the selected/indicated commit"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(commit) = table_clone.get_chosen_commit() {
+                    table_clone.create_tag_for(Some(&commit))
+                }
+            });
+
+        let table_clone = Rc::clone(&table);
+        table
+            .popup_menu
+            .append_item(
+                "branch",
+                &(
+                    "Branch",
+                    None,
+                    Some("Create a new branch at the selected/indicated commit"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK,
+            )
+            .connect_activate(move |_| {
+                if let Some(commit) = table_clone.get_chosen_commit() {
+                    let (response, name) = table_clone.ask_string_cancel_or_ok("Branch Name:");
+                    if response == gtk::ResponseType::Ok {
+                        if let Some(branch) = name {
+                            let cmd = GitCommand::new("branch").arg(&branch).arg(&commit);
+                            let result = table_clone
+                                .exec_console
+                                .exec_cmd(&cmd, Event::BranchesChange(Some(branch)));
+                            table_clone.report_any_command_problems(&cmd.to_string(), &result);
+                        }
+                    }
+                }
+            });
+
+        let table_clone = table.clone();
+        table.view.connect_button_press_event(move |view, event| {
+            if event.get_button() == 3 {
+                let commit = get_row_item_for_event!(view, event, String, 0);
+                table_clone.set_hovered_commit(commit);
+                table_clone.popup_menu.popup_at_event(event);
+                return Inhibit(true);
+            } else if event.get_button() == 2 {
+                table_clone.view.get_selection().unselect_all();
+                return Inhibit(true);
+            }
+            Inhibit(false)
+        });
+
+        table
+    }
+
+    fn set_hovered_commit(&self, commit: Option<String>) {
+        let condns = self
+            .view
+            .get_selection()
+            .get_masked_conditions_with_hover_ok(commit.is_some());
+        self.popup_menu.update_condns(condns);
+        *self.hovered_commit.borrow_mut() = commit;
+    }
+
+    fn get_chosen_commit(&self) -> Option<String> {
+        let selection = self.view.get_selection();
+        if let Some((store, iter)) = selection.get_selected() {
+            store.get_value(&iter, 0).get::<String>().unwrap()
+        } else {
+            self.hovered_commit.borrow().clone()
+        }
+    }
+
+    fn show_commit_diff(&self, commit: &str) {
+        if let Some(text) = get_commit_diff_text(&self.exec_console.ws_path, commit) {
+            let lines = Lines::from_string(&text);
+            match self.diff_plus_parser.parse_lines(&lines) {
+                Ok(ref diff_pluses) => self.diff_notebook.repopulate(diff_pluses),
+                Err(err) => {
+                    self.diff_notebook.repopulate(&vec![]);
+                    let msg = format!("{commit}: Malformed diff text");
+                    self.report_error(&msg, &err);
+                }
+            }
+        } else {
+            self.diff_notebook.repopulate(&vec![]);
+        }
+    }
+}
+
+// Merges are shown against their first parent as DiffPlusParser can't parse
+// the combined ("diff --cc") form that "git show" would otherwise produce.
+fn get_commit_diff_text(ws_path: &WsPath, commit: &str) -> Option<String> {
+    let output = ws_path
+        .git()
+        .arg("show")
+        .arg("--no-ext-diff")
+        .arg("--src-prefix=a/")
+        .arg("--dst-prefix=b/")
+        .arg("-M")
+        .arg("-m")
+        .arg("--first-parent")
+        .arg("--format=")
+        .arg(commit)
+        .output()
+        .expect("\"git show <commit>\" blew up!!!");
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lane_graph_works() {
+        // d is a merge of b and c which both have parent a
+        let mut lane_graph = LaneGraph::default();
+        assert_eq!(lane_graph.next_row("d", &["b", "c"]), "●");
+        assert_eq!(lane_graph.next_row("c", &["a"]), "│●");
+        assert_eq!(lane_graph.next_row("b", &["a"]), "●│");
+        assert_eq!(lane_graph.next_row("a", &[]), "●┘");
+        assert!(lane_graph.lanes.is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+use xml::escape;
+
+use pw_gix::{
+    glib,
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use gwsm_git_core::files;
+use gwsm_git_core::workspace::WsPath;
+
+use crate::events;
+use crate::exec::ExecConsole;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Hunk {
+    old_start: usize,
+    new_start: usize,
+    trailer: String,
+    lines: Vec<String>,
+}
+
+impl Hunk {
+    fn header(&self, old_len: usize, new_len: usize) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@{}",
+            self.old_start, old_len, self.new_start, new_len, self.trailer
+        )
+    }
+
+    // The header with the line counts of the hunk as it stands
+    fn full_header(&self) -> String {
+        let (old_len, new_len) = Self::line_counts(&self.lines);
+        self.header(old_len, new_len)
+    }
+
+    fn line_counts(lines: &[String]) -> (usize, usize) {
+        let old_len = lines
+            .iter()
+            .filter(|l| l.starts_with(' ') || l.starts_with('-'))
+            .count();
+        let new_len = lines
+            .iter()
+            .filter(|l| l.starts_with(' ') || l.starts_with('+'))
+            .count();
+        (old_len, new_len)
+    }
+
+    fn is_change(line: &str) -> bool {
+        line.starts_with('+') || line.starts_with('-')
+    }
+
+    // Generate the text for this hunk with only the chosen changes included.
+    // If "reverse" is true the patch is destined to be applied with "-R".
+    fn partial_text(&self, chosen: &[bool], reverse: bool) -> Option<String> {
+        let (keep, drop) = if reverse { ('+', '-') } else { ('-', '+') };
+        let mut lines: Vec<String> = vec![];
+        let mut any_chosen = false;
+        let mut dropped_last = false;
+        for (index, line) in self.lines.iter().enumerate() {
+            if line.starts_with('\\') {
+                if !dropped_last {
+                    lines.push(line.to_string());
+                }
+                continue;
+            }
+            dropped_last = false;
+            if Self::is_change(line) && !chosen.get(index).cloned().unwrap_or(false) {
+                if line.starts_with(drop) {
+                    dropped_last = true;
+                } else if line.starts_with(keep) {
+                    lines.push(format!(" {}", &line[1..]));
+                }
+            } else {
+                any_chosen |= Self::is_change(line);
+                lines.push(line.to_string());
+            }
+        }
+        if any_chosen {
+            let (old_len, new_len) = Self::line_counts(&lines);
+            let mut text = self.header(old_len, new_len);
+            text.push('\n');
+            for line in lines {
+                text.push_str(&line);
+                text.push('\n');
+            }
+            Some(text)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FilePatch {
+    path: String,
+    header_lines: Vec<String>,
+    hunks: Vec<Hunk>,
+}
+
+fn parse_range(text: &str) -> usize {
+    text[1..]
+        .split(',')
+        .next()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+// Split the output of "git diff" into per file patches and their hunks.
+pub fn parse_diff_text(text: &str) -> Vec<FilePatch> {
+    let mut patches: Vec<FilePatch> = vec![];
+    for line in text.lines() {
+        if line.starts_with("diff --git ") {
+            let path = line
+                .rsplit(" b/")
+                .next()
+                .unwrap_or("")
+                .trim_matches('"')
+                .to_string();
+            patches.push(FilePatch {
+                path,
+                header_lines: vec![line.to_string()],
+                hunks: vec![],
+            });
+        } else if let Some(patch) = patches.last_mut() {
+            if line.starts_with("@@ ") {
+                let fields: Vec<&str> = line.splitn(4, ' ').collect();
+                if fields.len() < 3 {
+                    continue;
+                }
+                let trailer = line[3..].splitn(2, "@@").nth(1).unwrap_or("").to_string();
+                patch.hunks.push(Hunk {
+                    old_start: parse_range(fields[1]),
+                    new_start: parse_range(fields[2]),
+                    trailer,
+                    lines: vec![],
+                });
+            } else if let Some(hunk) = patch.hunks.last_mut() {
+                hunk.lines.push(line.to_string());
+            } else {
+                patch.header_lines.push(line.to_string());
+            }
+        }
+    }
+    patches
+}
+
+// "chosen" holds a line selection vector for every hunk of every file patch
+pub fn build_partial_patch(
+    patches: &[FilePatch],
+    chosen: &[Vec<Vec<bool>>],
+    reverse: bool,
+) -> String {
+    let mut text = String::new();
+    for (patch, patch_chosen) in patches.iter().zip(chosen.iter()) {
+        let hunks: Vec<String> = patch
+            .hunks
+            .iter()
+            .zip(patch_chosen.iter())
+            .filter_map(|(hunk, hunk_chosen)| hunk.partial_text(hunk_chosen, reverse))
+            .collect();
+        if !hunks.is_empty() {
+            for line in patch.header_lines.iter() {
+                text.push_str(line);
+                text.push('\n');
+            }
+            for hunk in hunks {
+                text.push_str(&hunk);
+            }
+        }
+    }
+    text
+}
+
+const CHOSEN: u32 = 0;
+const MARKUP: u32 = 1;
+const IS_CHANGE: u32 = 2;
+
+// Shown in place of the diff notebook while the user chooses what to (un)stage
+#[derive(PWO, Wrapper)]
+pub struct HunkSelectionWidget {
+    scrolled_window: gtk::ScrolledWindow,
+    view: gtk::TreeView,
+    tree_store: gtk::TreeStore,
+    diff_text: RefCell<String>,
+    patches: RefCell<Vec<FilePatch>>,
+}
+
+impl HunkSelectionWidget {
+    pub fn new() -> Rc<Self> {
+        let tree_store =
+            gtk::TreeStore::new(&[glib::Type::Bool, glib::Type::String, glib::Type::Bool]);
+        let view = gtk::TreeView::with_model(&tree_store);
+        view.set_headers_visible(false);
+        let adj: Option<&gtk::Adjustment> = None;
+        let hsw = Rc::new(Self {
+            scrolled_window: gtk::ScrolledWindow::new(adj, adj),
+            view,
+            tree_store,
+            diff_text: RefCell::new(String::new()),
+            patches: RefCell::new(vec![]),
+        });
+
+        let col = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererToggle::new();
+        col.pack_start(&cell, false);
+        col.add_attribute(&cell, "active", CHOSEN as i32);
+        col.add_attribute(&cell, "visible", IS_CHANGE as i32);
+        let hsw_clone = Rc::clone(&hsw);
+        cell.connect_toggled(move |_, tree_path| hsw_clone.toggle(&tree_path));
+        let cell = gtk::CellRendererText::new();
+        cell.set_property_editable(false);
+        cell.set_property_family(Some("monospace"));
+        col.pack_start(&cell, true);
+        col.add_attribute(&cell, "markup", MARKUP as i32);
+        hsw.view.append_column(&col);
+
+        hsw.scrolled_window.add(&hsw.view);
+        hsw.scrolled_window.show_all();
+
+        hsw
+    }
+
+    // The user's choices are only discarded if the diff has actually changed
+    pub fn repopulate(&self, diff_text: &str) {
+        if *self.diff_text.borrow() == diff_text {
+            return;
+        }
+        *self.diff_text.borrow_mut() = diff_text.to_string();
+        *self.patches.borrow_mut() = parse_diff_text(diff_text);
+        self.tree_store.clear();
+        for patch in self.patches.borrow().iter() {
+            let markup = format!("<b>{}</b>", escape::escape_str_pcdata(&patch.path));
+            let file_iter = self.tree_store.append(None);
+            self.set_row(&file_iter, false, &markup, true);
+            for hunk in patch.hunks.iter() {
+                let hunk_iter = self.tree_store.append(Some(&file_iter));
+                let header = hunk.full_header();
+                let markup = format!(
+                    "<span foreground=\"blue\">{}</span>",
+                    escape::escape_str_pcdata(&header)
+                );
+                self.set_row(&hunk_iter, false, &markup, true);
+                for line in hunk.lines.iter() {
+                    let line_iter = self.tree_store.append(Some(&hunk_iter));
+                    let colour = if line.starts_with('+') {
+                        "darkgreen"
+                    } else if line.starts_with('-') {
+                        "red"
+                    } else {
+                        "black"
+                    };
+                    let markup = format!(
+                        "<span foreground=\"{}\">{}</span>",
+                        colour,
+                        escape::escape_str_pcdata(line)
+                    );
+                    self.set_row(&line_iter, false, &markup, Hunk::is_change(line));
+                }
+            }
+        }
+        self.view.expand_all();
+    }
+
+    fn set_row(&self, iter: &gtk::TreeIter, chosen: bool, markup: &str, is_change: bool) {
+        self.tree_store.set_value(iter, CHOSEN, &chosen.to_value());
+        self.tree_store.set_value(iter, MARKUP, &markup.to_value());
+        self.tree_store
+            .set_value(iter, IS_CHANGE, &is_change.to_value());
+    }
+
+    fn is_chosen(&self, iter: &gtk::TreeIter) -> bool {
+        self.tree_store
+            .get_value(iter, CHOSEN as i32)
+            .get::<bool>()
+            .unwrap()
+            .unwrap_or(false)
+    }
+
+    fn set_chosen_recursive(&self, iter: &gtk::TreeIter, chosen: bool) {
+        self.tree_store.set_value(iter, CHOSEN, &chosen.to_value());
+        if let Some(child) = self.tree_store.iter_children(Some(iter)) {
+            loop {
+                self.set_chosen_recursive(&child, chosen);
+                if !self.tree_store.iter_next(&child) {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn toggle(&self, tree_path: &gtk::TreePath) {
+        if let Some(iter) = self.tree_store.get_iter(tree_path) {
+            let chosen = !self.is_chosen(&iter);
+            self.set_chosen_recursive(&iter, chosen);
+        }
+    }
+
+    fn children(&self, iter: &gtk::TreeIter) -> Vec<gtk::TreeIter> {
+        let mut children = vec![];
+        if let Some(child) = self.tree_store.iter_children(Some(iter)) {
+            loop {
+                children.push(child.clone());
+                if !self.tree_store.iter_next(&child) {
+                    break;
+                }
+            }
+        }
+        children
+    }
+
+    pub fn partial_patch(&self, reverse: bool) -> String {
+        let mut chosen = vec![];
+        let mut file_iters = vec![];
+        if let Some(iter) = self.tree_store.get_iter_first() {
+            loop {
+                file_iters.push(iter.clone());
+                if !self.tree_store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        for file_iter in file_iters.iter() {
+            let mut file_chosen = vec![];
+            for hunk_iter in self.children(file_iter) {
+                let lines_chosen: Vec<bool> = self
+                    .children(&hunk_iter)
+                    .iter()
+                    .map(|line_iter| self.is_chosen(line_iter))
+                    .collect();
+                file_chosen.push(lines_chosen);
+            }
+            chosen.push(file_chosen);
+        }
+        build_partial_patch(&self.patches.borrow(), &chosen, reverse)
+    }
+}
+
+// A private file (removed when dropped) so that concurrent instances don't collide
+fn write_partial_patch(patch: &str) -> std::io::Result<tempfile::NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("partial")
+        .suffix(".patch")
+        .tempfile()?;
+    file.write_all(patch.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
+
+// The prefixes are forced (and external diff tools bypassed) so that the user's
+// diff configuration can't produce a patch that "git apply" won't accept.
+fn get_diff_text(ws_path: &WsPath, staged: bool) -> String {
+    let mut cmd = ws_path.git();
+    cmd.arg("diff")
+        .arg("--no-ext-diff")
+        .arg("--no-color")
+        .arg("--src-prefix=a/")
+        .arg("--dst-prefix=b/");
+    if staged {
+        cmd.arg("--staged");
+    }
+    let output = cmd.output().expect("\"git diff\" blew up");
+    if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        "".to_string()
+    }
+}
+
+// Implemented by the diff views so that hunks/lines from "git diff" (or from
+// "git diff --staged" when unstaging) can be chosen in place and applied (or
+// reverse applied) to the index.
+pub trait StageHunks: WidgetWrapper {
+    fn exec_console(&self) -> &Rc<ExecConsole>;
+
+    fn hunk_selection(&self) -> &Rc<HunkSelectionWidget>;
+
+    fn update_hunk_selection(&self, unstage: bool) {
+        let diff_text = get_diff_text(&self.exec_console().ws_path, unstage);
+        self.hunk_selection().repopulate(&diff_text);
+    }
+
+    fn apply_chosen_hunks(&self, unstage: bool) {
+        let patch = self.hunk_selection().partial_patch(unstage);
+        if patch.is_empty() {
+            self.inform_user("No changes were chosen.", None);
+            return;
+        }
+        match write_partial_patch(&patch) {
+            Ok(file) => {
+                let cmd = files::apply_to_index_cmd(file.path(), unstage);
+                let cursor = self.show_busy();
+                let result = self.exec_console().exec_cmd(&cmd, events::EV_FILES_CHANGE);
+                self.unshow_busy(cursor);
+                self.report_any_command_problems(&cmd.to_string(), &result);
+            }
+            Err(err) => self.report_error("Failed to write partial patch", &err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/f.txt b/f.txt
+index 1111111..2222222 100644
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,4 @@ fn main
+ one
+-two
++TWO
+ three
+-four
++FOUR
+";
+
+    #[test]
+    fn parse_diff_text_works() {
+        let patches = parse_diff_text(DIFF);
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path, "f.txt");
+        assert_eq!(patches[0].header_lines.len(), 4);
+        assert_eq!(patches[0].hunks.len(), 1);
+        assert_eq!(patches[0].hunks[0].old_start, 1);
+        assert_eq!(patches[0].hunks[0].trailer, " fn main");
+        assert_eq!(patches[0].hunks[0].lines.len(), 6);
+        assert_eq!(patches[0].hunks[0].full_header(), "@@ -1,4 +1,4 @@ fn main");
+    }
+
+    #[test]
+    fn build_partial_patch_works() {
+        let patches = parse_diff_text(DIFF);
+        let chosen = vec![vec![vec![false, true, true, false, false, false]]];
+        let expected = "diff --git a/f.txt b/f.txt
+index 1111111..2222222 100644
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,4 @@ fn main
+ one
+-two
++TWO
+ three
+ four
+";
+        assert_eq!(build_partial_patch(&patches, &chosen, false), expected);
+        let expected = "diff --git a/f.txt b/f.txt
+index 1111111..2222222 100644
+--- a/f.txt
++++ b/f.txt
+@@ -1,4 +1,4 @@ fn main
+ one
+-two
++TWO
+ three
+ FOUR
+";
+        assert_eq!(build_partial_patch(&patches, &chosen, true), expected);
+        let chosen = vec![vec![vec![false; 6]]];
+        assert_eq!(build_partial_patch(&patches, &chosen, false), "");
+    }
+}
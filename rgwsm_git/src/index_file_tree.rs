@@ -26,9 +26,11 @@ use pw_gix::{
     wrapper::*,
 };
 
-use crate::events;
+use gwsm_git_core::files;
+
+use crate::events::{self, Event};
 use crate::exec;
-use crate::fs_db::{self, GitIndexDb, ScmFsoData};
+use crate::fs_db::{self, GitIndexDb, ScmFsoData, WsFsDbIfce};
 use crate::repos;
 
 #[derive(PWO, Wrapper)]
@@ -82,7 +84,7 @@ where
 
 impl<FSDB, FSOI> GenIndexFsTree<FSDB, FSOI>
 where
-    FSDB: FsDbIfce<FSOI> + 'static,
+    FSDB: FsDbIfce<FSOI> + WsFsDbIfce + 'static,
     FSOI: FsObjectIfce + 'static,
 {
     pub fn new(exec_console: &Rc<exec::ExecConsole>) -> Rc<Self> {
@@ -119,7 +121,7 @@ where
             v_box,
             view,
             store,
-            fs_db: FSDB::new(),
+            fs_db: FSDB::with_ws_path(&exec_console.ws_path),
             hide_clean,
             exec_console: Rc::clone(exec_console),
             popup_menu,
@@ -166,20 +168,20 @@ where
                 repos::SAV_IN_REPO + SAV_SELN_MADE_OR_HOVER_OK,
             )
             .connect_activate(move |_| {
-                if let Some(fso_paths) = ift_clone.get_chosen_file_paths_string() {
-                    let cmd = format!("git reset HEAD -- {fso_paths}");
+                let fso_paths = ift_clone.get_chosen_file_paths();
+                if !fso_paths.is_empty() {
+                    let cmd = files::unstage_cmd(&fso_paths);
                     let result = ift_clone
                         .exec_console
-                        .exec_cmd(&cmd, events::EV_FILES_CHANGE);
-                    ift_clone.report_any_command_problems(&cmd, &result);
+                        .exec_cmd(&cmd, Event::FilesChange(fso_paths.clone()));
+                    ift_clone.report_any_command_problems(&cmd.to_string(), &result);
                 }
             });
 
         let ift_clone = ift.clone();
         ift.view.connect_button_press_event(move |view, event| {
             if event.get_button() == 3 {
-                let fso_path = get_row_item_for_event!(view, event, String, fs_db::PATH)
-                    .map(|fso_path| shlex::quote(&fso_path).to_string());
+                let fso_path = get_row_item_for_event!(view, event, String, fs_db::PATH);
                 ift_clone.set_hovered_fso_path(fso_path);
                 ift_clone.popup_menu.popup_at_event(event);
                 return Inhibit(true);
@@ -207,32 +209,23 @@ where
         *self.hovered_fso_path.borrow_mut() = path;
     }
 
-    fn get_chosen_file_paths_string(&self) -> Option<String> {
+    fn get_chosen_file_paths(&self) -> Vec<String> {
         let selection = self.view.get_selection();
         let (tree_paths, store) = selection.get_selected_rows();
         if !tree_paths.is_empty() {
-            let mut count = 0;
-            let mut fso_paths = String::new();
+            let mut fso_paths = vec![];
             for tree_path in tree_paths.iter() {
                 if let Some(iter) = store.get_iter(tree_path) {
                     if let Some(fso_path) =
                         store.get_value(&iter, fs_db::PATH).get::<String>().unwrap()
                     {
-                        if count > 0 {
-                            fso_paths.push(' ');
-                        }
-                        count += 1;
-                        fso_paths.push_str(&shlex::quote(&fso_path));
+                        fso_paths.push(fso_path);
                     }
                 }
             }
-            if count > 0 {
-                Some(fso_paths)
-            } else {
-                None
-            }
+            fso_paths
         } else {
-            self.hovered_fso_path.borrow().clone()
+            self.hovered_fso_path.borrow().iter().cloned().collect()
         }
     }
 }
@@ -0,0 +1,44 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use pw_gix::gtk::{self, prelude::*};
+
+// Find the first row whose string in the given column is the given value
+pub fn find_row(store: &gtk::ListStore, column: i32, value: &str) -> Option<gtk::TreeIter> {
+    let iter = store.get_iter_first()?;
+    loop {
+        if store
+            .get_value(&iter, column)
+            .get::<String>()
+            .unwrap()
+            .as_deref()
+            == Some(value)
+        {
+            return Some(iter);
+        }
+        if !store.iter_next(&iter) {
+            return None;
+        }
+    }
+}
+
+// Select the row and scroll it into view. The iter must belong to the
+// view's model (which may be a sort model wrapped around the list store).
+pub fn select_and_show(view: &gtk::TreeView, iter: &gtk::TreeIter) {
+    view.get_selection().select_iter(iter);
+    if let Some(path) = view.get_model().and_then(|model| model.get_path(iter)) {
+        let col: Option<&gtk::TreeViewColumn> = None;
+        view.scroll_to_cell(Some(&path), col, false, 0.0, 0.0);
+    }
+}
@@ -18,9 +18,17 @@ extern crate lazy_static;
 #[macro_use]
 extern crate pw_gix;
 
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
 use pw_gix::{
     gdkx::format_geometry,
-    gio::{self, prelude::ApplicationExtManual, ApplicationExt},
+    gio::{
+        self, prelude::ApplicationExtManual, ApplicationCommandLineExt, ApplicationExt, FileExt,
+    },
     gtk::{self, prelude::MenuShellExt, prelude::*},
     gtkx::paned::RememberPosition,
     recollections,
@@ -29,27 +37,52 @@ use pw_gix::{
 
 mod action_icons;
 mod branches;
+mod cli;
 mod commit;
 mod config;
+mod conflicts;
 mod diff;
 mod edit;
 mod events;
 mod exec;
 mod fs_db;
+mod history;
+mod hunks;
 mod icon;
 mod index_file_tree;
+mod list_view;
+mod merge;
 mod message;
+mod rebase;
 mod remotes;
 mod repos;
 mod stashes;
+mod status_bar;
 mod submodules;
 mod tags;
+mod terminal;
+mod txn_log;
+mod watcher;
 mod ws_file_tree;
 
-fn activate(app: &gtk::Application) {
+// The parts of a workspace window that need to be accessible after it's built
+struct Workspace {
+    window: gtk::ApplicationWindow,
+    exec: Rc<exec::ExecConsole>,
+    commit_button: Rc<commit::CommitButton>,
+    diff_button: Rc<diff::DiffButton>,
+    _repo_watcher: Rc<watcher::RepoWatcher>,
+}
+
+// All of the open workspace windows (most recently opened last)
+type Workspaces = Rc<RefCell<Vec<Workspace>>>;
+
+fn build_workspace(
+    app: &gtk::Application,
+    workspaces: &Workspaces,
+    workspace_dir: Option<&str>,
+) -> Workspace {
     let window = gtk::ApplicationWindow::new(app);
-    // TODO: mechansim to change title when directory changes
-    window.set_title(&config::window_title(None));
     let app_icon = icon::rgwsmgit_pixbuf();
     window.set_icon(Some(&app_icon));
     if let Some(geometry) = recollections::recall("main_window:geometry") {
@@ -62,16 +95,32 @@ fn activate(app: &gtk::Application) {
         false
     });
     let exec = exec::ExecConsole::new();
+    window.set_title(&config::window_title(&exec.ws_path, None));
     let w = window.clone();
+    let ws_path = exec.ws_path.clone();
     exec.event_notifier.add_notification_cb(
         events::EV_CHANGE_DIR,
-        Box::new(move |_| w.set_title(&config::window_title(None))),
+        Box::new(move |_| w.set_title(&config::window_title(&ws_path, None))),
     );
-    if !exec.in_repo() {
+    let weak_workspaces = Rc::downgrade(workspaces);
+    let exec_clone = Rc::clone(&exec);
+    window.connect_destroy(move |window| {
+        // the callbacks refer to the window's widgets so they'd never be freed
+        exec_clone.event_notifier.del_all_notification_cbs();
+        if let Some(workspaces) = weak_workspaces.upgrade() {
+            workspaces
+                .borrow_mut()
+                .retain(|workspace| workspace.window != *window);
+        }
+    });
+    if let Some(workspace_dir) = workspace_dir {
+        exec.chdir(workspace_dir);
+    } else if !exec.in_repo() {
         if let Some(last_ws_dir) = recollections::recall("last:git:ws:dir") {
             exec.chdir(&last_ws_dir);
         }
     }
+    let repo_watcher = watcher::RepoWatcher::new(&exec);
     let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
 
     let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
@@ -81,7 +130,14 @@ fn activate(app: &gtk::Application) {
     menu_bar.show();
     hbox.pack_start(&menu_bar, true, true, 0);
     menu_bar.add(&exec::create_files_menu(&exec));
-    menu_bar.add(&repos::create_workspaces_menu(&exec));
+    let app_clone = app.clone();
+    let weak_workspaces = Rc::downgrade(workspaces);
+    let open_in_new_window = Rc::new(move |dir_path: &str| {
+        if let Some(workspaces) = weak_workspaces.upgrade() {
+            open_workspace(&app_clone, &workspaces, Some(dir_path));
+        }
+    });
+    menu_bar.add(&repos::create_workspaces_menu(&exec, open_in_new_window));
     menu_bar.add(&exec::create_friends_menu(&exec));
 
     let config_menu = gtk::Menu::new();
@@ -156,8 +212,13 @@ fn activate(app: &gtk::Application) {
     paned_h_2.add1(&paned_h_1);
     let notebook = gtk::Notebook::new();
     let branches_table = branches::BranchesNameTable::new(&exec);
-    notebook.add(branches_table.pwo());
-    notebook.set_tab_label_text(branches_table.pwo(), "Branches");
+    let remote_branches_table = branches::RemoteBranchesTable::new(&exec);
+    let branches_paned = gtk::Paned::new(gtk::Orientation::Vertical);
+    branches_paned.add1(branches_table.pwo());
+    branches_paned.add2(remote_branches_table.pwo());
+    branches_paned.set_position_from_recollections("branches_paned:position", 200);
+    notebook.add(&branches_paned);
+    notebook.set_tab_label_text(&branches_paned, "Branches");
     let stashes_table = stashes::StashesNameTable::new(&exec);
     notebook.add(stashes_table.pwo());
     notebook.set_tab_label_text(stashes_table.pwo(), "Stashes");
@@ -167,7 +228,12 @@ fn activate(app: &gtk::Application) {
     let tags_table = tags::TagsNameTable::new(&exec);
     notebook.add(tags_table.pwo());
     notebook.set_tab_label_text(tags_table.pwo(), "Tags");
-    notebook.add(&gtk::Label::new(Some("History will go here!!")));
+    let history_table = history::HistoryTable::new(&exec);
+    notebook.add(history_table.pwo());
+    notebook.set_tab_label_text(history_table.pwo(), "History");
+    let conflicts_panel = conflicts::ConflictsPanel::new(&exec);
+    notebook.add(conflicts_panel.pwo());
+    notebook.set_tab_label_text(conflicts_panel.pwo(), "Conflicts");
     notebook.set_property_enable_popup(true);
     paned_h_2.add2(&notebook);
     paned_h_2.show_all();
@@ -179,20 +245,167 @@ fn activate(app: &gtk::Application) {
     let notebook = gtk::Notebook::new();
     notebook.add(exec.pwo());
     notebook.set_tab_label_text(exec.pwo(), "Transaction Log");
-    notebook.add(&gtk::Label::new(Some("Vte terminal will go here!!")));
+    let git_terminal = terminal::GitTerminal::new(&exec);
+    notebook.add(git_terminal.pwo());
+    notebook.set_tab_label_text(git_terminal.pwo(), "Terminal");
     notebook.set_property_enable_popup(true);
     paned_v.add2(&notebook);
+    let status_bar = status_bar::GitStatusBar::new(&exec, &ws_file_tree);
+    vbox.pack_start(status_bar.pwo(), false, false, 0);
     window.add(&vbox);
     window.show_all();
+    Workspace {
+        window,
+        exec,
+        commit_button,
+        diff_button,
+        _repo_watcher: repo_watcher,
+    }
+}
+
+fn find_workspace(workspaces: &[Workspace], dir_path: &str) -> Option<usize> {
+    let dir_path =
+        repos::get_repo_workdir_for_path(dir_path).unwrap_or_else(|| dir_path.to_string());
+    let abs_path = fs::canonicalize(&dir_path).ok()?;
+    workspaces
+        .iter()
+        .position(|workspace| workspace.exec.ws_path.path() == abs_path)
+}
+
+// Present the window for the given directory (building it if there isn't one
+// already).  With no directory the most recent window is presented.
+fn open_workspace(
+    app: &gtk::Application,
+    workspaces: &Workspaces,
+    workspace_dir: Option<&str>,
+) -> usize {
+    let index = {
+        let workspaces = workspaces.borrow();
+        match workspace_dir {
+            Some(dir_path) => find_workspace(&workspaces, dir_path),
+            None if !workspaces.is_empty() => Some(workspaces.len() - 1),
+            None => None,
+        }
+    };
+    let index = match index {
+        Some(index) => index,
+        None => {
+            let workspace = build_workspace(app, workspaces, workspace_dir);
+            let mut workspaces = workspaces.borrow_mut();
+            workspaces.push(workspace);
+            workspaces.len() - 1
+        }
+    };
+    workspaces.borrow()[index].window.present();
+    index
+}
+
+fn handle_cli_options(
+    app: &gtk::Application,
+    workspaces: &Workspaces,
+    cli_options: &cli::CliOptions,
+) -> usize {
+    let index = open_workspace(app, workspaces, cli_options.workspace_dir.as_deref());
+    let workspaces = workspaces.borrow();
+    let workspace = &workspaces[index];
+    match cli_options.open_window.as_deref() {
+        Some("commit") => workspace.commit_button.present_window(),
+        Some("diff") => workspace.diff_button.present_window(),
+        _ => (),
+    }
+    index
+}
+
+// A remote launch is handled by the primary instance whose configuration is
+// already in use so a different "--config-dir" can't be honoured.
+fn unusable_config_dir(options: &cli::CliOptions, cwd: Option<&Path>) -> Option<String> {
+    let dir = options.config_dir.as_deref()?;
+    let mut requested = config::expand_config_dir_path(dir);
+    if let (true, Some(cwd)) = (requested.is_relative(), cwd) {
+        requested = cwd.join(requested);
+    }
+    let in_use = config::get_config_dir_path();
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if canonical(&requested) == canonical(&in_use) {
+        None
+    } else {
+        Some(dir.to_string())
+    }
 }
 
 fn main() {
-    recollections::init(config::get_config_dir_path().join("recollections"));
-    edit::init_editor_assignment_table();
-    repos::init_known_repos_table();
-    let flags = gio::ApplicationFlags::empty();
-    let app = gtk::Application::new(None, flags)
+    let cli_options = cli::get_cli_options();
+    if let Some(ref config_dir) = cli_options.config_dir {
+        config::set_config_dir_path_override(config_dir);
+    }
+    let flags = gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::HANDLES_COMMAND_LINE;
+    let app = gtk::Application::new(Some(config::APP_ID), flags)
         .unwrap_or_else(|err| panic!("{:?}: line {:?}: {:?}", file!(), line!(), err));
-    app.connect_activate(activate);
-    app.run(&[]);
+    let workspaces: Workspaces = Rc::new(RefCell::new(vec![]));
+
+    // Only the primary instance uses the configuration
+    app.connect_startup(|_| {
+        recollections::init(config::get_config_dir_path().join("recollections"));
+        edit::init_editor_assignment_table();
+        repos::init_known_repos_table();
+    });
+
+    // Invoked (in the primary instance) for every launch including remote ones
+    let workspaces_clone = Rc::clone(&workspaces);
+    app.connect_command_line(move |app, command_line| {
+        let args: Vec<String> = command_line
+            .get_arguments()
+            .iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let mut options = cli::parse_cli_options(&args).unwrap_or_default();
+        // relative paths are relative to the invoking process's directory
+        if let (Some(dir), Some(cwd)) = (&options.workspace_dir, command_line.get_cwd()) {
+            if Path::new(dir).is_relative() {
+                options.workspace_dir = Some(cwd.join(dir).to_string_lossy().to_string());
+            }
+        }
+        let index = handle_cli_options(app, &workspaces_clone, &options);
+        if command_line.get_is_remote() {
+            let cwd = command_line.get_cwd();
+            if let Some(dir) = unusable_config_dir(&options, cwd.as_deref()) {
+                let msg = format!("\"--config-dir {}\" ignored.", dir);
+                let explanation = format!(
+                    "{} is already running using \"{}\" for its configuration.",
+                    config::APP_NAME,
+                    config::get_config_dir_path().to_string_lossy()
+                );
+                workspaces_clone.borrow()[index]
+                    .exec
+                    .inform_user(&msg, Some(&explanation));
+            }
+        }
+        0
+    });
+
+    // e.g. "Open With" from a file manager
+    let workspaces_clone = Rc::clone(&workspaces);
+    app.connect_open(move |app, files, _hint| {
+        let workspace_dir = files.first().and_then(|file| file.get_path()).map(|path| {
+            if path.is_dir() {
+                path
+            } else {
+                path.parent().map(|dir| dir.to_path_buf()).unwrap_or(path)
+            }
+        });
+        let options = cli::CliOptions {
+            workspace_dir: workspace_dir.map(|path| path.to_string_lossy().to_string()),
+            ..cli::CliOptions::default()
+        };
+        handle_cli_options(app, &workspaces_clone, &options);
+    });
+
+    // e.g. D-Bus activation
+    let workspaces_clone = Rc::clone(&workspaces);
+    app.connect_activate(move |app| {
+        handle_cli_options(app, &workspaces_clone, &cli::CliOptions::default());
+    });
+
+    let args: Vec<String> = env::args().collect();
+    app.run(&args);
 }
@@ -0,0 +1,218 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    gtkx::dialog::RememberDialogSize,
+    wrapper::*,
+};
+
+use gwsm_git_core::merge::{FastForward, MergeOptions};
+use gwsm_git_core::workspace::WsPath;
+
+use crate::events;
+use crate::exec::ExecConsole;
+use crate::message;
+
+const STRATEGIES: &[&str] = &[
+    "",
+    "ort",
+    "recursive",
+    "resolve",
+    "octopus",
+    "ours",
+    "subtree",
+];
+const STRATEGY_OPTIONS: &[&str] = &["", "ours", "theirs", "patience", "ignore-space-change"];
+
+// The (one line) descriptions of the commits that merging "branch" would bring in
+fn commits_to_merge_text(ws_path: &WsPath, branch: &str) -> String {
+    let output = ws_path
+        .git()
+        .arg("log")
+        .arg("--oneline")
+        .arg(format!("HEAD..{branch}"))
+        .output()
+        .expect("\"git log --oneline HEAD..<branch>\" blew up!!!");
+    if !output.status.success() {
+        String::from_utf8_lossy(&output.stderr).to_string()
+    } else if output.stdout.is_empty() {
+        "Already up to date: there are no commits to merge.".to_string()
+    } else {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+}
+
+fn non_empty_text(combo: &gtk::ComboBoxText) -> Option<String> {
+    match combo.get_active_text() {
+        Some(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+        _ => None,
+    }
+}
+
+#[derive(PWO, Wrapper)]
+pub struct MergeWidget {
+    v_box: gtk::Box,
+    default_ff_rbtn: gtk::RadioButton,
+    no_ff_rbtn: gtk::RadioButton,
+    ff_only_rbtn: gtk::RadioButton,
+    squash_cbtn: gtk::CheckButton,
+    no_commit_cbtn: gtk::CheckButton,
+    strategy_combo: gtk::ComboBoxText,
+    strategy_option_combo: gtk::ComboBoxText,
+    message: Rc<message::MessageWidget>,
+}
+
+impl MergeWidget {
+    pub fn new(branch: &str, ws_path: &WsPath) -> Rc<MergeWidget> {
+        let default_ff_rbtn = gtk::RadioButton::with_label("fast forward if possible");
+        let no_ff_rbtn = gtk::RadioButton::with_label_from_widget(&default_ff_rbtn, "--no-ff");
+        let ff_only_rbtn = gtk::RadioButton::with_label_from_widget(&default_ff_rbtn, "--ff-only");
+        let mw = Rc::new(MergeWidget {
+            v_box: gtk::Box::new(gtk::Orientation::Vertical, 0),
+            default_ff_rbtn,
+            no_ff_rbtn,
+            ff_only_rbtn,
+            squash_cbtn: gtk::CheckButton::with_label("--squash"),
+            no_commit_cbtn: gtk::CheckButton::with_label("--no-commit"),
+            strategy_combo: gtk::ComboBoxText::new(),
+            strategy_option_combo: gtk::ComboBoxText::with_entry(),
+            message: message::MessageWidget::new("Message", ws_path),
+        });
+
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 20);
+        h_box.pack_start(&mw.default_ff_rbtn, false, false, 0);
+        h_box.pack_start(&mw.no_ff_rbtn, false, false, 0);
+        h_box.pack_start(&mw.ff_only_rbtn, false, false, 0);
+        h_box.pack_start(&mw.squash_cbtn, false, false, 0);
+        h_box.pack_start(&mw.no_commit_cbtn, false, false, 0);
+        mw.v_box.pack_start(&h_box, false, false, 0);
+
+        for strategy in STRATEGIES.iter() {
+            mw.strategy_combo.append_text(strategy);
+        }
+        mw.strategy_combo.set_active(Some(0));
+        for strategy_option in STRATEGY_OPTIONS.iter() {
+            mw.strategy_option_combo.append_text(strategy_option);
+        }
+        mw.strategy_option_combo.set_active(Some(0));
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        h_box.pack_start(&gtk::Label::new(Some("Strategy:")), false, false, 0);
+        h_box.pack_start(&mw.strategy_combo, false, false, 0);
+        h_box.pack_start(&gtk::Label::new(Some("  Option (-X):")), false, false, 0);
+        h_box.pack_start(&mw.strategy_option_combo, false, false, 0);
+        mw.v_box.pack_start(&h_box, false, false, 0);
+
+        let text_view = gtk::TextView::new();
+        text_view.set_editable(false);
+        text_view.set_cursor_visible(false);
+        text_view.set_monospace(true);
+        if let Some(buffer) = text_view.get_buffer() {
+            buffer.set_text(&commits_to_merge_text(ws_path, branch));
+        }
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.set_size_request(-1, 100);
+        scrolled_window.add(&text_view);
+        let label = format!("Commits to be merged (HEAD..{branch}):");
+        mw.v_box
+            .pack_start(&gtk::Label::new(Some(&label)), false, false, 0);
+        mw.v_box.pack_start(&scrolled_window, true, true, 0);
+
+        mw.message.set_message(&format!("Merge branch '{branch}'"));
+        mw.v_box.pack_start(mw.message.pwo(), true, true, 0);
+
+        // --squash and --no-ff are mutually exclusive
+        let mw_clone = Rc::clone(&mw);
+        mw.squash_cbtn.connect_property_active_notify(move |cb| {
+            if cb.get_active() && mw_clone.no_ff_rbtn.get_active() {
+                mw_clone.default_ff_rbtn.set_active(true);
+            }
+            mw_clone.no_ff_rbtn.set_sensitive(!cb.get_active());
+            mw_clone.update_message_sensitivity();
+        });
+        let mw_clone = Rc::clone(&mw);
+        mw.no_commit_cbtn
+            .connect_property_active_notify(move |_| mw_clone.update_message_sensitivity());
+        let mw_clone = Rc::clone(&mw);
+        mw.ff_only_rbtn
+            .connect_property_active_notify(move |_| mw_clone.update_message_sensitivity());
+
+        mw.v_box.show_all();
+
+        mw
+    }
+
+    fn update_message_sensitivity(&self) {
+        let uses_message = self.get_options().uses_message();
+        self.message.pwo().set_sensitive(uses_message);
+    }
+
+    pub fn get_options(&self) -> MergeOptions {
+        let fast_forward = if self.no_ff_rbtn.get_active() {
+            FastForward::Never
+        } else if self.ff_only_rbtn.get_active() {
+            FastForward::Only
+        } else {
+            FastForward::Default
+        };
+        MergeOptions {
+            fast_forward,
+            squash: self.squash_cbtn.get_active(),
+            no_commit: self.no_commit_cbtn.get_active(),
+            strategy: non_empty_text(&self.strategy_combo),
+            strategy_option: non_empty_text(&self.strategy_option_combo),
+            message: self.message.get_message(),
+        }
+    }
+}
+
+pub trait MergeBranch: WidgetWrapper {
+    fn exec_console(&self) -> &Rc<ExecConsole>;
+
+    fn merge_branch(&self, branch: &str) {
+        let dialog = self
+            .new_dialog_builder()
+            .title(&format!("Merge \"{branch}\" Into Current Branch"))
+            .destroy_with_parent(true)
+            .modal(true)
+            .build();
+        for button in Self::CANCEL_OK_BUTTONS.iter() {
+            dialog.add_button(button.0, button.1);
+        }
+        dialog.set_default_response(gtk::ResponseType::Ok);
+        let mw = MergeWidget::new(branch, &self.exec_console().ws_path);
+        dialog
+            .get_content_area()
+            .pack_start(mw.pwo(), true, true, 0);
+        dialog.get_content_area().show_all();
+        dialog.set_size_from_recollections("merge:dialog", (640, 480));
+        let result = dialog.run();
+        dialog.hide();
+        // No second chances: a failed merge may have left conflicts to resolve
+        if result == gtk::ResponseType::Ok {
+            let cmd = mw.get_options().command(branch);
+            let old_cursor = self.show_busy();
+            let result = self.exec_console().exec_cmd(
+                &cmd,
+                events::EV_BRANCHES_CHANGE | events::EV_COMMIT | events::EV_FILES_CHANGE,
+            );
+            self.unshow_busy(old_cursor);
+            self.report_any_command_problems(&cmd.to_string(), &result);
+        }
+        unsafe { dialog.destroy() };
+    }
+}
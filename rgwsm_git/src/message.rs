@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::process::Command;
 use std::rc::Rc;
 
 use pw_gix::{
@@ -21,8 +20,11 @@ use pw_gix::{
     wrapper::*,
 };
 
-fn get_name_and_email_string() -> String {
-    let output = Command::new("git")
+use gwsm_git_core::workspace::WsPath;
+
+fn get_name_and_email_string(ws_path: &WsPath) -> String {
+    let output = ws_path
+        .git()
         .arg("config")
         .arg("user.name")
         .output()
@@ -32,7 +34,8 @@ fn get_name_and_email_string() -> String {
     } else {
         "user name".to_string()
     };
-    let output = Command::new("git")
+    let output = ws_path
+        .git()
         .arg("config")
         .arg("user.email")
         .output()
@@ -45,18 +48,19 @@ fn get_name_and_email_string() -> String {
     format!("{} <{}>", name.trim_end(), email.trim_end())
 }
 
-fn insert_acked_by_at_cursor(buffer: &gtk::TextBuffer) {
-    let text = format!("Acked-by: {}", get_name_and_email_string());
+fn insert_acked_by_at_cursor(ws_path: &WsPath, buffer: &gtk::TextBuffer) {
+    let text = format!("Acked-by: {}", get_name_and_email_string(ws_path));
     buffer.insert_at_cursor(&text)
 }
 
-fn insert_signed_off_by_at_cursor(buffer: &gtk::TextBuffer) {
-    let text = format!("Signed-off-by: {}", get_name_and_email_string());
+fn insert_signed_off_by_at_cursor(ws_path: &WsPath, buffer: &gtk::TextBuffer) {
+    let text = format!("Signed-off-by: {}", get_name_and_email_string(ws_path));
     buffer.insert_at_cursor(&text)
 }
 
-pub fn last_commit_message() -> String {
-    let output = Command::new("git")
+pub fn last_commit_message(ws_path: &WsPath) -> String {
+    let output = ws_path
+        .git()
         .arg("log")
         .arg("-n")
         .arg("1")
@@ -77,7 +81,7 @@ pub struct MessageWidget {
 }
 
 impl MessageWidget {
-    pub fn new(title: &str) -> Rc<Self> {
+    pub fn new(title: &str, ws_path: &WsPath) -> Rc<Self> {
         let mw = Rc::new(Self {
             v_box: gtk::Box::new(gtk::Orientation::Vertical, 0),
             text_view: sourceview::View::new(),
@@ -96,15 +100,20 @@ impl MessageWidget {
         mw.text_view.set_monospace(true);
         mw.text_view.set_show_right_margin(true);
         mw.text_view.set_right_margin_position(71);
-        mw.text_view.connect_populate_popup(|view, widget| {
+        let ws_path = ws_path.clone();
+        mw.text_view.connect_populate_popup(move |view, widget| {
             if let Ok(ref menu) = widget.clone().downcast::<gtk::Menu>() {
                 let mi = gtk::MenuItem::with_label("Insert Acked-by");
                 let buffer = view.get_buffer().unwrap();
-                mi.connect_activate(move |_| insert_acked_by_at_cursor(&buffer));
+                let ws_path_clone = ws_path.clone();
+                mi.connect_activate(move |_| insert_acked_by_at_cursor(&ws_path_clone, &buffer));
                 menu.append(&mi);
                 let mi = gtk::MenuItem::with_label("Insert Signed-off-by");
                 let buffer = view.get_buffer().unwrap();
-                mi.connect_activate(move |_| insert_signed_off_by_at_cursor(&buffer));
+                let ws_path_clone = ws_path.clone();
+                mi.connect_activate(move |_| {
+                    insert_signed_off_by_at_cursor(&ws_path_clone, &buffer)
+                });
                 menu.append(&mi);
                 menu.show_all();
             }
@@ -118,6 +127,11 @@ impl MessageWidget {
         mw
     }
 
+    pub fn set_message(&self, text: &str) {
+        let buffer = self.text_view.get_buffer().expect("get_buffer() failed");
+        buffer.set_text(text);
+    }
+
     pub fn get_message(&self) -> Option<String> {
         let buffer = self.text_view.get_buffer().expect("get_buffer() failed");
         let start = buffer.get_start_iter();
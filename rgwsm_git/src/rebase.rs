@@ -0,0 +1,315 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+
+use pw_gix::{
+    glib,
+    gtk::{self, prelude::*},
+    gtkx::dialog::RememberDialogSize,
+    wrapper::*,
+};
+
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::rebase::{self, RebaseAction, RebaseStep};
+use gwsm_git_core::workspace::WsPath;
+
+use crate::events::{self, EventSet};
+use crate::exec::ExecConsole;
+use crate::message;
+use crate::repos::{self, OperationInProgress};
+
+const REBASE_EVENTS: EventSet = events::EV_FILES_CHANGE
+    .union(events::EV_COMMIT)
+    .union(events::EV_BRANCHES_CHANGE);
+
+// Plan list store columns
+const ACTION: u32 = 0;
+const COMMIT: u32 = 1;
+const SUBJECT: u32 = 2;
+const MESSAGE: u32 = 3;
+
+fn commit_message(ws_path: &WsPath, commit: &str) -> String {
+    let output = ws_path
+        .git()
+        .arg("log")
+        .arg("-n")
+        .arg("1")
+        .arg("--format=%B")
+        .arg(commit)
+        .output()
+        .expect("\"git log -n 1 --format=%B <commit>\" blew up!!!");
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[derive(PWO, Wrapper)]
+pub struct RebasePlanWidget {
+    v_box: gtk::Box,
+    view: gtk::TreeView,
+    list_store: gtk::ListStore,
+    ws_path: WsPath,
+}
+
+impl RebasePlanWidget {
+    pub fn new(steps: &[RebaseStep], ws_path: &WsPath) -> Rc<RebasePlanWidget> {
+        let list_store = gtk::ListStore::new(&[glib::Type::String; 4]);
+        for step in steps.iter() {
+            list_store.insert_with_values(
+                None,
+                &[ACTION, COMMIT, SUBJECT, MESSAGE],
+                &[
+                    &step.action.name(),
+                    &step.commit,
+                    &step.subject,
+                    &step.message.clone().unwrap_or_default(),
+                ],
+            );
+        }
+        let view = gtk::TreeView::with_model(&list_store);
+        view.set_headers_visible(true);
+        view.set_reorderable(true);
+        view.get_selection().set_mode(gtk::SelectionMode::Single);
+
+        let actions = gtk::ListStore::new(&[glib::Type::String]);
+        for action in RebaseAction::ALL.iter() {
+            actions.insert_with_values(None, &[0], &[&action.name()]);
+        }
+        let col = gtk::TreeViewColumn::new();
+        col.set_title("Action");
+        let action_cell = gtk::CellRendererCombo::new();
+        action_cell.set_property_model(Some(&actions));
+        action_cell.set_property_text_column(0);
+        action_cell.set_property_has_entry(false);
+        action_cell.set_property_editable(true);
+        col.pack_start(&action_cell, false);
+        col.add_attribute(&action_cell, "text", ACTION as i32);
+        view.append_column(&col);
+
+        for (title, column) in [("Commit", COMMIT), ("Subject", SUBJECT)].iter() {
+            let col = gtk::TreeViewColumn::new();
+            col.set_title(title);
+            col.set_expand(*column == SUBJECT);
+            let cell = gtk::CellRendererText::new();
+            cell.set_property_editable(false);
+            col.pack_start(&cell, false);
+            col.add_attribute(&cell, "text", *column as i32);
+            view.append_column(&col);
+        }
+
+        let v_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        let label = gtk::Label::new(Some(
+            "Commits are applied from the top down: drag and drop rows to reorder them and click on an action to change it.",
+        ));
+        label.set_line_wrap(true);
+        v_box.pack_start(&label, false, false, 0);
+        let adj: Option<&gtk::Adjustment> = None;
+        let scrolled_window = gtk::ScrolledWindow::new(adj, adj);
+        scrolled_window.add(&view);
+        v_box.pack_start(&scrolled_window, true, true, 0);
+        v_box.show_all();
+
+        let rpw = Rc::new(RebasePlanWidget {
+            v_box,
+            view,
+            list_store,
+            ws_path: ws_path.clone(),
+        });
+
+        let rpw_clone = Rc::clone(&rpw);
+        action_cell.connect_edited(move |_, path, new_text| {
+            if let Some(iter) = rpw_clone.list_store.get_iter(&path) {
+                if new_text == RebaseAction::Reword.name() && !rpw_clone.reword(&iter) {
+                    return;
+                }
+                rpw_clone
+                    .list_store
+                    .set_value(&iter, ACTION, &new_text.to_value());
+            }
+        });
+
+        rpw
+    }
+
+    fn get_string(&self, iter: &gtk::TreeIter, column: u32) -> String {
+        self.list_store
+            .get_value(iter, column as i32)
+            .get::<String>()
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    // Ask for the reworded commit's new message and return whether one was given
+    fn reword(&self, iter: &gtk::TreeIter) -> bool {
+        let current_message = match self.get_string(iter, MESSAGE) {
+            message if message.is_empty() => {
+                commit_message(&self.ws_path, &self.get_string(iter, COMMIT))
+            }
+            message => message,
+        };
+        let dialog = self
+            .new_dialog_builder()
+            .title("Reword Commit")
+            .destroy_with_parent(true)
+            .modal(true)
+            .build();
+        for button in Self::CANCEL_OK_BUTTONS.iter() {
+            dialog.add_button(button.0, button.1);
+        }
+        let message = message::MessageWidget::new("New Message", &self.ws_path);
+        message.set_message(current_message.trim_end());
+        dialog
+            .get_content_area()
+            .pack_start(message.pwo(), true, true, 0);
+        dialog.get_content_area().show_all();
+        dialog.set_size_from_recollections("rebase:reword:dialog", (560, 240));
+        let new_message = if dialog.run() == gtk::ResponseType::Ok {
+            message.get_message()
+        } else {
+            None
+        };
+        unsafe { dialog.destroy() };
+        match new_message {
+            Some(new_message) => {
+                let subject = new_message.lines().next().unwrap_or("").to_string();
+                self.list_store
+                    .set_value(iter, SUBJECT, &subject.to_value());
+                self.list_store
+                    .set_value(iter, MESSAGE, &new_message.to_value());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_steps(&self) -> Vec<RebaseStep> {
+        let mut steps = vec![];
+        if let Some(iter) = self.list_store.get_iter_first() {
+            loop {
+                let message = self.get_string(&iter, MESSAGE);
+                steps.push(RebaseStep {
+                    action: RebaseAction::from_name(&self.get_string(&iter, ACTION))
+                        .unwrap_or(RebaseAction::Pick),
+                    commit: self.get_string(&iter, COMMIT),
+                    subject: self.get_string(&iter, SUBJECT),
+                    message: if message.is_empty() {
+                        None
+                    } else {
+                        Some(message)
+                    },
+                });
+                if !self.list_store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        steps
+    }
+}
+
+pub trait RebaseBranch: WidgetWrapper {
+    fn exec_console(&self) -> &Rc<ExecConsole>;
+
+    fn rebase_onto(&self, upstream: &str) {
+        let cmd = GitCommand::new("rebase").arg(upstream);
+        let msg = format!("Confirm: {cmd}");
+        let explanation = format!(
+            "The current branch's commits that aren't in \"{upstream}\" will be rewritten."
+        );
+        if self.ask_confirm_action(&msg, Some(&explanation)) {
+            let cursor = self.show_busy();
+            let result = self.exec_console().exec_cmd(&cmd, REBASE_EVENTS);
+            self.unshow_busy(cursor);
+            self.report_any_command_problems(&cmd.to_string(), &result);
+            self.report_any_stop();
+        }
+    }
+
+    fn interactive_rebase(&self, upstream: &str) {
+        let ws_path = self.exec_console().ws_path.clone();
+        let output = rebase::rebase_commits_cmd(upstream)
+            .command(&ws_path)
+            .output()
+            .expect("\"git log --reverse <upstream>...HEAD\" blew up!!!");
+        let steps = rebase::parse_rebase_commits(&String::from_utf8_lossy(&output.stdout));
+        if steps.is_empty() {
+            let msg = format!("The current branch has no commits that aren't in \"{upstream}\".");
+            self.inform_user(&msg, None);
+            return;
+        }
+        let dialog = self
+            .new_dialog_builder()
+            .title(&format!("Interactive Rebase Onto \"{upstream}\""))
+            .destroy_with_parent(true)
+            .modal(true)
+            .build();
+        for button in Self::CANCEL_OK_BUTTONS.iter() {
+            dialog.add_button(button.0, button.1);
+        }
+        dialog.set_default_response(gtk::ResponseType::Ok);
+        let rpw = RebasePlanWidget::new(&steps, &ws_path);
+        dialog
+            .get_content_area()
+            .pack_start(rpw.pwo(), true, true, 0);
+        dialog.get_content_area().show_all();
+        dialog.set_size_from_recollections("rebase:interactive:dialog", (640, 400));
+        let mut todo_path = None;
+        while dialog.run() == gtk::ResponseType::Ok {
+            let steps = rpw.get_steps();
+            if let Err(err) = rebase::check_rebase_plan(&steps) {
+                self.report_error("Invalid rebase plan", &err);
+                continue;
+            }
+            match rebase::rebase_plan_dir(&ws_path) {
+                Some(dir) => match rebase::write_rebase_plan(&steps, &dir) {
+                    Ok(path) => todo_path = Some(path),
+                    Err(err) => self.report_error("Failed to write rebase plan", &err),
+                },
+                None => self.inform_user("Unable to find the repository's git directory.", None),
+            }
+            break;
+        }
+        unsafe { dialog.destroy() };
+        if let Some(todo_path) = todo_path {
+            let cmd = rebase::interactive_rebase_cmd(upstream, &todo_path);
+            let cursor = self.show_busy();
+            let result = self.exec_console().exec_cmd(&cmd, REBASE_EVENTS);
+            self.unshow_busy(cursor);
+            self.report_any_command_problems(&cmd.to_string(), &result);
+            self.report_any_stop();
+        }
+    }
+
+    // Let the user know where to find the controls if the rebase has stopped
+    fn report_any_stop(&self) {
+        if repos::get_operation_in_progress(&self.exec_console().ws_path)
+            == Some(OperationInProgress::Rebase)
+        {
+            self.inform_user(
+                "The rebase has stopped.",
+                Some("Use the \"Continue\", \"Skip\" or \"Abort\" buttons in the \"Conflicts\" tab once ready."),
+            );
+        } else {
+            remove_finished_rebase_plan(&self.exec_console().ws_path);
+        }
+    }
+}
+
+// The plan has to survive stops but not the end (or abandonment) of the rebase
+pub fn remove_finished_rebase_plan(ws_path: &WsPath) {
+    if repos::get_operation_in_progress(ws_path) != Some(OperationInProgress::Rebase) {
+        if let Some(dir) = rebase::rebase_plan_dir(ws_path) {
+            let _ = rebase::remove_rebase_plan(&dir);
+        }
+    }
+}
@@ -14,11 +14,9 @@
 
 use std::cell::{Cell, Ref, RefCell};
 use std::io::Write;
-use std::process::Command;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
-use regex::Regex;
 
 use pw_gix::{
     glib,
@@ -34,6 +32,10 @@ use pw_gix::{
     wrapper::*,
 };
 
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::remotes::parse_remotes;
+use gwsm_git_core::workspace::WsPath;
+
 use crate::events;
 use crate::exec::ExecConsole;
 use crate::repos;
@@ -64,11 +66,11 @@ impl SimpleRemoteActionButtons {
         );
         let srab_clone = Rc::clone(&srab);
         srab.simple_pull_button.connect_clicked(move |_| {
-            let cmd = "git pull";
+            let cmd = GitCommand::new("pull");
             let cursor = srab_clone.show_busy();
-            let result = srab_clone.exec_console.exec_cmd(cmd, events::EV_PULL);
+            let result = srab_clone.exec_console.exec_cmd(&cmd, events::EV_PULL);
             srab_clone.unshow_busy(cursor);
-            srab_clone.report_any_command_problems(cmd, &result);
+            srab_clone.report_any_command_problems(&cmd.to_string(), &result);
         });
 
         srab.simple_push_button
@@ -80,11 +82,11 @@ impl SimpleRemoteActionButtons {
         );
         let srab_clone = Rc::clone(&srab);
         srab.simple_push_button.connect_clicked(move |_| {
-            let cmd = "git push";
+            let cmd = GitCommand::new("push");
             let cursor = srab_clone.show_busy();
-            let result = srab_clone.exec_console.exec_cmd(cmd, events::EV_PULL);
+            let result = srab_clone.exec_console.exec_cmd(&cmd, events::EV_PULL);
             srab_clone.unshow_busy(cursor);
-            srab_clone.report_any_command_problems(cmd, &result);
+            srab_clone.report_any_command_problems(&cmd.to_string(), &result);
         });
 
         srab.h_box
@@ -97,9 +99,10 @@ impl SimpleRemoteActionButtons {
     }
 }
 
-fn get_raw_data() -> (String, Vec<u8>) {
+fn get_raw_data(ws_path: &WsPath) -> (String, Vec<u8>) {
     let mut hasher = Hasher::new(Algorithm::SHA256);
-    let output = Command::new("git")
+    let output = ws_path
+        .git()
         .arg("remote")
         .arg("-v")
         .output()
@@ -113,19 +116,17 @@ fn get_raw_data() -> (String, Vec<u8>) {
     (text, hasher.finish())
 }
 
-lazy_static! {
-    static ref VREMOTE_RE: Regex = Regex::new(r"(\S+)\s+(\S+)\s*(\S*)").unwrap();
-}
-
 struct RemotesRowBuffer {
     row_buffer_core: Rc<RefCell<RowBufferCore<String>>>,
+    ws_path: WsPath,
 }
 
 impl RemotesRowBuffer {
-    fn new() -> Self {
+    fn new(ws_path: &WsPath) -> Self {
         let core = RowBufferCore::<String>::default();
         let buffer = Self {
             row_buffer_core: Rc::new(RefCell::new(core)),
+            ws_path: ws_path.clone(),
         };
         buffer.init();
         buffer
@@ -138,33 +139,22 @@ impl RowBuffer<String> for RemotesRowBuffer {
     }
 
     fn set_raw_data(&self) {
-        let (raw_data, digest) = get_raw_data();
+        let (raw_data, digest) = get_raw_data(&self.ws_path);
         let mut core = self.row_buffer_core.borrow_mut();
         core.set_raw_data(raw_data, digest);
     }
 
     fn finalise(&self) {
-        let mut rows: Vec<Row> = Vec::new();
-        {
-            let core = self.row_buffer_core.borrow();
-            let mut name: &str = "";
-            let mut inbound_url: &str = "";
-            for (i, line) in core.raw_data.lines().enumerate() {
-                let captures = VREMOTE_RE.captures(line).unwrap();
-                if i % 2 == 0 {
-                    name = captures.get(1).unwrap().as_str();
-                    inbound_url = captures.get(2).unwrap().as_str();
-                } else {
-                    let outbound_url = captures.get(2).unwrap().as_str();
-                    let row = vec![
-                        name.to_value(),
-                        inbound_url.to_value(),
-                        outbound_url.to_value(),
-                    ];
-                    rows.push(row);
-                }
-            }
-        }
+        let rows: Vec<Row> = parse_remotes(&self.row_buffer_core.borrow().raw_data)
+            .iter()
+            .map(|remote| {
+                vec![
+                    remote.name.to_value(),
+                    remote.inbound_url.to_value(),
+                    remote.outbound_url.to_value(),
+                ]
+            })
+            .collect();
         let mut core = self.row_buffer_core.borrow_mut();
         core.rows = Rc::new(rows);
         core.set_is_finalised_true();
@@ -187,10 +177,10 @@ impl BufferedUpdate<String, gtk::ListStore> for RemotesNameListStore {
 }
 
 impl RemotesNameListStore {
-    pub fn new() -> RemotesNameListStore {
+    pub fn new(ws_path: &WsPath) -> RemotesNameListStore {
         Self {
             list_store: gtk::ListStore::new(&[glib::Type::String; 3]),
-            remotes_row_buffer: Rc::new(RefCell::new(RemotesRowBuffer::new())),
+            remotes_row_buffer: Rc::new(RefCell::new(RemotesRowBuffer::new(ws_path))),
         }
     }
 }
@@ -226,7 +216,7 @@ impl MapManagedUpdate<RemotesNameListStore, String, gtk::ListStore> for RemotesN
 
 impl RemotesNameTable {
     pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<RemotesNameTable> {
-        let list_store = RefCell::new(RemotesNameListStore::new());
+        let list_store = RefCell::new(RemotesNameListStore::new(&exec_console.ws_path));
 
         let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
         view.set_headers_visible(true);
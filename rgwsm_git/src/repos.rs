@@ -12,10 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::error::Error;
-use std::fmt;
-use std::fs::File;
-use std::io::{self, Read, Write};
 use std::rc::Rc;
 
 use pw_gix::{
@@ -26,7 +22,13 @@ use pw_gix::{
 
 use pw_pathux::str_path::*;
 
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::known_repos::{self, KRTError};
+use gwsm_git_core::status;
+use gwsm_git_core::workspace::WsPath;
+
 use crate::config;
+use crate::events;
 use crate::exec::ExecConsole;
 use crate::submodules;
 
@@ -36,12 +38,62 @@ pub const SAV_NOT_IN_SUBMODULE: u64 = SAV_NOT_IN_REPO << 2;
 pub const SAV_IN_SUBMODULE: u64 = SAV_NOT_IN_REPO << 3;
 pub const SAV_NOT_HAS_SUBMODULES: u64 = SAV_NOT_IN_REPO << 4;
 pub const SAV_HAS_SUBMODULES: u64 = SAV_NOT_IN_REPO << 5;
+pub const SAV_NOT_MERGING: u64 = SAV_NOT_IN_REPO << 6;
+pub const SAV_MERGING: u64 = SAV_NOT_IN_REPO << 7;
+pub const SAV_NOT_REBASING: u64 = SAV_NOT_IN_REPO << 8;
+pub const SAV_REBASING: u64 = SAV_NOT_IN_REPO << 9;
+pub const SAV_NOT_PICKING: u64 = SAV_NOT_IN_REPO << 10;
+pub const SAV_PICKING: u64 = SAV_NOT_IN_REPO << 11;
+pub const SAV_NOT_BISECTING: u64 = SAV_NOT_IN_REPO << 12;
+pub const SAV_BISECTING: u64 = SAV_NOT_IN_REPO << 13;
+pub const SAV_NOT_HEAD_DETACHED: u64 = SAV_NOT_IN_REPO << 14;
+pub const SAV_HEAD_DETACHED: u64 = SAV_NOT_IN_REPO << 15;
+pub const SAV_NOT_WD_DIRTY: u64 = SAV_NOT_IN_REPO << 16;
+pub const SAV_WD_DIRTY: u64 = SAV_NOT_IN_REPO << 17;
+pub const SAV_NOT_UNBORN_BRANCH: u64 = SAV_NOT_IN_REPO << 18;
+pub const SAV_UNBORN_BRANCH: u64 = SAV_NOT_IN_REPO << 19;
+pub const SAV_NOT_HAS_STASHES: u64 = SAV_NOT_IN_REPO << 20;
+pub const SAV_HAS_STASHES: u64 = SAV_NOT_IN_REPO << 21;
+pub const SAV_NOT_HAS_CONFLICTS: u64 = SAV_NOT_IN_REPO << 22;
+pub const SAV_HAS_CONFLICTS: u64 = SAV_NOT_IN_REPO << 23;
+pub const SAV_NOT_APPLYING_MAILBOX: u64 = SAV_NOT_IN_REPO << 24;
+pub const SAV_APPLYING_MAILBOX: u64 = SAV_NOT_IN_REPO << 25;
+pub const SAV_NOT_OP_IN_PROGRESS: u64 = SAV_NOT_MERGING
+    | SAV_NOT_REBASING
+    | SAV_NOT_PICKING
+    | SAV_NOT_BISECTING
+    | SAV_NOT_APPLYING_MAILBOX;
+// The conditions derived from the work tree's status rather than the repository
+pub const SAV_STATUS_MASK: u64 =
+    SAV_NOT_WD_DIRTY | SAV_WD_DIRTY | SAV_NOT_HAS_CONFLICTS | SAV_HAS_CONFLICTS;
 pub const SAV_REPO_STATE_MASK: u64 = SAV_NOT_IN_REPO
     | SAV_IN_REPO
     | SAV_NOT_IN_SUBMODULE
     | SAV_IN_SUBMODULE
     | SAV_NOT_HAS_SUBMODULES
-    | SAV_HAS_SUBMODULES;
+    | SAV_HAS_SUBMODULES
+    | SAV_NOT_MERGING
+    | SAV_MERGING
+    | SAV_NOT_REBASING
+    | SAV_REBASING
+    | SAV_NOT_PICKING
+    | SAV_PICKING
+    | SAV_NOT_BISECTING
+    | SAV_BISECTING
+    | SAV_NOT_HEAD_DETACHED
+    | SAV_HEAD_DETACHED
+    | SAV_NOT_WD_DIRTY
+    | SAV_WD_DIRTY
+    | SAV_NOT_UNBORN_BRANCH
+    | SAV_UNBORN_BRANCH
+    | SAV_NOT_HAS_STASHES
+    | SAV_HAS_STASHES
+    | SAV_NOT_HAS_CONFLICTS
+    | SAV_HAS_CONFLICTS
+    | SAV_NOT_APPLYING_MAILBOX
+    | SAV_APPLYING_MAILBOX;
+// First condition bit available for use by individual widgets
+pub const SAV_NEXT_REPO_CONDN: u64 = SAV_APPLYING_MAILBOX << 1;
 
 pub fn is_repo_workdir(dir_path: &str) -> bool {
     git2::Repository::open(dir_path.path_absolute().unwrap()).is_ok()
@@ -56,66 +108,184 @@ pub fn get_repo_workdir_for_path(dir_path: &str) -> Option<String> {
     }
 }
 
-pub fn get_repo_condns() -> MaskedCondns {
+fn get_repo_op_condns(repo: &git2::Repository) -> u64 {
+    let mut condns = SAV_NOT_OP_IN_PROGRESS;
+    match get_repo_operation(repo) {
+        Some(OperationInProgress::Merge) => condns ^= SAV_NOT_MERGING | SAV_MERGING,
+        Some(OperationInProgress::Rebase) => condns ^= SAV_NOT_REBASING | SAV_REBASING,
+        Some(OperationInProgress::CherryPick) | Some(OperationInProgress::Revert) => {
+            condns ^= SAV_NOT_PICKING | SAV_PICKING
+        }
+        Some(OperationInProgress::ApplyMailbox) => {
+            condns ^= SAV_NOT_APPLYING_MAILBOX | SAV_APPLYING_MAILBOX
+        }
+        Some(OperationInProgress::Bisect) => condns ^= SAV_NOT_BISECTING | SAV_BISECTING,
+        None => (),
+    }
+    condns
+}
+
+// Whether the work tree is dirty (ignoring untracked files and submodules) or
+// has conflicts according to "git status --porcelain=v2 -z" output
+pub fn get_status_condns(status_text: &str) -> MaskedCondns {
+    let mut dirty = false;
+    let mut has_conflicts = false;
+    for entry in status::parse_porcelain_v2(status_text) {
+        if status::status_is_unmerged(&entry.status) {
+            has_conflicts = true;
+            dirty = true;
+        } else if entry.submodule_state.is_none() && status::status_is_tracked(&entry.status) {
+            dirty = true;
+        }
+    }
+    let mut condns = if dirty {
+        SAV_WD_DIRTY
+    } else {
+        SAV_NOT_WD_DIRTY
+    };
+    condns |= if has_conflicts {
+        SAV_HAS_CONFLICTS
+    } else {
+        SAV_NOT_HAS_CONFLICTS
+    };
+    MaskedCondns {
+        condns,
+        mask: SAV_STATUS_MASK,
+    }
+}
+
+pub fn get_repo_condns(ws_path: &WsPath) -> MaskedCondns {
     let mut condns: u64;
-    if is_repo_workdir(".") {
+    if is_repo_workdir(&ws_path.path_string()) {
         condns = SAV_IN_REPO;
-        if submodules::is_git_submodule(None) {
+        if submodules::is_git_submodule(ws_path, None) {
             condns |= SAV_IN_SUBMODULE;
         } else {
             condns |= SAV_NOT_IN_SUBMODULE;
         }
-        if submodules::submodule_count() > 0 {
+        if submodules::submodule_count(ws_path) > 0 {
             condns |= SAV_HAS_SUBMODULES;
         } else {
             condns |= SAV_NOT_HAS_SUBMODULES;
         }
+        if let Ok(repo) = git2::Repository::open(ws_path.path()) {
+            condns |= get_repo_op_condns(&repo);
+            match repo.head() {
+                Err(ref err) if err.code() == git2::ErrorCode::UnbornBranch => {
+                    condns |= SAV_UNBORN_BRANCH | SAV_NOT_HEAD_DETACHED;
+                }
+                _ => {
+                    condns |= SAV_NOT_UNBORN_BRANCH;
+                    if repo.head_detached().unwrap_or(false) {
+                        condns |= SAV_HEAD_DETACHED;
+                    } else {
+                        condns |= SAV_NOT_HEAD_DETACHED;
+                    }
+                }
+            }
+            if repo.find_reference("refs/stash").is_ok() {
+                condns |= SAV_HAS_STASHES;
+            } else {
+                condns |= SAV_NOT_HAS_STASHES;
+            }
+        }
     } else {
         condns = SAV_NOT_IN_REPO | SAV_NOT_IN_SUBMODULE | SAV_NOT_HAS_SUBMODULES;
     }
+    // the work tree's status is left to get_status_condns()
     MaskedCondns {
         condns,
-        mask: SAV_REPO_STATE_MASK,
+        mask: SAV_REPO_STATE_MASK & !SAV_STATUS_MASK,
     }
 }
 
-#[derive(Debug)]
-pub enum KRTError {
-    IOError(io::Error),
-    JsonError(serde_json::Error),
+// Multi step operations that may be left in progress (e.g. pending conflict resolution)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OperationInProgress {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    ApplyMailbox,
+    Bisect,
 }
 
-impl fmt::Display for KRTError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "KRTError is here!")
+impl OperationInProgress {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OperationInProgress::Merge => "merge",
+            OperationInProgress::Rebase => "rebase",
+            OperationInProgress::CherryPick => "cherry-pick",
+            OperationInProgress::Revert => "revert",
+            OperationInProgress::ApplyMailbox => "am",
+            OperationInProgress::Bisect => "bisect",
+        }
     }
-}
 
-impl Error for KRTError {
-    fn description(&self) -> &str {
+    // GIT_EDITOR is overridden (it beats VISUAL, EDITOR and "core.editor") so
+    // that the default messages are accepted rather than waiting for an
+    // editor that the user can't see.
+    pub fn continue_cmd(&self) -> Option<GitCommand> {
         match self {
-            KRTError::IOError(_) => "I/O Error accessing known repos table",
-            KRTError::JsonError(_) => "Serde Json Error accessing known repos table",
+            OperationInProgress::Bisect => None,
+            _ => Some(
+                GitCommand::new(self.name())
+                    .env("GIT_EDITOR", "true")
+                    .arg("--continue"),
+            ),
         }
     }
 
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
+    // Skip the commit (or patch) that the operation has stopped at
+    pub fn skip_cmd(&self) -> Option<GitCommand> {
         match self {
-            KRTError::IOError(err) => Some(err),
-            KRTError::JsonError(err) => Some(err),
+            OperationInProgress::Merge | OperationInProgress::Bisect => None,
+            _ => Some(
+                GitCommand::new(self.name())
+                    .env("GIT_EDITOR", "true")
+                    .arg("--skip"),
+            ),
         }
     }
-}
 
-impl From<io::Error> for KRTError {
-    fn from(error: io::Error) -> Self {
-        KRTError::IOError(error)
+    pub fn abort_cmd(&self) -> GitCommand {
+        match self {
+            OperationInProgress::Bisect => GitCommand::new("bisect").arg("reset"),
+            _ => GitCommand::new(self.name()).arg("--abort"),
+        }
     }
 }
 
-impl From<serde_json::Error> for KRTError {
-    fn from(error: serde_json::Error) -> Self {
-        KRTError::JsonError(error)
+pub fn get_operation_in_progress(ws_path: &WsPath) -> Option<OperationInProgress> {
+    let repo = git2::Repository::discover(ws_path.path()).ok()?;
+    get_repo_operation(&repo)
+}
+
+// git2 can't tell "git am" from a patch based "git rebase" (both use the
+// "rebase-apply" directory) so do what git does and look for "applying".
+fn get_repo_operation(repo: &git2::Repository) -> Option<OperationInProgress> {
+    use git2::RepositoryState;
+    match repo.state() {
+        RepositoryState::Merge => Some(OperationInProgress::Merge),
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => Some(OperationInProgress::Rebase),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+            Some(OperationInProgress::CherryPick)
+        }
+        RepositoryState::Revert | RepositoryState::RevertSequence => {
+            Some(OperationInProgress::Revert)
+        }
+        RepositoryState::ApplyMailbox => Some(OperationInProgress::ApplyMailbox),
+        RepositoryState::ApplyMailboxOrRebase => {
+            if repo.path().join("rebase-apply").join("applying").exists() {
+                Some(OperationInProgress::ApplyMailbox)
+            } else {
+                Some(OperationInProgress::Rebase)
+            }
+        }
+        RepositoryState::Bisect => Some(OperationInProgress::Bisect),
+        RepositoryState::Clean => None,
     }
 }
 
@@ -126,43 +296,26 @@ fn known_repos_table_filepath() -> PathBuf {
 }
 
 fn read_known_repos_table() -> Result<Vec<(String, String)>, KRTError> {
-    let mut file = File::open(known_repos_table_filepath())?;
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
-    let mut v: Vec<(String, String)> = serde_json::from_str(&buffer)?;
+    let mut v = known_repos::read_known_repos_table(&known_repos_table_filepath())?;
     // Prune any repos that no longer exist.
-    let mut pruned = vec![];
-    for item in v.drain(..) {
-        if is_repo_workdir(&item.1) {
-            pruned.push(item);
-        }
-    }
-    Ok(pruned)
-}
-
-fn write_known_repos_table(table: &[(String, String)]) -> Result<usize, KRTError> {
-    let data = serde_json::to_string(table)?;
-    let mut file = File::create(known_repos_table_filepath())?;
-    let nbytes = file.write(data.as_bytes())?;
-    Ok(nbytes)
+    v.retain(|item| is_repo_workdir(&item.1));
+    Ok(v)
 }
 
 pub fn init_known_repos_table() {
-    if !known_repos_table_filepath().is_file() {
-        write_known_repos_table(&[]).expect("failed to initialize editor assignment table");
+    let file_path = known_repos_table_filepath();
+    if !file_path.is_file() {
+        known_repos::write_known_repos_table(&file_path, &[])
+            .expect("failed to initialize known repos table");
     }
 }
 
 pub fn add_to_known_repos(repo_path: &str) -> Result<(), KRTError> {
     if is_repo_workdir(repo_path) {
         if let Some(dir_name) = repo_path.path_file_name() {
-            let new_entry = (dir_name, repo_path.to_string());
-            let mut known_repos = read_known_repos_table()?;
-            let result = known_repos.binary_search(&new_entry);
-            if let Err(insert_index) = result {
-                known_repos.insert(insert_index, new_entry);
-            }
-            write_known_repos_table(&known_repos)?;
+            let mut table = read_known_repos_table()?;
+            known_repos::insert_known_repo(&mut table, &dir_name, repo_path);
+            known_repos::write_known_repos_table(&known_repos_table_filepath(), &table)?;
         }
     }
     Ok(())
@@ -171,14 +324,14 @@ pub fn add_to_known_repos(repo_path: &str) -> Result<(), KRTError> {
 #[derive(PWO, Wrapper)]
 pub struct OpenKnownRepoMenuItem {
     menu_item: gtk::MenuItem,
-    exec_console: Rc<ExecConsole>,
+    open_repo: Rc<dyn Fn(&str)>,
 }
 
 impl OpenKnownRepoMenuItem {
-    pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<Self> {
+    pub fn new(label: &str, open_repo: Rc<dyn Fn(&str)>) -> Rc<Self> {
         let ormi = Rc::new(Self {
-            menu_item: gtk::MenuItem::with_label("Change Directory To ->"),
-            exec_console: Rc::clone(exec_console),
+            menu_item: gtk::MenuItem::with_label(label),
+            open_repo,
         });
 
         let ormi_clone = Rc::clone(&ormi);
@@ -198,8 +351,8 @@ impl OpenKnownRepoMenuItem {
         for item in table.drain(..) {
             let label = format!("{} :-> {}", shlex::quote(&item.0), shlex::quote(&item.1));
             let menu_item = gtk::MenuItem::with_label(&label);
-            let exec_console = Rc::clone(&self.exec_console);
-            menu_item.connect_activate(move |_| exec_console.chdir(&item.1));
+            let open_repo = Rc::clone(&self.open_repo);
+            menu_item.connect_activate(move |_| open_repo(&item.1));
             menu.append(&menu_item);
         }
         menu.show_all();
@@ -392,16 +545,13 @@ impl CloneRepoMenuItem {
                     let in_dir = if let Some(in_dir) = crw.in_dir() {
                         in_dir
                     } else {
-                        str_path_current_dir_or_panic()
+                        self.exec_console.ws_path.path_string()
                     };
                     let tgt_dir = in_dir.path_join(&as_name);
-                    let cmd = format!(
-                        "git clone {} {}",
-                        shlex::quote(&src_url),
-                        shlex::quote(&tgt_dir)
-                    );
-                    let result = self.exec_console.exec_cmd(&cmd, 0);
-                    self.exec_console.report_any_command_problems(&cmd, &result);
+                    let cmd = GitCommand::new("clone").arg(src_url).arg(&tgt_dir);
+                    let result = self.exec_console.exec_cmd(&cmd, events::EV_NONE);
+                    self.exec_console
+                        .report_any_command_problems(&cmd.to_string(), &result);
                     if let Ok(ref output) = result {
                         if output.status.success() {
                             self.exec_console.chdir(&tgt_dir);
@@ -421,31 +571,185 @@ impl CloneRepoMenuItem {
     }
 }
 
-pub fn create_workspaces_menu(exec_console: &Rc<ExecConsole>) -> gtk::MenuItem {
+// "open_in_new_window" is supplied by the application as it owns the windows
+pub fn create_workspaces_menu(
+    exec_console: &Rc<ExecConsole>,
+    open_in_new_window: Rc<dyn Fn(&str)>,
+) -> gtk::MenuItem {
     let mi = gtk::MenuItem::with_label("Workspaces");
     let menu = gtk::Menu::new();
     mi.set_submenu(Some(&menu));
     menu.append(CloneRepoMenuItem::new(exec_console).pwo());
-    menu.append(OpenKnownRepoMenuItem::new(exec_console).pwo());
+    let ec_clone = Rc::clone(exec_console);
+    let chdir = Rc::new(move |dir_path: &str| ec_clone.chdir(dir_path));
+    menu.append(OpenKnownRepoMenuItem::new("Change Directory To ->", chdir).pwo());
+    menu.append(OpenKnownRepoMenuItem::new("Open In New Window ->", open_in_new_window).pwo());
     mi
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    use gwsm_git_core::test_support::ScratchRepo;
 
     #[test]
     fn is_repo_workdir_works() {
-        println!("{} -> {:?}", "..", "..".path_absolute());
-        assert!(!is_repo_workdir("."));
-        assert!(!is_repo_workdir("../src"));
-        assert!(is_repo_workdir(".."));
+        let repo = ScratchRepo::new();
+        let sub_dir = repo.path().join("sub");
+        fs::create_dir(&sub_dir).unwrap();
+        let not_repo = tempfile::tempdir().unwrap();
+        assert!(is_repo_workdir(&repo.path_string()));
+        assert!(!is_repo_workdir(&sub_dir.to_string_lossy()));
+        assert!(!is_repo_workdir(&not_repo.path().to_string_lossy()));
     }
 
     #[test]
     fn get_repo_workdir_for_path_works() {
-        assert!(get_repo_workdir_for_path(".").is_some());
-        assert!(get_repo_workdir_for_path("..").is_some());
-        assert!(get_repo_workdir_for_path("../..").is_none());
+        let repo = ScratchRepo::new();
+        let sub_dir = repo.path().join("sub");
+        fs::create_dir(&sub_dir).unwrap();
+        let not_repo = tempfile::tempdir().unwrap();
+        for dir_path in [repo.path(), sub_dir.as_path()].iter() {
+            let workdir = get_repo_workdir_for_path(&dir_path.to_string_lossy()).unwrap();
+            assert_eq!(Path::new(&workdir), repo.path());
+        }
+        assert!(get_repo_workdir_for_path(&not_repo.path().to_string_lossy()).is_none());
+    }
+
+    #[test]
+    fn get_repo_condns_works() {
+        let repo = ScratchRepo::empty();
+        let ws_path = repo.ws_path();
+        let condns = get_repo_condns(&ws_path).condns;
+        assert_ne!(condns & SAV_IN_REPO, 0);
+        assert_ne!(condns & SAV_UNBORN_BRANCH, 0);
+        repo.commit_file("README", "Read me.\n", "Initial commit");
+        repo.stash("README", "changed\n", "stashed");
+        let condns = get_repo_condns(&ws_path).condns;
+        assert_ne!(condns & SAV_NOT_UNBORN_BRANCH, 0);
+        assert_ne!(condns & SAV_HAS_STASHES, 0);
+        assert_eq!(condns & SAV_NOT_OP_IN_PROGRESS, SAV_NOT_OP_IN_PROGRESS);
+        assert_eq!(condns & SAV_STATUS_MASK, 0);
+        repo.start_conflicted_merge("theirs", "README");
+        let condns = get_repo_condns(&ws_path).condns;
+        assert_ne!(condns & SAV_MERGING, 0);
+        let not_repo = tempfile::tempdir().unwrap();
+        let condns = get_repo_condns(&WsPath::new(not_repo.path())).condns;
+        assert_ne!(condns & SAV_NOT_IN_REPO, 0);
+    }
+
+    #[test]
+    fn get_status_condns_works() {
+        let repo = ScratchRepo::new();
+        repo.commit_file("README", "Read me.\n", "Initial commit");
+        let status_text = || repo.git(&["status", "--porcelain=v2", "--branch", "-z"]);
+        repo.write_file("untracked.txt", "untracked\n");
+        let masked_condns = get_status_condns(&status_text());
+        assert_eq!(masked_condns.mask, SAV_STATUS_MASK);
+        assert_eq!(
+            masked_condns.condns,
+            SAV_NOT_WD_DIRTY | SAV_NOT_HAS_CONFLICTS
+        );
+        repo.write_file("README", "changed\n");
+        assert_eq!(
+            get_status_condns(&status_text()).condns,
+            SAV_WD_DIRTY | SAV_NOT_HAS_CONFLICTS
+        );
+        repo.git(&["checkout", "--", "README"]);
+        repo.start_conflicted_merge("theirs", "README");
+        assert_eq!(
+            get_status_condns(&status_text()).condns,
+            SAV_WD_DIRTY | SAV_HAS_CONFLICTS
+        );
+    }
+
+    #[test]
+    fn operation_in_progress_commands_work() {
+        let repo = ScratchRepo::new();
+        let ws_path = repo.ws_path();
+        let run = |cmd: GitCommand| {
+            let output = cmd.command(&ws_path).output().unwrap();
+            assert!(output.status.success(), "{}", cmd);
+        };
+        assert_eq!(get_operation_in_progress(&ws_path), None);
+
+        repo.start_conflicted_merge("theirs", "README");
+        let operation = get_operation_in_progress(&ws_path).unwrap();
+        assert_eq!(operation, OperationInProgress::Merge);
+        run(operation.abort_cmd());
+        assert_eq!(get_operation_in_progress(&ws_path), None);
+
+        assert!(!repo.git_output(&["merge", "theirs"]).status.success());
+        repo.write_file("README", "resolved\n");
+        repo.git(&["add", "README"]);
+        run(operation.continue_cmd().unwrap());
+        assert_eq!(get_operation_in_progress(&ws_path), None);
+
+        assert!(!repo.git_output(&["cherry-pick", "theirs"]).status.success());
+        let operation = get_operation_in_progress(&ws_path).unwrap();
+        assert_eq!(operation, OperationInProgress::CherryPick);
+        run(operation.abort_cmd());
+        assert_eq!(get_operation_in_progress(&ws_path), None);
+
+        assert!(!repo.git_output(&["cherry-pick", "theirs"]).status.success());
+        run(operation.skip_cmd().unwrap());
+        assert_eq!(get_operation_in_progress(&ws_path), None);
+        assert_eq!(OperationInProgress::Merge.skip_cmd(), None);
+
+        repo.git(&["bisect", "start"]);
+        let operation = get_operation_in_progress(&ws_path).unwrap();
+        assert_eq!(operation, OperationInProgress::Bisect);
+        assert_eq!(operation.continue_cmd(), None);
+        assert_eq!(operation.skip_cmd(), None);
+        run(operation.abort_cmd());
+        assert_eq!(get_operation_in_progress(&ws_path), None);
+    }
+
+    #[test]
+    fn mailbox_application_is_told_from_rebase() {
+        let repo = ScratchRepo::new();
+        let ws_path = repo.ws_path();
+        repo.git(&["checkout", "-q", "-b", "topic"]);
+        repo.commit_file("README", "topic\n", "Topic change");
+        repo.checkout("master");
+        repo.commit_file("README", "master\n", "Master change");
+        let patch = repo.git(&["format-patch", "--stdout", "master..topic"]);
+        repo.write_file("topic.mbox", &patch);
+
+        assert!(!repo.git_output(&["am", "topic.mbox"]).status.success());
+        assert_eq!(
+            get_operation_in_progress(&ws_path),
+            Some(OperationInProgress::ApplyMailbox)
+        );
+        let git_repo = git2::Repository::open(ws_path.path()).unwrap();
+        assert_eq!(
+            get_repo_op_condns(&git_repo) & (SAV_REBASING | SAV_APPLYING_MAILBOX),
+            SAV_APPLYING_MAILBOX
+        );
+        repo.git(&["am", "--abort"]);
+
+        repo.checkout("topic");
+        assert!(!repo
+            .git_output(&["rebase", "--apply", "master"])
+            .status
+            .success());
+        assert_eq!(
+            get_operation_in_progress(&ws_path),
+            Some(OperationInProgress::Rebase)
+        );
+        assert_eq!(
+            get_repo_op_condns(&git_repo) & (SAV_REBASING | SAV_APPLYING_MAILBOX),
+            SAV_REBASING
+        );
+    }
+
+    #[test]
+    fn repo_state_condns_are_distinct() {
+        assert_eq!(SAV_REPO_STATE_MASK.count_ones(), 26);
+        assert_eq!(SAV_REPO_STATE_MASK & SAV_NEXT_REPO_CONDN, 0);
+        assert!(SAV_NEXT_REPO_CONDN > SAV_REPO_STATE_MASK);
     }
 }
@@ -14,11 +14,9 @@
 
 use std::cell::{Cell, Ref, RefCell};
 use std::io::Write;
-use std::process::Command;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
-use regex::Regex;
 
 use cub_diff_lib::diff::DiffPlusParser;
 use cub_diff_lib::lines::*;
@@ -39,9 +37,13 @@ use pw_gix::{
     wrapper::*,
 };
 
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::stashes::parse_stashes;
+use gwsm_git_core::workspace::WsPath;
+
 use crate::action_icons;
 use crate::config;
-use crate::events;
+use crate::events::{self, Event, Events};
 use crate::exec::ExecConsole;
 use crate::repos;
 
@@ -158,33 +160,35 @@ impl StashPushButton {
         let result = dialog.run();
         dialog.hide();
         if result == gtk::ResponseType::Ok {
-            let mut cmd = "git stash push".to_string();
-            if stash_push_widget.keep_index_ch_btn.get_active() {
-                cmd.push_str(" --keep-index");
-            }
-            if stash_push_widget.include_untracked_ch_btn.get_active() {
-                cmd.push_str(" --include-untracked");
-            }
-            if stash_push_widget.all_ch_btn.get_active() {
-                cmd.push_str(" --all");
-            }
+            let mut cmd = GitCommand::new("stash")
+                .arg("push")
+                .arg_if(
+                    stash_push_widget.keep_index_ch_btn.get_active(),
+                    "--keep-index",
+                )
+                .arg_if(
+                    stash_push_widget.include_untracked_ch_btn.get_active(),
+                    "--include-untracked",
+                )
+                .arg_if(stash_push_widget.all_ch_btn.get_active(), "--all");
             if let Some(text) = stash_push_widget.get_message() {
-                cmd.push_str(&format!(" -m {}", shlex::quote(&text)));
+                cmd = cmd.arg("-m").arg(text);
             }
             let cursor = self.show_busy();
             let result = self
                 .exec_console
-                .exec_cmd(&cmd, events::EV_STASHES_CHANGE + events::EV_FILES_CHANGE);
+                .exec_cmd(&cmd, events::EV_STASHES_CHANGE | events::EV_FILES_CHANGE);
             self.unshow_busy(cursor);
-            self.report_any_command_problems(&cmd, &result);
+            self.report_any_command_problems(&cmd.to_string(), &result);
         }
         unsafe { dialog.destroy() };
     }
 }
 
-fn get_raw_data() -> (String, Vec<u8>) {
+fn get_raw_data(ws_path: &WsPath) -> (String, Vec<u8>) {
     let mut hasher = Hasher::new(Algorithm::SHA256);
-    let output = Command::new("git")
+    let output = ws_path
+        .git()
         .arg("stash")
         .arg("list")
         .output()
@@ -198,20 +202,17 @@ fn get_raw_data() -> (String, Vec<u8>) {
     (text, hasher.finish())
 }
 
-lazy_static! {
-    static ref STASH_RE: Regex =
-        Regex::new(r"^(stash@\{\d+\}):\s*([^:]+):(.*)").expect("STASH regex creation failed");
-}
-
 struct StashesRowBuffer {
     row_buffer_core: Rc<RefCell<RowBufferCore<String>>>,
+    ws_path: WsPath,
 }
 
 impl StashesRowBuffer {
-    fn new() -> Self {
+    fn new(ws_path: &WsPath) -> Self {
         let core = RowBufferCore::<String>::default();
         let buffer = Self {
             row_buffer_core: Rc::new(RefCell::new(core)),
+            ws_path: ws_path.clone(),
         };
         buffer.init();
         buffer
@@ -224,23 +225,23 @@ impl RowBuffer<String> for StashesRowBuffer {
     }
 
     fn set_raw_data(&self) {
-        let (raw_data, digest) = get_raw_data();
+        let (raw_data, digest) = get_raw_data(&self.ws_path);
         let mut core = self.row_buffer_core.borrow_mut();
         core.set_raw_data(raw_data, digest);
     }
 
     fn finalise(&self) {
         let mut core = self.row_buffer_core.borrow_mut();
-        let mut rows: Vec<Row> = Vec::new();
-        for line in core.raw_data.lines() {
-            let captures = STASH_RE.captures(line).unwrap();
-            let row = vec![
-                captures.get(1).unwrap().as_str().to_value(),
-                captures.get(2).unwrap().as_str().to_value(),
-                captures.get(3).unwrap().as_str().to_value(),
-            ];
-            rows.push(row);
-        }
+        let rows: Vec<Row> = parse_stashes(&core.raw_data)
+            .iter()
+            .map(|stash| {
+                vec![
+                    stash.name.to_value(),
+                    stash.branch.to_value(),
+                    stash.commit.to_value(),
+                ]
+            })
+            .collect();
         core.rows = Rc::new(rows);
         core.set_is_finalised_true();
     }
@@ -262,10 +263,10 @@ impl BufferedUpdate<String, gtk::ListStore> for StashesNameListStore {
 }
 
 impl StashesNameListStore {
-    pub fn new() -> StashesNameListStore {
+    pub fn new(ws_path: &WsPath) -> StashesNameListStore {
         Self {
             list_store: gtk::ListStore::new(&[glib::Type::String; 3]),
-            stashes_row_buffer: Rc::new(RefCell::new(StashesRowBuffer::new())),
+            stashes_row_buffer: Rc::new(RefCell::new(StashesRowBuffer::new(ws_path))),
         }
     }
 }
@@ -301,7 +302,7 @@ impl MapManagedUpdate<StashesNameListStore, String, gtk::ListStore> for StashesN
 
 impl StashesNameTable {
     pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<StashesNameTable> {
-        let list_store = RefCell::new(StashesNameListStore::new());
+        let list_store = RefCell::new(StashesNameListStore::new(&exec_console.ws_path));
 
         let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
         view.set_headers_visible(true);
@@ -411,7 +412,9 @@ impl StashesNameTable {
             )
             .connect_activate(move |_| {
                 if let Some(stash) = table_clone.get_chosen_stash() {
-                    if let Some(text) = get_stash_diff_text(&stash) {
+                    if let Some(text) =
+                        get_stash_diff_text(&table_clone.exec_console.ws_path, &stash)
+                    {
                         let lines = Lines::from_string(&text);
                         let diff_plus_parser = DiffPlusParser::new();
                         match diff_plus_parser.parse_lines(&lines) {
@@ -419,7 +422,10 @@ impl StashesNameTable {
                                 let diff_notebook = DiffPlusNotebook::new(1);
                                 diff_notebook.repopulate(diff_pluses);
                                 let subtitle = format!("diff: {stash}");
-                                let title = config::window_title(Some(&subtitle));
+                                let title = config::window_title(
+                                    &table_clone.exec_console.ws_path,
+                                    Some(&subtitle),
+                                );
                                 let dialog = table_clone
                                     .new_dialog_builder()
                                     .title(&title)
@@ -471,7 +477,8 @@ impl StashesNameTable {
             .connect_activate(move |_| {
                 if let Some(stash) = table_clone.get_chosen_stash() {
                     let subtitle = format!("Pop Stash: {stash}");
-                    let title = config::window_title(Some(&subtitle));
+                    let title =
+                        config::window_title(&table_clone.exec_console.ws_path, Some(&subtitle));
                     let dialog = table_clone
                         .new_dialog_builder()
                         .title(&title)
@@ -488,17 +495,16 @@ impl StashesNameTable {
                     let result = dialog.run();
                     dialog.hide();
                     if result == gtk::ResponseType::Ok {
-                        let cmd = if index_ch_btn.get_active() {
-                            format!("git stash pop --index {}", shlex::quote(&stash))
-                        } else {
-                            format!("git stash pop {}", shlex::quote(&stash))
-                        };
+                        let cmd = GitCommand::new("stash")
+                            .arg("pop")
+                            .arg_if(index_ch_btn.get_active(), "--index")
+                            .arg(&stash);
                         let cursor = table_clone.show_busy();
                         let result = table_clone
                             .exec_console
                             .exec_cmd(&cmd, events::EV_STASHES_CHANGE | events::EV_FILES_CHANGE);
                         table_clone.unshow_busy(cursor);
-                        table_clone.report_any_command_problems(&cmd, &result);
+                        table_clone.report_any_command_problems(&cmd.to_string(), &result);
                     }
                     unsafe { dialog.destroy() };
                 }
@@ -520,7 +526,8 @@ impl StashesNameTable {
             .connect_activate(move |_| {
                 if let Some(stash) = table_clone.get_chosen_stash() {
                     let subtitle = format!("Apply Stash: {stash}");
-                    let title = config::window_title(Some(&subtitle));
+                    let title =
+                        config::window_title(&table_clone.exec_console.ws_path, Some(&subtitle));
                     let dialog = table_clone
                         .new_dialog_builder()
                         .title(&title)
@@ -537,17 +544,16 @@ impl StashesNameTable {
                     let result = dialog.run();
                     dialog.hide();
                     if result == gtk::ResponseType::Ok {
-                        let cmd = if index_ch_btn.get_active() {
-                            format!("git stash apply --index {}", shlex::quote(&stash))
-                        } else {
-                            format!("git stash apply {}", shlex::quote(&stash))
-                        };
+                        let cmd = GitCommand::new("stash")
+                            .arg("apply")
+                            .arg_if(index_ch_btn.get_active(), "--index")
+                            .arg(&stash);
                         let cursor = table_clone.show_busy();
                         let result = table_clone
                             .exec_console
                             .exec_cmd(&cmd, events::EV_STASHES_CHANGE | events::EV_FILES_CHANGE);
                         table_clone.unshow_busy(cursor);
-                        table_clone.report_any_command_problems(&cmd, &result);
+                        table_clone.report_any_command_problems(&cmd.to_string(), &result);
                     }
                     unsafe { dialog.destroy() };
                 }
@@ -571,20 +577,18 @@ impl StashesNameTable {
                     let (response, name) = table_clone.ask_string_cancel_or_ok("Branch Name:");
                     if response == gtk::ResponseType::Ok {
                         if let Some(branch) = name {
-                            let cmd = format!(
-                                "git stash branch {} {}",
-                                shlex::quote(&branch),
-                                shlex::quote(&stash)
-                            );
+                            let cmd = GitCommand::new("stash")
+                                .arg("branch")
+                                .arg(&branch)
+                                .arg(&stash);
                             let cursor = table_clone.show_busy();
                             let result = table_clone.exec_console.exec_cmd(
                                 &cmd,
-                                events::EV_STASHES_CHANGE
-                                    | events::EV_FILES_CHANGE
-                                    | events::EV_BRANCHES_CHANGE,
+                                Events::from(events::EV_STASHES_CHANGE | events::EV_FILES_CHANGE)
+                                    .with(Event::BranchesChange(Some(branch.clone()))),
                             );
                             table_clone.unshow_busy(cursor);
-                            table_clone.report_any_command_problems(&cmd, &result);
+                            table_clone.report_any_command_problems(&cmd.to_string(), &result);
                         }
                     }
                 }
@@ -606,7 +610,7 @@ impl StashesNameTable {
             )
             .connect_activate(move |_| {
                 if let Some(stash) = table_clone.get_chosen_stash() {
-                    let cmd = format!("git stash drop {}", shlex::quote(&stash));
+                    let cmd = GitCommand::new("stash").arg("drop").arg(&stash);
                     let msg = format!("Confirm: {cmd}");
                     if table_clone.ask_confirm_action(&msg, None) {
                         let cursor = table_clone.show_busy();
@@ -614,7 +618,7 @@ impl StashesNameTable {
                             .exec_console
                             .exec_cmd(&cmd, events::EV_STASHES_CHANGE);
                         table_clone.unshow_busy(cursor);
-                        table_clone.report_any_command_problems(&cmd, &result);
+                        table_clone.report_any_command_problems(&cmd.to_string(), &result);
                     }
                 }
             });
@@ -641,8 +645,9 @@ impl StashesNameTable {
     }
 }
 
-fn get_stash_diff_text(stash_name: &str) -> Option<String> {
-    let output = Command::new("git")
+fn get_stash_diff_text(ws_path: &WsPath, stash_name: &str) -> Option<String> {
+    let output = ws_path
+        .git()
         .arg("stash")
         .arg("show")
         .arg("-p")
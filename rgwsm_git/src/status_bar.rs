@@ -0,0 +1,206 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+
+use xml::escape;
+
+use pw_gix::{
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use gwsm_git_core::status::{self, StatusCounts};
+use gwsm_git_core::workspace::WsPath;
+
+use crate::events;
+use crate::exec::ExecConsole;
+use crate::repos;
+use crate::ws_file_tree::{self, GitWsFsTree};
+
+#[derive(Debug, Default, PartialEq)]
+struct BranchInfo {
+    oid: Option<String>,
+    head: Option<String>,
+    upstream: Option<String>,
+    ahead_behind: Option<(usize, usize)>,
+}
+
+impl BranchInfo {
+    fn head_description(&self) -> String {
+        match (self.head.as_deref(), self.oid.as_deref()) {
+            (Some("(detached)"), Some(oid)) => format!("detached at {}", &oid[..oid.len().min(8)]),
+            (Some(head), Some("(initial)")) => format!("{} (no commits)", head),
+            (Some(head), _) => head.to_string(),
+            (None, _) => "".to_string(),
+        }
+    }
+}
+
+// Extract the "# branch.*" headers from "git status --porcelain=v2 --branch -z" output
+fn parse_branch_headers(text: &str) -> BranchInfo {
+    let mut info = BranchInfo::default();
+    for record in text.split('\0') {
+        if !record.starts_with("# branch.") {
+            continue;
+        }
+        let fields: Vec<&str> = record[9..].split(' ').collect();
+        match fields[0] {
+            "oid" if fields.len() > 1 => info.oid = Some(fields[1].to_string()),
+            "head" if fields.len() > 1 => info.head = Some(fields[1].to_string()),
+            "upstream" if fields.len() > 1 => info.upstream = Some(fields[1].to_string()),
+            "ab" if fields.len() > 2 => {
+                let ahead = fields[1].trim_start_matches('+').parse::<usize>();
+                let behind = fields[2].trim_start_matches('-').parse::<usize>();
+                if let (Ok(ahead), Ok(behind)) = (ahead, behind) {
+                    info.ahead_behind = Some((ahead, behind));
+                }
+            }
+            _ => (),
+        }
+    }
+    info
+}
+
+#[derive(PWO, Wrapper)]
+pub struct GitStatusBar {
+    h_box: gtk::Box,
+    head_label: gtk::Label,
+    upstream_label: gtk::Label,
+    operation_label: gtk::Label,
+    counts_label: gtk::Label,
+    ws_path: WsPath,
+    ws_file_tree: Rc<GitWsFsTree>,
+}
+
+// The status comes from the work tree's latest snapshot rather than another
+// "git status" so the bar has to be created after (and hence be notified
+// after) the tree.
+impl GitStatusBar {
+    pub fn new(exec_console: &Rc<ExecConsole>, ws_file_tree: &Rc<GitWsFsTree>) -> Rc<Self> {
+        let h_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        let head_label = gtk::Label::new(None);
+        head_label.set_tooltip_text(Some("Current branch (or detached HEAD commit)"));
+        let upstream_label = gtk::Label::new(None);
+        upstream_label.set_tooltip_text(Some("Upstream branch and commits ahead/behind"));
+        let operation_label = gtk::Label::new(None);
+        let counts_label = gtk::Label::new(None);
+        counts_label.set_tooltip_text(Some(
+            "Staged, unstaged, untracked and conflicted file counts",
+        ));
+        h_box.pack_start(&head_label, false, false, 2);
+        h_box.pack_start(
+            &gtk::Separator::new(gtk::Orientation::Vertical),
+            false,
+            false,
+            1,
+        );
+        h_box.pack_start(&upstream_label, false, false, 2);
+        h_box.pack_start(&operation_label, false, false, 2);
+        h_box.pack_end(&counts_label, false, false, 2);
+        h_box.show_all();
+
+        let sb = Rc::new(Self {
+            h_box,
+            head_label,
+            upstream_label,
+            operation_label,
+            counts_label,
+            ws_path: exec_console.ws_path.clone(),
+            ws_file_tree: Rc::clone(ws_file_tree),
+        });
+        sb.update();
+
+        let sb_clone = Rc::clone(&sb);
+        exec_console.event_notifier.add_notification_cb(
+            ws_file_tree::WS_FILE_TREE_EVENTS | events::EV_CHANGE_DIR,
+            Box::new(move |_| sb_clone.update()),
+        );
+
+        sb
+    }
+
+    fn update(&self) {
+        let text = self.ws_file_tree.status_text();
+        if !text.is_empty() {
+            let info = parse_branch_headers(&text);
+            self.head_label.set_markup(&format!(
+                "<b>{}</b>",
+                escape::escape_str_pcdata(&info.head_description())
+            ));
+            let upstream = match (&info.upstream, info.ahead_behind) {
+                (Some(upstream), Some((ahead, behind))) => {
+                    format!("{} \u{2191}{} \u{2193}{}", upstream, ahead, behind)
+                }
+                (Some(upstream), None) => format!("{} (gone)", upstream),
+                (None, _) => "no upstream".to_string(),
+            };
+            self.upstream_label.set_text(&upstream);
+            self.set_counts(status::status_counts_from_text(&text));
+        } else {
+            self.head_label.set_text("not in a repository");
+            self.upstream_label.set_text("");
+            self.counts_label.set_text("");
+        }
+        if let Some(operation) = repos::get_operation_in_progress(&self.ws_path) {
+            self.operation_label.set_markup(&format!(
+                "<span foreground=\"red\">{} in progress</span>",
+                operation.name()
+            ));
+        } else {
+            self.operation_label.set_text("");
+        }
+    }
+
+    fn set_counts(&self, counts: StatusCounts) {
+        let mut text = format!(
+            "staged: {}  unstaged: {}  untracked: {}",
+            counts.staged, counts.unstaged, counts.untracked
+        );
+        if counts.conflicted > 0 {
+            text.push_str(&format!("  conflicted: {}", counts.conflicted));
+        }
+        self.counts_label.set_text(&text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_branch_headers_works() {
+        let text = "# branch.oid 3b18e512dba79e4c8300dd08aeb37f8e728b8dad\0\
+                    # branch.head master\0\
+                    # branch.upstream origin/master\0\
+                    # branch.ab +2 -3\0\
+                    1 .M N... 100644 100644 100644 aaaa aaaa src/main.rs\0";
+        let info = parse_branch_headers(text);
+        assert_eq!(info.head_description(), "master");
+        assert_eq!(info.upstream, Some("origin/master".to_string()));
+        assert_eq!(info.ahead_behind, Some((2, 3)));
+
+        let text = "# branch.oid 3b18e512dba79e4c8300dd08aeb37f8e728b8dad\0\
+                    # branch.head (detached)\0";
+        let info = parse_branch_headers(text);
+        assert_eq!(info.head_description(), "detached at 3b18e512");
+        assert_eq!(info.upstream, None);
+
+        let text = "# branch.oid (initial)\0# branch.head main\0";
+        assert_eq!(
+            parse_branch_headers(text).head_description(),
+            "main (no commits)"
+        );
+    }
+}
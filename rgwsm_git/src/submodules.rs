@@ -13,7 +13,6 @@
 // limitations under the License.
 
 use std::io::Write;
-use std::process::Command;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
@@ -27,29 +26,32 @@ use pw_gix::{
 
 use pw_pathux::str_path::*;
 
+use gwsm_git_core::workspace::WsPath;
+
 use crate::action_icons;
 use crate::exec::ExecConsole;
 use crate::repos;
 
-pub fn is_git_submodule(dir_path: Option<&str>) -> bool {
+pub fn is_git_submodule(ws_path: &WsPath, dir_path: Option<&str>) -> bool {
     if let Some(dir_path) = dir_path {
-        dir_path.path_join(".git").path_is_file()
+        ws_path.join(dir_path).path_join(".git").path_is_file()
     } else {
-        ".git".path_is_file()
+        ws_path.join(".git").path_is_file()
     }
 }
 
-fn find_submodule_parent() -> Option<String> {
-    assert!(is_git_submodule(None));
-    if let Ok(repo) = git2::Repository::discover("..") {
+fn find_submodule_parent(ws_path: &WsPath) -> Option<String> {
+    assert!(is_git_submodule(ws_path, None));
+    if let Ok(repo) = git2::Repository::discover(ws_path.join("..")) {
         repo.workdir().map(|wd| wd.to_string_lossy().to_string())
     } else {
         None
     }
 }
 
-pub fn submodule_count() -> usize {
-    let output = Command::new("git")
+pub fn submodule_count(ws_path: &WsPath) -> usize {
+    let output = ws_path
+        .git()
         .arg("submodule")
         .arg("status")
         .arg("--recursive")
@@ -65,9 +67,10 @@ pub fn submodule_count() -> usize {
     }
 }
 
-fn _get_submodule_paths_rawdata() -> (String, Vec<u8>) {
+fn _get_submodule_paths_rawdata(ws_path: &WsPath) -> (String, Vec<u8>) {
     let mut hasher = Hasher::new(Algorithm::SHA256);
-    let output = Command::new("git")
+    let output = ws_path
+        .git()
         .arg("submodule")
         .arg("status")
         .arg("--recursive")
@@ -112,7 +115,7 @@ impl SubmoduleParentButton {
 
         let bb_clone = Rc::clone(&bb);
         bb.button.connect_clicked(move |_| {
-            if let Some(parent_dir) = find_submodule_parent() {
+            if let Some(parent_dir) = find_submodule_parent(&bb_clone.exec_console.ws_path) {
                 bb_clone.exec_console.chdir(&parent_dir)
             }
         });
@@ -16,11 +16,10 @@ use std::cell::{Cell, Ref, RefCell};
 use std::error::Error;
 use std::fmt;
 use std::io::{self, Write};
-use std::process::{self, Command};
+use std::process;
 use std::rc::Rc;
 
 use crypto_hash::{Algorithm, Hasher};
-use regex::Regex;
 
 use pw_gix::{
     glib,
@@ -38,9 +37,14 @@ use pw_gix::{
     wrapper::*,
 };
 
+use gwsm_git_core::git_command::GitCommand;
+use gwsm_git_core::tags::{parse_tags, Tag, TAG_FORMAT};
+use gwsm_git_core::workspace::WsPath;
+
 use crate::action_icons;
-use crate::events;
+use crate::events::{self, Event, Events};
 use crate::exec::ExecConsole;
+use crate::list_view;
 use crate::message;
 use crate::repos;
 
@@ -94,7 +98,7 @@ impl NewTagWidget {
             sign_cbtn: gtk::CheckButton::with_label("--sign"),
             key_cbtn: gtk::CheckButton::with_label("--local-user"),
             key_id_entry: LabelledTextEntry::new("Key Id: "),
-            message: message::MessageWidget::new("Message"),
+            message: message::MessageWidget::new("Message", &exec_console.ws_path),
             exec_console: Rc::clone(exec_console),
         });
         ntw.key_id_entry.pwo().set_sensitive(false);
@@ -160,47 +164,41 @@ impl NewTagWidget {
     pub fn apply(
         &self,
         target: Option<&str>,
-    ) -> Result<(String, io::Result<process::Output>), MissingTagComponentError> {
+    ) -> Result<(GitCommand, io::Result<process::Output>), MissingTagComponentError> {
         let tag_name = self.tag_name_entry.entry().get_text();
         if tag_name.len() > 0 {
-            let mut cmd = "git tag ".to_string();
-            if self.force_cbth.get_active() {
-                cmd.push_str("--force ")
-            }
+            let mut cmd = GitCommand::new("tag").arg_if(self.force_cbth.get_active(), "--force");
             let mut annotate = false;
             if self.annotate_cbtn.get_active() {
                 annotate = true;
-                cmd.push_str("--annotate ");
+                cmd = cmd.arg("--annotate");
             } else if self.sign_cbtn.get_active() {
                 annotate = true;
-                cmd.push_str("--sign ");
+                cmd = cmd.arg("--sign");
             } else if self.key_cbtn.get_active() {
                 annotate = true;
                 let key_id = self.key_id_entry.entry().get_text();
                 if key_id.len() > 0 {
-                    cmd.push_str("--local-user ");
-                    cmd.push_str(&shlex::quote(&key_id));
-                    cmd.push(' ');
+                    cmd = cmd.arg("--local-user").arg(key_id);
                 } else {
                     return Err(MissingTagComponentError::KeyId);
                 }
             }
             if annotate {
                 if let Some(msg) = self.message.get_message() {
-                    cmd.push_str("-m ");
-                    cmd.push_str(&shlex::quote(&msg));
-                    cmd.push(' ');
+                    cmd = cmd.arg("-m").arg(msg);
                 } else {
                     return Err(MissingTagComponentError::AnnotationMessage);
                 }
             }
-            cmd.push_str(&shlex::quote(&tag_name));
+            cmd = cmd.arg(&tag_name);
             if let Some(target) = target {
-                cmd.push(' ');
-                cmd.push_str(&shlex::quote(target));
+                cmd = cmd.arg(target);
             };
             let old_cursor = self.show_busy();
-            let result = self.exec_console.exec_cmd(&cmd, events::EV_TAGS_CHANGE);
+            let result = self
+                .exec_console
+                .exec_cmd(&cmd, Event::TagsChange(Some(tag_name.to_string())));
             self.unshow_busy(old_cursor);
             Ok((cmd, result))
         } else {
@@ -234,7 +232,7 @@ pub trait CreatTag: WidgetWrapper {
             if result == gtk::ResponseType::Ok {
                 match ntw.apply(target) {
                     Ok((cmd, result)) => {
-                        self.report_any_command_problems(&cmd, &result);
+                        self.report_any_command_problems(&cmd.to_string(), &result);
                         if let Ok(ref output) = result {
                             if output.status.success() {
                                 break;
@@ -291,11 +289,12 @@ impl TagButton {
 
 // Tag table
 
-fn get_raw_data() -> (String, Vec<u8>) {
+fn get_raw_data(ws_path: &WsPath) -> (String, Vec<u8>) {
     let mut hasher = Hasher::new(Algorithm::SHA256);
-    let output = Command::new("git")
+    let output = ws_path
+        .git()
         .arg("tag")
-        .arg("--format=%(refname:short) %(objectname:short) %(subject)")
+        .arg(TAG_FORMAT)
         .output()
         .expect("getting tags text failed");
     let text: String = if output.status.success() {
@@ -307,33 +306,27 @@ fn get_raw_data() -> (String, Vec<u8>) {
     (text, hasher.finish())
 }
 
-lazy_static! {
-    static ref TAGS_RE: Regex =
-        Regex::new(r"(\S+)\s+([a-fA-F0-9]{7}[a-fA-F0-9]*)?\s*([^\s].*)").unwrap();
-}
-
-fn extract_tag_row(line: &str) -> Row {
-    let captures = TAGS_RE.captures(line).unwrap();
-    let name = captures.get(1).unwrap().as_str();
-    let rev = captures.get(2).unwrap().as_str();
-    let synopsis = captures.get(3).unwrap().as_str();
+fn tag_row(tag: &Tag) -> Row {
+    let name = &tag.name;
     let mut v = vec![];
     v.push(name.to_value());
     v.push(format!("<b>{name}</b>").to_value());
-    v.push(rev.to_value());
-    v.push(synopsis.to_value());
+    v.push(tag.rev.to_value());
+    v.push(tag.synopsis.to_value());
     v
 }
 
 struct TagsRowBuffer {
     row_buffer_core: Rc<RefCell<RowBufferCore<String>>>,
+    ws_path: WsPath,
 }
 
 impl TagsRowBuffer {
-    fn new() -> Self {
+    fn new(ws_path: &WsPath) -> Self {
         let core = RowBufferCore::<String>::default();
         let buffer = Self {
             row_buffer_core: Rc::new(RefCell::new(core)),
+            ws_path: ws_path.clone(),
         };
         buffer.init();
         buffer
@@ -346,17 +339,14 @@ impl RowBuffer<String> for TagsRowBuffer {
     }
 
     fn set_raw_data(&self) {
-        let (raw_data, digest) = get_raw_data();
+        let (raw_data, digest) = get_raw_data(&self.ws_path);
         let mut core = self.row_buffer_core.borrow_mut();
         core.set_raw_data(raw_data, digest);
     }
 
     fn finalise(&self) {
         let mut core = self.row_buffer_core.borrow_mut();
-        let mut rows: Vec<Row> = Vec::new();
-        for line in core.raw_data.lines() {
-            rows.push(extract_tag_row(line))
-        }
+        let rows: Vec<Row> = parse_tags(&core.raw_data).iter().map(tag_row).collect();
         core.rows = Rc::new(rows);
         core.set_is_finalised_true();
     }
@@ -378,10 +368,10 @@ impl BufferedUpdate<String, gtk::ListStore> for TagsNameListStore {
 }
 
 impl TagsNameListStore {
-    pub fn new() -> TagsNameListStore {
+    pub fn new(ws_path: &WsPath) -> TagsNameListStore {
         Self {
             list_store: gtk::ListStore::new(&[glib::Type::String; 4]),
-            tags_row_buffer: Rc::new(RefCell::new(TagsRowBuffer::new())),
+            tags_row_buffer: Rc::new(RefCell::new(TagsRowBuffer::new(ws_path))),
         }
     }
 }
@@ -423,7 +413,7 @@ impl MapManagedUpdate<TagsNameListStore, String, gtk::ListStore> for TagsNameTab
 
 impl TagsNameTable {
     pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<TagsNameTable> {
-        let list_store = RefCell::new(TagsNameListStore::new());
+        let list_store = RefCell::new(TagsNameListStore::new(&exec_console.ws_path));
 
         let view = gtk::TreeView::with_model(&list_store.borrow().get_list_store());
         view.set_headers_visible(true);
@@ -495,10 +485,16 @@ impl TagsNameTable {
         table.exec_console.event_notifier.add_notification_cb(
             events::EV_AUTO_UPDATE
                 | events::EV_BRANCHES_CHANGE
+                | events::EV_TAGS_CHANGE
                 | events::EV_COMMIT
                 | events::EV_PULL
                 | events::EV_PUSH,
-            Box::new(move |_| table_clone.auto_update()),
+            Box::new(move |events| {
+                table_clone.auto_update();
+                if let Some(tag) = events.ref_names().first() {
+                    table_clone.select_tag(tag);
+                }
+            }),
         );
         let table_clone = Rc::clone(&table);
         table.view.connect_map(move |_| table_clone.on_map_action());
@@ -529,11 +525,13 @@ impl TagsNameTable {
                     table_clone.hovered_tag.borrow().clone()
                 };
                 if let Some(tag) = tag {
-                    let cmd = format!("git checkout {}", shlex::quote(&tag));
-                    let result = table_clone
-                        .exec_console
-                        .exec_cmd(&cmd, events::EV_BRANCHES_CHANGE | events::EV_CHECKOUT);
-                    table_clone.report_any_command_problems(&cmd, &result);
+                    let cmd = GitCommand::new("checkout").arg(&tag);
+                    let result = table_clone.exec_console.exec_cmd(
+                        &cmd,
+                        Events::from(events::EV_BRANCHES_CHANGE)
+                            .with(Event::Checkout(Some(tag.clone()))),
+                    );
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
                 }
             });
 
@@ -578,11 +576,11 @@ impl TagsNameTable {
                     table_clone.hovered_tag.borrow().clone()
                 };
                 if let Some(tag) = tag {
-                    let cmd = format!("git tag -d {}", shlex::quote(&tag));
+                    let cmd = GitCommand::new("tag").arg("-d").arg(&tag);
                     let result = table_clone
                         .exec_console
-                        .exec_cmd(&cmd, events::EV_TAGS_CHANGE);
-                    table_clone.report_any_command_problems(&cmd, &result);
+                        .exec_cmd(&cmd, Event::TagsChange(Some(tag.clone())));
+                    table_clone.report_any_command_problems(&cmd.to_string(), &result);
                 }
             });
 
@@ -611,4 +609,12 @@ impl TagsNameTable {
         self.popup_menu.update_condns(condns);
         *self.hovered_tag.borrow_mut() = tag;
     }
+
+    // Select (and reveal) the named tag if it's in the table
+    fn select_tag(&self, tag: &str) {
+        let store = self.list_store.borrow().get_list_store();
+        if let Some(iter) = list_view::find_row(&store, 0, tag) {
+            list_view::select_and_show(&self.view, &iter);
+        }
+    }
 }
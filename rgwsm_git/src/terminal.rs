@@ -0,0 +1,196 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::env;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use pw_gix::{
+    gio, glib,
+    gtk::{self, prelude::*},
+    wrapper::*,
+};
+
+use vte::{TerminalExt, TerminalExtManual};
+
+use crate::events;
+use crate::exec::ExecConsole;
+
+#[cfg(target_family = "unix")]
+const DEFAULT_SHELL: &str = "/bin/sh";
+#[cfg(target_os = "windows")]
+const DEFAULT_SHELL: &str = "cmd.exe";
+
+// Have the shell report its working directory (OSC 7) each time it prompts
+// so that we know when a command typed into the terminal has finished.
+// NB: only bash honours PROMPT_COMMAND.  zsh and fish hooks (precmd and
+// fish_prompt) can't be installed via the environment so, for those shells,
+// the effects of typed commands are left to the repository watcher.
+const PROMPT_COMMAND: &str = r#"PROMPT_COMMAND=printf '\033]7;file://%s%s\007' "$HOSTNAME" "$PWD""#;
+
+// How often to check whether a busy shell has become free to change directory
+const FOLLOW_CWD_RETRY_MS: u32 = 500;
+
+// A shell that keeps exiting this soon after starting (e.g. a bad $SHELL or a
+// broken rc file) is not restarted again.
+const RAPID_EXIT: Duration = Duration::from_secs(2);
+const MAX_RAPID_EXITS: u32 = 3;
+
+fn user_shell() -> String {
+    if let Ok(shell) = env::var("SHELL") {
+        shell
+    } else {
+        DEFAULT_SHELL.to_string()
+    }
+}
+
+#[derive(PWO, Wrapper)]
+pub struct GitTerminal {
+    scrolled_window: gtk::ScrolledWindow,
+    terminal: vte::Terminal,
+    exec_console: Rc<ExecConsole>,
+    shell_pid: Cell<Option<glib::Pid>>,
+    spawned_at: Cell<Option<Instant>>,
+    rapid_exits: Cell<u32>,
+    follow_pending: Cell<bool>,
+}
+
+impl GitTerminal {
+    pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<Self> {
+        let adj: Option<&gtk::Adjustment> = None;
+        let gt = Rc::new(Self {
+            scrolled_window: gtk::ScrolledWindow::new(adj, adj),
+            terminal: vte::Terminal::new(),
+            exec_console: Rc::clone(exec_console),
+            shell_pid: Cell::new(None),
+            spawned_at: Cell::new(None),
+            rapid_exits: Cell::new(0),
+            follow_pending: Cell::new(false),
+        });
+        gt.scrolled_window
+            .set_policy(gtk::PolicyType::Automatic, gtk::PolicyType::Always);
+        gt.scrolled_window.add(&gt.terminal);
+        gt.scrolled_window.show_all();
+
+        gt.spawn_shell();
+
+        let gt_clone = Rc::clone(&gt);
+        gt.terminal
+            .connect_child_exited(move |_, _| gt_clone.shell_exited());
+
+        let gt_clone = Rc::clone(&gt);
+        gt.terminal.connect_current_directory_uri_changed(move |_| {
+            gt_clone
+                .exec_console
+                .event_notifier
+                .notify_events(events::EV_AUTO_UPDATE)
+        });
+
+        let gt_clone = Rc::clone(&gt);
+        gt.exec_console.event_notifier.add_notification_cb(
+            events::EV_CHANGE_DIR,
+            Box::new(move |_| gt_clone.follow_cwd()),
+        );
+
+        gt
+    }
+
+    fn spawn_shell(&self) {
+        let curr_dir = self.exec_console.ws_path.path_string();
+        let shell = user_shell();
+        let argv = [Path::new(&shell)];
+        let envv = [Path::new(PROMPT_COMMAND)];
+        match self.terminal.spawn_sync(
+            vte::PtyFlags::DEFAULT,
+            Some(&curr_dir),
+            &argv,
+            &envv,
+            glib::SpawnFlags::DEFAULT,
+            Some(&mut || {}),
+            None::<&gio::Cancellable>,
+        ) {
+            Ok(pid) => {
+                self.shell_pid.set(Some(pid));
+                self.spawned_at.set(Some(Instant::now()));
+            }
+            Err(err) => {
+                self.shell_pid.set(None);
+                let msg = format!("{}: failed to start terminal shell", shlex::quote(&shell));
+                self.report_error(&msg, &err);
+            }
+        }
+    }
+
+    fn shell_exited(&self) {
+        self.shell_pid.set(None);
+        let rapid = self
+            .spawned_at
+            .get()
+            .map_or(true, |spawned_at| spawned_at.elapsed() < RAPID_EXIT);
+        let rapid_exits = if rapid { self.rapid_exits.get() + 1 } else { 0 };
+        self.rapid_exits.set(rapid_exits);
+        if rapid_exits < MAX_RAPID_EXITS {
+            self.spawn_shell();
+        } else {
+            let msg = format!(
+                "{}: terminal shell keeps exiting so it has not been restarted",
+                shlex::quote(&user_shell())
+            );
+            self.inform_user(&msg, Some("Check $SHELL and the shell's start up files."));
+        }
+    }
+
+    // The shell is idle (i.e. at its prompt) if it owns the terminal's foreground
+    fn shell_is_idle(&self, pid: glib::Pid) -> bool {
+        match self.terminal.get_pty() {
+            Some(pty) => unsafe { libc::tcgetpgrp(pty.get_fd()) == pid.0 },
+            None => false,
+        }
+    }
+
+    // Typing "cd" while a command is running would feed it to that command so
+    // wait until the shell is back at its prompt.
+    fn follow_cwd(self: &Rc<Self>) {
+        if self.cd_if_idle() || self.follow_pending.replace(true) {
+            return;
+        }
+        let gt_weak = Rc::downgrade(self);
+        gtk::timeout_add(FOLLOW_CWD_RETRY_MS, move || match gt_weak.upgrade() {
+            Some(gt) if !gt.cd_if_idle() => glib::Continue(true),
+            _ => glib::Continue(false),
+        });
+    }
+
+    // The leading space keeps the "cd" out of the shell's history (where that
+    // is configured, e.g. bash's HISTCONTROL=ignorespace).
+    fn cd_if_idle(&self) -> bool {
+        match self.shell_pid.get() {
+            Some(pid) if self.shell_is_idle(pid) => {
+                let curr_dir = self.exec_console.ws_path.path_string();
+                let cmd = format!(" cd {}\n", shlex::quote(&curr_dir));
+                self.terminal.feed_child(cmd.as_bytes());
+            }
+            Some(_) => return false,
+            // a shell that's given up restarting gets another chance here
+            None => {
+                self.rapid_exits.set(0);
+                self.spawn_shell();
+            }
+        }
+        self.follow_pending.set(false);
+        true
+    }
+}
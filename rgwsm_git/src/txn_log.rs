@@ -0,0 +1,360 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crypto_hash::Algorithm;
+
+use serde::{Deserialize, Serialize};
+
+use gwsm_git_core::git_command::{self, GitCommand, ScriptStep};
+
+use crate::config;
+
+// Limits on the size of a repository's log.  Each stream of a command's output
+// is logged up to MAX_LOGGED_OUTPUT bytes and when the file grows beyond
+// MAX_LOG_BYTES its oldest entries are dropped to get it down to half that.
+const MAX_LOGGED_OUTPUT: usize = 64 * 1024;
+const MAX_LOG_BYTES: u64 = 4 * 1024 * 1024;
+
+fn capped_output(text: &str) -> String {
+    if text.len() <= MAX_LOGGED_OUTPUT {
+        return text.to_string();
+    }
+    let mut end = MAX_LOGGED_OUTPUT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!(
+        "{}\n[{} more bytes not logged]\n",
+        &text[..end],
+        text.len() - end
+    )
+}
+
+// A record of a command run by the ExecConsole.  The log for each repository is
+// kept in a file (one JSON encoded entry per line) in the config directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub session: String,
+    pub timestamp: String,
+    pub cwd: String,
+    pub cmd: String,
+    #[serde(default)]
+    pub argv: Vec<String>,
+    pub exit_code: Option<i32>, // None if the command couldn't be run or was killed
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutcomeFilter {
+    All,
+    Succeeded,
+    Failed,
+}
+
+impl LogEntry {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    // Case insensitive search of the command line and its output
+    pub fn matches(&self, search: &str, outcome: OutcomeFilter) -> bool {
+        let outcome_ok = match outcome {
+            OutcomeFilter::All => true,
+            OutcomeFilter::Succeeded => self.succeeded(),
+            OutcomeFilter::Failed => !self.succeeded(),
+        };
+        if !outcome_ok {
+            return false;
+        }
+        if search.is_empty() {
+            return true;
+        }
+        let search = search.to_lowercase();
+        [&self.cmd, &self.stdout, &self.stderr]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&search))
+    }
+
+    // The entry as it's written to the log file
+    pub fn capped(&self) -> LogEntry {
+        LogEntry {
+            stdout: capped_output(&self.stdout),
+            stderr: capped_output(&self.stderr),
+            ..self.clone()
+        }
+    }
+
+    pub fn output(&self) -> String {
+        format!("{}{}", self.stdout, self.stderr)
+    }
+
+    // The command line (re)built from the argument vector so that arguments
+    // containing newlines stay quoted.  Entries logged before argument vectors
+    // were recorded fall back to splitting the display string.
+    fn git_command(&self) -> Option<GitCommand> {
+        if self.argv.is_empty() {
+            GitCommand::from_argv(&shlex::split(&self.cmd)?)
+        } else {
+            GitCommand::from_argv(&self.argv)
+        }
+    }
+
+    pub fn outcome_description(&self) -> String {
+        match self.exit_code {
+            Some(code) => format!("exit {}, {}ms", code, self.duration_ms),
+            None => format!("failed, {}ms", self.duration_ms),
+        }
+    }
+}
+
+// A shell script that replays the git commands in "entries" (in their original
+// directories).  Commands that failed are included as comments (every line of
+// which is commented out).
+pub fn session_script(session: &str, entries: &[LogEntry]) -> String {
+    let steps: Vec<ScriptStep> = entries
+        .iter()
+        .filter_map(|entry| {
+            entry.git_command().map(|cmd| ScriptStep {
+                cwd: entry.cwd.clone(),
+                cmd,
+                failure: if entry.succeeded() {
+                    None
+                } else {
+                    Some(entry.outcome_description())
+                },
+            })
+        })
+        .collect();
+    let heading = format!(
+        "git commands run by {} in session {}",
+        config::APP_NAME,
+        session
+    );
+    git_command::replay_script(&heading, &steps)
+}
+
+fn txn_logs_dir_path() -> PathBuf {
+    let mut pathbuf = config::get_config_dir_path();
+    pathbuf.push("txn_logs");
+    pathbuf
+}
+
+// Use the repo's directory name for readability and a digest of its full path
+// to distinguish repositories with the same name.
+fn log_file_name(repo_dir: &str) -> String {
+    let name = Path::new(repo_dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    let digest = crypto_hash::hex_digest(Algorithm::SHA256, repo_dir.as_bytes());
+    format!("{}-{}.log", name, &digest[..16])
+}
+
+pub fn log_file_path(repo_dir: &str) -> PathBuf {
+    let mut pathbuf = txn_logs_dir_path();
+    pathbuf.push(log_file_name(repo_dir.trim_end_matches('/')));
+    pathbuf
+}
+
+// Returns true if the log had to be trimmed (i.e. older entries were dropped)
+pub fn append_entry(log_path: &Path, entry: &LogEntry) -> io::Result<bool> {
+    if let Some(dir_path) = log_path.parent() {
+        fs::create_dir_all(dir_path)?;
+    }
+    let mut line = serde_json::to_string(&entry.capped())?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)?;
+    file.write_all(line.as_bytes())?;
+    if file.metadata()?.len() > MAX_LOG_BYTES {
+        trim_log(log_path, MAX_LOG_BYTES / 2)?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+// Keep the most recent entries that fit in "keep_bytes".  The trimmed log is
+// renamed into place so that a failure can't lose the whole log.
+fn trim_log(log_path: &Path, keep_bytes: u64) -> io::Result<()> {
+    let text = fs::read_to_string(log_path)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut first = lines.len();
+    let mut kept = 0;
+    while first > 0 && kept + lines[first - 1].len() as u64 + 1 <= keep_bytes {
+        first -= 1;
+        kept += lines[first].len() as u64 + 1;
+    }
+    let mut trimmed = String::new();
+    for line in lines[first..].iter() {
+        trimmed.push_str(line);
+        trimmed.push('\n');
+    }
+    let tmp_path = log_path.with_extension("log.tmp");
+    fs::write(&tmp_path, trimmed)?;
+    fs::rename(&tmp_path, log_path)
+}
+
+// Entries that can't be decoded (e.g. a partially written line) are skipped
+pub fn entries_from_reader<R: BufRead>(reader: R) -> Vec<LogEntry> {
+    reader
+        .lines()
+        .filter_map(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+pub fn read_entries(log_path: &Path) -> io::Result<Vec<LogEntry>> {
+    let file = File::open(log_path)?;
+    Ok(entries_from_reader(BufReader::new(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cmd: &str, exit_code: Option<i32>, stderr: &str) -> LogEntry {
+        LogEntry {
+            session: "s1".to_string(),
+            timestamp: "2019-06-01-10-00-00".to_string(),
+            cwd: "/work/repo".to_string(),
+            cmd: cmd.to_string(),
+            argv: shlex::split(cmd).unwrap(),
+            exit_code,
+            duration_ms: 12,
+            stdout: "".to_string(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let entries = vec![
+            entry("git status", Some(0), ""),
+            entry("git push", Some(1), "rejected\nnon-fast-forward\n"),
+        ];
+        let mut text = String::new();
+        for entry in entries.iter() {
+            text += &serde_json::to_string(entry).unwrap();
+            text.push('\n');
+        }
+        text += "{\"session\": \"trunc";
+        assert_eq!(entries_from_reader(text.as_bytes()), entries);
+    }
+
+    #[test]
+    fn capped_works() {
+        let mut big = entry("git log", Some(0), "");
+        big.stdout = "é".repeat(MAX_LOGGED_OUTPUT);
+        let capped = big.capped();
+        assert!(capped.stdout.starts_with(&big.stdout[..MAX_LOGGED_OUTPUT]));
+        assert!(capped.stdout.ends_with(&format!(
+            "\n[{} more bytes not logged]\n",
+            MAX_LOGGED_OUTPUT
+        )));
+        assert_eq!(capped.stderr, "");
+        let small = entry("git status", Some(0), "oops");
+        assert_eq!(small.capped(), small);
+    }
+
+    #[test]
+    fn trim_log_keeps_newest_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("repo.log");
+        let entries: Vec<LogEntry> = (0..10)
+            .map(|i| entry(&format!("git tag v{}", i), Some(0), ""))
+            .collect();
+        for entry in entries.iter() {
+            assert!(!append_entry(&log_path, entry).unwrap());
+        }
+        let line_len = serde_json::to_string(&entries[9]).unwrap().len() as u64 + 1;
+        trim_log(&log_path, line_len * 3 + 1).unwrap();
+        assert_eq!(read_entries(&log_path).unwrap(), entries[7..].to_vec());
+        assert!(!dir.path().join("repo.log.tmp").exists());
+    }
+
+    #[test]
+    fn entry_matches_works() {
+        let ok = entry("git status", Some(0), "");
+        let failed = entry("git push origin", Some(1), "Rejected");
+        let killed = entry("git fetch", None, "");
+        assert!(ok.matches("", OutcomeFilter::All));
+        assert!(ok.matches("STATUS", OutcomeFilter::Succeeded));
+        assert!(!ok.matches("", OutcomeFilter::Failed));
+        assert!(failed.matches("rejected", OutcomeFilter::Failed));
+        assert!(!failed.matches("status", OutcomeFilter::All));
+        assert!(killed.matches("fetch", OutcomeFilter::Failed));
+    }
+
+    #[test]
+    fn session_script_works() {
+        let mut entries = vec![
+            entry("git add src/main.rs", Some(0), ""),
+            entry("meld .", Some(0), ""),
+            entry("git push", Some(1), "rejected"),
+            entry("git status", Some(0), ""),
+        ];
+        entries[3].cwd = "/work/my repo".to_string();
+        assert_eq!(
+            session_script("s1", &entries),
+            "#!/bin/sh\n\
+             # git commands run by rgwsm_git in session s1\n\
+             cd /work/repo || exit 1\n\
+             git add src/main.rs\n\
+             # [exit 1, 12ms] git push\n\
+             cd '/work/my repo' || exit 1\n\
+             git status\n"
+        );
+    }
+
+    #[test]
+    fn session_script_comments_out_all_of_failed_commands() {
+        let mut entries = vec![
+            entry("git commit -m x", Some(1), "nothing to commit"),
+            entry("git commit -m y", Some(0), ""),
+        ];
+        for entry in entries.iter_mut() {
+            entry.argv = vec!["git", "commit", "-m", "a\nrm -rf b"]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect();
+        }
+        assert_eq!(
+            session_script("s1", &entries),
+            "#!/bin/sh\n\
+             # git commands run by rgwsm_git in session s1\n\
+             cd /work/repo || exit 1\n\
+             # [exit 1, 12ms] git commit -m 'a\n\
+             # rm -rf b'\n\
+             git commit -m 'a\n\
+             rm -rf b'\n"
+        );
+    }
+
+    #[test]
+    fn log_file_name_works() {
+        let name = log_file_name("/work/repo");
+        assert!(name.starts_with("repo-"));
+        assert!(name.ends_with(".log"));
+        assert_ne!(name, log_file_name("/other/repo"));
+    }
+}
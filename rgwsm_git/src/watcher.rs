@@ -0,0 +1,504 @@
+// Copyright 2019 Peter Williams <pwil3058@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//    http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use pw_gix::{glib, gtk};
+
+use gwsm_git_core::workspace::WsPath;
+
+use crate::events::{self, Event, EventSet, Events};
+use crate::exec::ExecConsole;
+
+// How long the file system must be quiet before changes are reported
+const DEBOUNCE_MS: u64 = 300;
+// How often the GUI thread checks for reported changes
+const DRAIN_INTERVAL_MS: u32 = 200;
+// Changes reported this soon after a command has notified its events are
+// assumed to be its own
+const SETTLE_MS: u64 = DEBOUNCE_MS + DRAIN_INTERVAL_MS as u64;
+
+fn path_names(rel_path: &Path) -> Vec<String> {
+    rel_path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect()
+}
+
+// Map a path within the common git directory (which is shared by all of a
+// repository's work trees) to the events that a change to it implies
+fn common_dir_names_events(names: &[&str]) -> EventSet {
+    match names {
+        ["packed-refs"] => events::EV_BRANCHES_CHANGE | events::EV_TAGS_CHANGE,
+        ["refs", "heads", ..] | ["refs", "remotes", ..] => events::EV_BRANCHES_CHANGE,
+        ["refs", "tags", ..] => events::EV_TAGS_CHANGE,
+        ["refs", "stash"] | ["logs", "refs", "stash"] => events::EV_STASHES_CHANGE,
+        _ => events::EV_NONE,
+    }
+}
+
+// Map a path within the work tree's own git directory to the events that a
+// change to it implies
+fn git_dir_names_events(names: &[&str]) -> EventSet {
+    match names {
+        ["index"] => events::EV_FILES_CHANGE,
+        ["HEAD"] => events::EV_BRANCHES_CHANGE | events::EV_FILES_CHANGE,
+        ["MERGE_HEAD"]
+        | ["CHERRY_PICK_HEAD"]
+        | ["REVERT_HEAD"]
+        | ["BISECT_LOG"]
+        | ["rebase-merge", ..]
+        | ["rebase-apply", ..] => events::EV_FILES_CHANGE,
+        _ => common_dir_names_events(names),
+    }
+}
+
+// In a linked work tree the git directory (".git/worktrees/<name>" in the
+// main repository) is within the common directory so it's checked first.
+fn path_events(path: &Path, git_dir: &Path, common_dir: &Path) -> EventSet {
+    let (rel_path, names_events): (&Path, fn(&[&str]) -> EventSet) =
+        if let Ok(rel_path) = path.strip_prefix(git_dir) {
+            (rel_path, git_dir_names_events)
+        } else if let Ok(rel_path) = path.strip_prefix(common_dir) {
+            (rel_path, common_dir_names_events)
+        } else {
+            return events::EV_FILES_CHANGE;
+        };
+    let components = path_names(rel_path);
+    let names: Vec<&str> = components.iter().map(|s| s.as_str()).collect();
+    if names.last().map_or(false, |name| name.ends_with(".lock")) {
+        // git renames the lock file into place so we'll see the real change
+        return events::EV_NONE;
+    }
+    names_events(&names)
+}
+
+// Changes in the git directory (e.g. the index) can't be attributed to
+// particular files so the paths are only reported if all changes are in the
+// work tree.
+#[derive(Debug, Default)]
+struct Changes {
+    event_set: EventSet,
+    paths: Vec<String>,
+    paths_known: bool,
+}
+
+impl Changes {
+    fn new() -> Self {
+        Self {
+            paths_known: true,
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, path: &Path, git_dir: &Path, common_dir: &Path, work_dir: &Path) {
+        let event_set = path_events(path, git_dir, common_dir);
+        if event_set.intersects(events::EV_FILES_CHANGE) {
+            match path.strip_prefix(work_dir) {
+                Ok(rel_path) if !path.starts_with(git_dir) && !path.starts_with(common_dir) => {
+                    let rel_path = rel_path.to_string_lossy().to_string();
+                    if !self.paths.contains(&rel_path) {
+                        self.paths.push(rel_path);
+                    }
+                }
+                _ => self.paths_known = false,
+            }
+        }
+        self.event_set |= event_set;
+    }
+
+    fn merge(&mut self, other: Changes) {
+        self.event_set |= other.event_set;
+        for path in other.paths {
+            if !self.paths.contains(&path) {
+                self.paths.push(path);
+            }
+        }
+        self.paths_known &= other.paths_known;
+    }
+
+    fn events(self) -> Events {
+        if self.paths_known && !self.paths.is_empty() {
+            Events::from(self.event_set.without(events::EV_FILES_CHANGE))
+                .with(Event::FilesChange(self.paths))
+        } else {
+            Events::from(self.event_set)
+        }
+    }
+}
+
+// Ignored directories (e.g. build output) aren't watched so that their churn
+// neither wastes watches nor triggers pointless updates.  The work tree is
+// watched one directory at a time so that they can be left out.  In a linked
+// work tree, branches, tags and stashes live in the common directory.
+struct Watch {
+    watcher: RecommendedWatcher,
+    receiver: Receiver<DebouncedEvent>,
+    repo: git2::Repository,
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    work_dir: PathBuf,
+}
+
+impl Watch {
+    fn new(ws_path: &WsPath) -> Option<Self> {
+        let repo = git2::Repository::discover(ws_path.path()).ok()?;
+        let git_dir = repo.path().canonicalize().ok()?;
+        let common_dir = repo.commondir().canonicalize().ok()?;
+        let work_dir = repo.workdir()?.canonicalize().ok()?;
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::watcher(sender, Duration::from_millis(DEBOUNCE_MS)).ok()?;
+        let mut watch = Self {
+            watcher,
+            receiver,
+            repo,
+            git_dir,
+            common_dir,
+            work_dir,
+        };
+        watch
+            .watcher
+            .watch(&watch.common_dir, RecursiveMode::Recursive)
+            .ok()?;
+        if !watch.git_dir.starts_with(&watch.common_dir) {
+            watch
+                .watcher
+                .watch(&watch.git_dir, RecursiveMode::Recursive)
+                .ok()?;
+        }
+        let work_dir = watch.work_dir.clone();
+        watch.watch_dir_tree(&work_dir).ok()?;
+        Some(watch)
+    }
+
+    fn is_in_git_dirs(&self, path: &Path) -> bool {
+        path.starts_with(&self.git_dir) || path.starts_with(&self.common_dir)
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        match path.strip_prefix(&self.work_dir) {
+            Ok(rel_path) if !self.is_in_git_dirs(path) => {
+                self.repo.status_should_ignore(rel_path).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+
+    fn watch_dir_tree(&mut self, dir: &Path) -> notify::Result<()> {
+        self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                if entry.file_type().map_or(false, |t| t.is_dir())
+                    && !self.is_in_git_dirs(&path)
+                    && !self.is_ignored(&path)
+                {
+                    self.watch_dir_tree(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // New directories need watches of their own.  Failure is tolerated as
+    // the directory may already have gone again.
+    fn note_new_path(&mut self, path: &Path) {
+        if path.is_dir() && !self.is_in_git_dirs(path) {
+            let _ = self.watch_dir_tree(path);
+        }
+    }
+
+    // Collect the events implied by all changes reported so far. "None" means
+    // that the watch has failed and polling should take over.
+    fn drain(&mut self) -> Option<Changes> {
+        let mut changes = Changes::new();
+        let reported: Vec<DebouncedEvent> = self.receiver.try_iter().collect();
+        for event in reported {
+            match event {
+                DebouncedEvent::Create(ref path)
+                | DebouncedEvent::Write(ref path)
+                | DebouncedEvent::Chmod(ref path)
+                | DebouncedEvent::Remove(ref path) => {
+                    if self.is_ignored(path) {
+                        continue;
+                    }
+                    if let DebouncedEvent::Create(_) = event {
+                        self.note_new_path(path);
+                    }
+                    changes.add(path, &self.git_dir, &self.common_dir, &self.work_dir);
+                }
+                DebouncedEvent::Rename(ref from, ref to) => {
+                    if !self.is_ignored(from) {
+                        changes.add(from, &self.git_dir, &self.common_dir, &self.work_dir);
+                    }
+                    if !self.is_ignored(to) {
+                        self.note_new_path(to);
+                        changes.add(to, &self.git_dir, &self.common_dir, &self.work_dir);
+                    }
+                }
+                DebouncedEvent::Rescan => {
+                    changes.event_set |= events::EV_AUTO_UPDATE;
+                }
+                DebouncedEvent::Error(_, _) => return None,
+                DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => (),
+            }
+        }
+        Some(changes)
+    }
+}
+
+// Watches the workspace's work tree and its git directory and translates changes
+// into the corresponding events.  Automatic polling is suspended while the
+// watch is working and resumes if it can't be established or fails.  The
+// watcher stops (and unsubscribes) when its owner drops it.
+pub struct RepoWatcher {
+    exec_console: Rc<ExecConsole>,
+    watch: RefCell<Option<Watch>>,
+    pending: RefCell<Changes>,
+    change_dir_cb_token: Cell<Option<u64>>,
+}
+
+impl RepoWatcher {
+    pub fn new(exec_console: &Rc<ExecConsole>) -> Rc<Self> {
+        let rw = Rc::new(Self {
+            exec_console: Rc::clone(exec_console),
+            watch: RefCell::new(None),
+            pending: RefCell::new(Changes::new()),
+            change_dir_cb_token: Cell::new(None),
+        });
+        rw.rewatch();
+
+        let rw_weak = Rc::downgrade(&rw);
+        let token = exec_console.event_notifier.add_notification_cb(
+            events::EV_CHANGE_DIR,
+            Box::new(move |_| {
+                if let Some(rw) = rw_weak.upgrade() {
+                    rw.rewatch()
+                }
+            }),
+        );
+        rw.change_dir_cb_token.set(Some(token));
+
+        let rw_weak = Rc::downgrade(&rw);
+        gtk::timeout_add(DRAIN_INTERVAL_MS, move || {
+            if let Some(rw) = rw_weak.upgrade() {
+                rw.deliver_events();
+                glib::Continue(true)
+            } else {
+                glib::Continue(false)
+            }
+        });
+
+        rw
+    }
+
+    fn rewatch(&self) {
+        let watch = Watch::new(&self.exec_console.ws_path);
+        self.exec_console.set_polling_required(watch.is_none());
+        // any held changes were relative to the old work tree
+        *self.pending.borrow_mut() = Changes::new();
+        *self.watch.borrow_mut() = watch;
+    }
+
+    // Changes are held while a command runs.  If it succeeds, it notifies its
+    // own events so they (and any reported just after) are dropped, otherwise
+    // (e.g. a merge that stops with conflicts) they're delivered.  They're
+    // always drained so that any new directories get watched.
+    fn deliver_events(&self) {
+        let drained = self.watch.borrow_mut().as_mut().map(|watch| watch.drain());
+        match drained {
+            None => (),
+            Some(Some(changes)) => {
+                self.pending.borrow_mut().merge(changes);
+                if self.exec_console.is_running() {
+                    return;
+                }
+                let changes = self.pending.replace(Changes::new());
+                let settle = Duration::from_millis(SETTLE_MS);
+                if changes.event_set.is_empty() || self.exec_console.cmd_notified_within(settle) {
+                    return;
+                }
+                self.exec_console.check_repo_states();
+                self.exec_console
+                    .event_notifier
+                    .notify_events(changes.events());
+            }
+            Some(None) => {
+                *self.watch.borrow_mut() = None;
+                self.exec_console.set_polling_required(true);
+            }
+        }
+    }
+}
+
+impl Drop for RepoWatcher {
+    fn drop(&mut self) {
+        if let Some(token) = self.change_dir_cb_token.take() {
+            self.exec_console.event_notifier.del_notification_cb(token);
+        }
+        // polling has to take over from the watch
+        self.exec_console.set_polling_required(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_events_works() {
+        let git_dir = Path::new("/work/.git");
+        assert_eq!(
+            path_events(Path::new("/work/src/main.rs"), git_dir, git_dir),
+            events::EV_FILES_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/work/.git/index"), git_dir, git_dir),
+            events::EV_FILES_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/work/.git/index.lock"), git_dir, git_dir),
+            events::EV_NONE
+        );
+        assert_eq!(
+            path_events(
+                Path::new("/work/.git/refs/heads/topic/one"),
+                git_dir,
+                git_dir
+            ),
+            events::EV_BRANCHES_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/work/.git/refs/tags/v1.0"), git_dir, git_dir),
+            events::EV_TAGS_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/work/.git/logs/refs/stash"), git_dir, git_dir),
+            events::EV_STASHES_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/work/.git/objects/ab/cdef"), git_dir, git_dir),
+            events::EV_NONE
+        );
+    }
+
+    #[test]
+    fn linked_work_tree_path_events_works() {
+        let common_dir = Path::new("/main/.git");
+        let git_dir = Path::new("/main/.git/worktrees/linked");
+        assert_eq!(
+            path_events(
+                Path::new("/main/.git/worktrees/linked/index"),
+                git_dir,
+                common_dir
+            ),
+            events::EV_FILES_CHANGE
+        );
+        assert_eq!(
+            path_events(
+                Path::new("/main/.git/refs/heads/topic"),
+                git_dir,
+                common_dir
+            ),
+            events::EV_BRANCHES_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/main/.git/packed-refs"), git_dir, common_dir),
+            events::EV_BRANCHES_CHANGE | events::EV_TAGS_CHANGE
+        );
+        assert_eq!(
+            path_events(Path::new("/main/.git/logs/refs/stash"), git_dir, common_dir),
+            events::EV_STASHES_CHANGE
+        );
+        // the main work tree's (and other linked work trees') own files
+        assert_eq!(
+            path_events(Path::new("/main/.git/index"), git_dir, common_dir),
+            events::EV_NONE
+        );
+        assert_eq!(
+            path_events(Path::new("/main/.git/HEAD"), git_dir, common_dir),
+            events::EV_NONE
+        );
+        assert_eq!(
+            path_events(
+                Path::new("/main/.git/worktrees/other/index"),
+                git_dir,
+                common_dir
+            ),
+            events::EV_NONE
+        );
+    }
+
+    #[test]
+    fn changes_events_works() {
+        let git_dir = Path::new("/work/.git");
+        let work_dir = Path::new("/work");
+        let mut changes = Changes::new();
+        changes.add(Path::new("/work/src/main.rs"), git_dir, git_dir, work_dir);
+        changes.add(
+            Path::new("/work/.git/refs/heads/topic"),
+            git_dir,
+            git_dir,
+            work_dir,
+        );
+        changes.add(Path::new("/work/src/main.rs"), git_dir, git_dir, work_dir);
+        assert_eq!(
+            changes.events(),
+            Events::from(events::EV_BRANCHES_CHANGE)
+                .with(Event::FilesChange(vec!["src/main.rs".to_string()]))
+        );
+        let mut changes = Changes::new();
+        changes.add(Path::new("/work/src/main.rs"), git_dir, git_dir, work_dir);
+        changes.add(Path::new("/work/.git/index"), git_dir, git_dir, work_dir);
+        assert_eq!(changes.events(), Events::from(events::EV_FILES_CHANGE));
+    }
+
+    #[test]
+    fn changes_merge_works() {
+        let git_dir = Path::new("/work/.git");
+        let work_dir = Path::new("/work");
+        let mut changes = Changes::new();
+        changes.add(Path::new("/work/a.rs"), git_dir, git_dir, work_dir);
+        let mut more = Changes::new();
+        more.add(Path::new("/work/a.rs"), git_dir, git_dir, work_dir);
+        more.add(Path::new("/work/b.rs"), git_dir, git_dir, work_dir);
+        more.add(
+            Path::new("/work/.git/refs/tags/v1"),
+            git_dir,
+            git_dir,
+            work_dir,
+        );
+        changes.merge(more);
+        assert_eq!(
+            changes.events(),
+            Events::from(events::EV_TAGS_CHANGE).with(Event::FilesChange(vec![
+                "a.rs".to_string(),
+                "b.rs".to_string()
+            ]))
+        );
+        let mut changes = Changes::new();
+        changes.add(Path::new("/work/a.rs"), git_dir, git_dir, work_dir);
+        let mut more = Changes::new();
+        more.add(Path::new("/work/.git/index"), git_dir, git_dir, work_dir);
+        changes.merge(more);
+        assert_eq!(changes.events(), Events::from(events::EV_FILES_CHANGE));
+    }
+}
@@ -13,8 +13,8 @@
 //limitations under the License.
 
 use std::cell::RefCell;
+use std::fs;
 use std::marker::PhantomData;
-use std::process::Command;
 use std::rc::Rc;
 
 use pw_gix::{
@@ -28,14 +28,33 @@ use pw_gix::{
 
 use pw_pathux::str_path::*;
 
+use gwsm_git_core::files;
+use gwsm_git_core::status;
+
 //use crate::action_icons;
 use crate::edit;
-use crate::events;
+use crate::events::{self, Event, EventSet};
 use crate::exec;
-use crate::fs_db::{self, GitFsDb, ScmFsoData};
+use crate::fs_db::{self, GitFsDb, ScmFsoData, WsFsDbIfce};
 use crate::repos;
 use crate::submodules;
 
+// Branch changes are included because they change the status' branch headers
+pub const WS_FILE_TREE_EVENTS: EventSet = events::EV_AUTO_UPDATE
+    .union(events::EV_CHECKOUT)
+    .union(events::EV_FILES_CHANGE)
+    .union(events::EV_COMMIT)
+    .union(events::EV_PULL)
+    .union(events::EV_PUSH)
+    .union(events::EV_BRANCHES_CHANGE);
+
+const SAV_SELN_HAS_WD_CHANGES: u64 = repos::SAV_NEXT_REPO_CONDN;
+const SAV_SELN_IS_TRACKED: u64 = SAV_SELN_HAS_WD_CHANGES << 1;
+const SAV_SELN_IS_IN_INDEX: u64 = SAV_SELN_HAS_WD_CHANGES << 2;
+const SAV_SELN_IS_IN_WD: u64 = SAV_SELN_HAS_WD_CHANGES << 3;
+const SAV_SELN_STATUS_MASK: u64 =
+    SAV_SELN_HAS_WD_CHANGES | SAV_SELN_IS_TRACKED | SAV_SELN_IS_IN_INDEX | SAV_SELN_IS_IN_WD;
+
 #[derive(PWO, Wrapper)]
 pub struct GenWsFsTree<FSDB, FSOI>
 where
@@ -51,12 +70,19 @@ where
     hide_clean: gtk::CheckButton,
     exec_console: Rc<exec::ExecConsole>,
     popup_menu: ManagedMenu,
-    hovered_fso_path: RefCell<Option<String>>,
+    hovered_fso: RefCell<Option<(String, String)>>,
     phantom: PhantomData<FSOI>,
 }
 
 pub type GitWsFsTree = GenWsFsTree<GitFsDb<ScmFsoData>, ScmFsoData>;
 
+impl GitWsFsTree {
+    // The status data (with branch headers) that the tree was last updated from
+    pub fn status_text(&self) -> Rc<String> {
+        self.fs_db.snapshot_text()
+    }
+}
+
 impl<FSDB, FSOI> FileTreeIfce<FSDB, FSOI> for GenWsFsTree<FSDB, FSOI>
 where
     FSDB: FsDbIfce<FSOI> + 'static,
@@ -89,7 +115,7 @@ where
 
 impl<FSDB, FSOI> GenWsFsTree<FSDB, FSOI>
 where
-    FSDB: FsDbIfce<FSOI> + 'static,
+    FSDB: FsDbIfce<FSOI> + WsFsDbIfce + 'static,
     FSOI: FsObjectIfce + 'static,
 {
     pub fn new(exec_console: &Rc<exec::ExecConsole>, auto_expand: bool) -> Rc<Self> {
@@ -130,13 +156,13 @@ where
             v_box,
             view,
             store,
-            fs_db: FSDB::new(),
+            fs_db: FSDB::with_ws_path(&exec_console.ws_path),
             auto_expand,
             show_hidden,
             hide_clean,
             exec_console: Rc::clone(exec_console),
             popup_menu,
-            hovered_fso_path: RefCell::new(None),
+            hovered_fso: RefCell::new(None),
             phantom: PhantomData,
         });
 
@@ -161,20 +187,20 @@ where
 
         let owft_clone = Rc::clone(&owft);
         owft.exec_console.event_notifier.add_notification_cb(
-            events::EV_AUTO_UPDATE
-                | events::EV_CHECKOUT
-                | events::EV_FILES_CHANGE
-                | events::EV_COMMIT
-                | events::EV_PULL,
+            WS_FILE_TREE_EVENTS,
             Box::new(move |_| {
                 owft_clone.update();
+                owft_clone.notify_status_condns();
             }),
         );
 
         let owft_clone = Rc::clone(&owft);
         owft.exec_console.event_notifier.add_notification_cb(
             events::EV_CHANGE_DIR,
-            Box::new(move |_| owft_clone.repopulate()),
+            Box::new(move |_| {
+                owft_clone.repopulate();
+                owft_clone.notify_status_condns();
+            }),
         );
 
         let owft_clone = Rc::clone(&owft);
@@ -190,21 +216,129 @@ where
                 repos::SAV_IN_REPO + SAV_SELN_MADE_OR_HOVER_OK,
             )
             .connect_activate(move |_| {
-                if let Some(fso_paths) = owft_clone.get_chosen_file_paths_string() {
-                    let cmd = format!("git add {fso_paths}");
+                let fso_paths = owft_clone.get_chosen_fso_paths();
+                if !fso_paths.is_empty() {
+                    let cmd = files::add_cmd(&fso_paths);
+                    let result = owft_clone
+                        .exec_console
+                        .exec_cmd(&cmd, Event::FilesChange(fso_paths.clone()));
+                    owft_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let owft_clone = Rc::clone(&owft);
+        owft.popup_menu
+            .append_item(
+                "discard",
+                &(
+                    "Discard Changes",
+                    None,
+                    Some("Discard the working directory changes to the selected/indicated file(s)"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_MADE_OR_HOVER_OK + SAV_SELN_HAS_WD_CHANGES,
+            )
+            .connect_activate(move |_| {
+                let fso_paths = owft_clone.get_chosen_fso_paths();
+                if owft_clone.confirm_paths_action(
+                    "Discard working directory changes to:",
+                    &fso_paths,
+                    Some("The changes will be irretrievably lost."),
+                ) {
+                    let cmd = files::discard_changes_cmd(&fso_paths);
+                    let result = owft_clone
+                        .exec_console
+                        .exec_cmd(&cmd, Event::FilesChange(fso_paths.clone()));
+                    owft_clone.report_any_command_problems(&cmd.to_string(), &result);
+                }
+            });
+
+        let owft_clone = Rc::clone(&owft);
+        owft.popup_menu
+            .append_item(
+                "delete",
+                &(
+                    "Delete",
+                    None,
+                    Some("Delete the selected/indicated file(s) from the working directory"),
+                )
+                    .into(),
+                SAV_SELN_MADE_OR_HOVER_OK + SAV_SELN_IS_IN_WD,
+            )
+            .connect_activate(move |_| {
+                let fso_paths = owft_clone.get_chosen_fso_paths();
+                if owft_clone.confirm_paths_action(
+                    "Delete:",
+                    &fso_paths,
+                    Some("Untracked files and uncommitted changes will be irretrievably lost."),
+                ) {
+                    owft_clone.delete_fso_paths(&fso_paths);
+                }
+            });
+
+        let owft_clone = Rc::clone(&owft);
+        owft.popup_menu
+            .append_item(
+                "move",
+                &(
+                    "Rename/Move",
+                    None,
+                    Some("Rename/move the selected/indicated file using \"git mv\""),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_UNIQUE_OR_HOVER_OK + SAV_SELN_IS_TRACKED,
+            )
+            .connect_activate(move |_| {
+                let fso_paths = owft_clone.get_chosen_fso_paths();
+                if let Some(fso_path) = fso_paths.first() {
+                    let question = format!("Rename/move {} to:", fso_path);
+                    let (response, target) = owft_clone.ask_string_cancel_or_ok(&question);
+                    if response == gtk::ResponseType::Ok {
+                        if let Some(target) = target {
+                            let cmd = files::move_cmd(fso_path, &target);
+                            let result = owft_clone.exec_console.exec_cmd(
+                                &cmd,
+                                Event::FilesChange(vec![fso_path.to_string(), target]),
+                            );
+                            owft_clone.report_any_command_problems(&cmd.to_string(), &result);
+                        }
+                    }
+                }
+            });
+
+        let owft_clone = Rc::clone(&owft);
+        owft.popup_menu
+            .append_item(
+                "remove_from_index",
+                &(
+                    "Remove From Index",
+                    None,
+                    Some("Remove the selected/indicated file(s) from the index only"),
+                )
+                    .into(),
+                repos::SAV_IN_REPO + SAV_SELN_MADE_OR_HOVER_OK + SAV_SELN_IS_IN_INDEX,
+            )
+            .connect_activate(move |_| {
+                let fso_paths = owft_clone.get_chosen_fso_paths();
+                if owft_clone.confirm_paths_action("Remove from the index:", &fso_paths, None) {
+                    let cmd = files::remove_from_index_cmd(&fso_paths);
                     let result = owft_clone
                         .exec_console
-                        .exec_cmd(&cmd, events::EV_FILES_CHANGE);
-                    owft_clone.report_any_command_problems(&cmd, &result);
+                        .exec_cmd(&cmd, Event::FilesChange(fso_paths.clone()));
+                    owft_clone.report_any_command_problems(&cmd.to_string(), &result);
                 }
             });
 
         let owft_clone = owft.clone();
         owft.view.connect_button_press_event(move |view, event| {
             if event.get_button() == 3 {
-                let fso_path = get_row_item_for_event!(view, event, String, fs_db::PATH)
-                    .map(|fso_path| shlex::quote(&fso_path).to_string());
-                owft_clone.set_hovered_fso_path(fso_path);
+                let fso =
+                    get_row_item_for_event!(view, event, String, fs_db::PATH).map(|fso_path| {
+                        let status = get_row_item_for_event!(view, event, String, fs_db::STATUS)
+                            .unwrap_or_default();
+                        (fso_path, status)
+                    });
+                owft_clone.set_hovered_fso(fso);
                 owft_clone.popup_menu.popup_at_event(event);
                 return Inhibit(true);
             } else if event.get_button() == 2 {
@@ -221,17 +355,19 @@ where
                 if let Some(fso_path) =
                     get_row_item_for_tree_path!(view, tree_path, String, fs_db::PATH)
                 {
-                    if fso_path.path_is_dir() {
-                        if submodules::is_git_submodule(Some(&fso_path))
+                    let ws_path = &owft_clone.exec_console.ws_path;
+                    let abs_path = ws_path.join(&fso_path);
+                    if abs_path.path_is_dir() {
+                        if submodules::is_git_submodule(ws_path, Some(&fso_path))
                             || !owft_clone.exec_console.in_repo()
                         {
                             owft_clone.exec_console.chdir(&fso_path);
                         }
-                    } else if fso_path.path_is_file() {
+                    } else if abs_path.path_is_file() {
                         // this will cause deleted files to be ignored
                         match edit::get_assigned_editor(&fso_path) {
                             Ok(editor) => {
-                                if let Err(err) = Command::new(&editor).arg(&fso_path).spawn() {
+                                if let Err(err) = ws_path.command(&editor).arg(&fso_path).spawn() {
                                     let msg = format!(
                                         "{} {}: failed",
                                         shlex::quote(&editor),
@@ -250,47 +386,117 @@ where
             });
 
         owft.repopulate();
+        owft.notify_status_condns();
         owft.view.show_all();
         scrolled_window.show_all();
         owft.v_box.show_all();
         owft
     }
 
-    fn set_hovered_fso_path(&self, path: Option<String>) {
+    // The status that the tree has just fetched also determines whether the
+    // work tree is dirty or has conflicts (saving another scan of the files)
+    fn notify_status_condns(&self) {
+        if let Some(text) = self.fs_db.status_text() {
+            self.exec_console
+                .changed_condns_notifier
+                .notify_changed_condns(repos::get_status_condns(&text));
+        }
+    }
+
+    fn set_hovered_fso(&self, fso: Option<(String, String)>) {
         let condns = self
             .view
             .get_selection()
-            .get_masked_conditions_with_hover_ok(path.is_some());
+            .get_masked_conditions_with_hover_ok(fso.is_some());
         self.popup_menu.update_condns(condns);
-        *self.hovered_fso_path.borrow_mut() = path;
+        *self.hovered_fso.borrow_mut() = fso;
+        let statuses: Vec<String> = self
+            .get_chosen_fsos()
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect();
+        let mut condns = 0;
+        if !statuses.is_empty() {
+            if statuses.iter().all(|s| status::status_has_wd_changes(s)) {
+                condns |= SAV_SELN_HAS_WD_CHANGES;
+            }
+            if statuses.iter().all(|s| status::status_is_tracked(s)) {
+                condns |= SAV_SELN_IS_TRACKED;
+            }
+            if statuses.iter().all(|s| status::status_is_in_index(s)) {
+                condns |= SAV_SELN_IS_IN_INDEX;
+            }
+            if statuses.iter().all(|s| status::status_is_in_wd(s)) {
+                condns |= SAV_SELN_IS_IN_WD;
+            }
+        }
+        self.popup_menu.update_condns(MaskedCondns {
+            condns,
+            mask: SAV_SELN_STATUS_MASK,
+        });
     }
 
-    fn get_chosen_file_paths_string(&self) -> Option<String> {
+    // Paths and statuses of the selected rows or, if none, the hovered row
+    fn get_chosen_fsos(&self) -> Vec<(String, String)> {
         let selection = self.view.get_selection();
         let (tree_paths, store) = selection.get_selected_rows();
         if !tree_paths.is_empty() {
-            let mut count = 0;
-            let mut fso_paths = String::new();
+            let mut fsos = vec![];
             for tree_path in tree_paths.iter() {
                 if let Some(iter) = store.get_iter(tree_path) {
                     if let Some(fso_path) =
                         store.get_value(&iter, fs_db::PATH).get::<String>().unwrap()
                     {
-                        if count > 0 {
-                            fso_paths.push(' ');
-                        }
-                        count += 1;
-                        fso_paths.push_str(&shlex::quote(&fso_path));
+                        let status = store
+                            .get_value(&iter, fs_db::STATUS)
+                            .get::<String>()
+                            .unwrap()
+                            .unwrap_or_default();
+                        fsos.push((fso_path, status));
                     }
                 }
             }
-            if count > 0 {
-                Some(fso_paths)
+            fsos
+        } else if let Some(fso) = self.hovered_fso.borrow().clone() {
+            vec![fso]
+        } else {
+            vec![]
+        }
+    }
+
+    fn get_chosen_fso_paths(&self) -> Vec<String> {
+        self.get_chosen_fsos()
+            .into_iter()
+            .map(|(fso_path, _)| fso_path)
+            .collect()
+    }
+
+    fn confirm_paths_action(&self, msg: &str, fso_paths: &[String], warning: Option<&str>) -> bool {
+        if fso_paths.is_empty() {
+            return false;
+        }
+        let mut explanation = fso_paths.join("\n");
+        if let Some(warning) = warning {
+            explanation = format!("{}\n\n{}", explanation, warning);
+        }
+        self.ask_confirm_action(msg, Some(&explanation))
+    }
+
+    fn delete_fso_paths(&self, fso_paths: &[String]) {
+        for fso_path in fso_paths.iter() {
+            let abs_path = self.exec_console.ws_path.join(fso_path);
+            let result = if abs_path.path_is_dir() {
+                fs::remove_dir_all(&abs_path)
             } else {
-                None
+                fs::remove_file(&abs_path)
+            };
+            if let Err(err) = result {
+                let msg = format!("{}: delete failed", shlex::quote(fso_path));
+                self.report_error(&msg, &err);
             }
-        } else {
-            self.hovered_fso_path.borrow().clone()
         }
+        self.exec_console
+            .event_notifier
+            .notify_events(Event::FilesChange(fso_paths.to_vec()));
     }
 }